@@ -19,16 +19,20 @@ Circe. If not, see <https://www.gnu.org/licenses/>.
 */
 
 
-use std::io::prelude::*;
-use std::fs::File;
-use std::path::Path;
+use std::fs;
+use std::path::PathBuf;
+use std::io::{self, IsTerminal, Read};
 use std::process::exit;
 
-use clap::Parser as ClapParser;
+use clap::{Parser as ClapParser, Subcommand};
 
-use cce_ast::{Parser, ParseNode};
-use cce_infer_ast::convert;
-use cce_infer::Deducer;
+use cce_ast::{format, Lexicon, LoadError, LoadedProgram, Loader};
+use cce_diagnostics::{Renderer, Severity, ToDiagnostic};
+use cce_stream::{FileId, Location, SourceMap, Span};
+use cce_infer_ast::{
+  check_namespaces, convert, phrase, resolve, NameError, NamespaceError, ProgramNode, QueryKind
+};
+use cce_infer::{Answer, Deducer, Found, Matcher};
 
 
 #[derive(ClapParser)]
@@ -36,40 +40,204 @@ use cce_infer::Deducer;
 #[command(about = "The Circe Compiler", long_about = None)]
 #[command(version = "0.1.0")]
 #[command(author = "Carlos Kieliszewski")]
+#[command(args_conflicts_with_subcommands = true, subcommand_negates_reqs = true)]
 struct Cli {
-  filename: String
+  #[command(subcommand)]
+  command: Option<Command>,
+
+  /// The file to compile, or `-` to read from stdin
+  #[arg(required = true)]
+  filename: Option<String>,
+
+  /// A directory to look for imported files in, after the directory of
+  /// the file importing them
+  #[arg(short = 'I', long = "search", value_name = "DIR")]
+  search: Vec<PathBuf>
 }
 
+#[derive(Subcommand)]
+enum Command {
+  /// Rewrite files in the canonical layout
+  Fmt {
+    /// The files to format, or `-` to format stdin to stdout
+    #[arg(required = true)]
+    files: Vec<String>,
 
-fn main() {
-  let cli = Cli::parse();
+    /// Only check that the files are formatted, without changing them
+    #[arg(long)]
+    check: bool
+  }
+}
+
+
+fn render_errors(errors: &[impl ToDiagnostic], sources: &SourceMap) {
+  let renderer = Renderer::new(io::stderr().is_terminal());
 
-  let path: &Path = Path::new(&cli.filename);
-  if !path.exists() {
-    println!("File not found");
+  for err in errors.iter() {
+    eprintln!("{}", renderer.render(&err.to_diagnostic(), sources));
   }
+}
+
+fn read_input(filename: &str) -> String {
+  let input: io::Result<String> = if filename == "-" {
+    let mut input = String::new();
+    io::stdin().read_to_string(&mut input).map(|_| input)
+  } else {
+    fs::read_to_string(filename)
+  };
+
+  match input {
+    Ok(input) => input,
+    Err(err) => {
+      eprintln!("error: could not read {}: {}", filename, err);
+      exit(1);
+    }
+  }
+}
 
-  let mut file = File::open(path).unwrap();
-  let mut contents = String::new();
-  file.read_to_string(&mut contents).unwrap();
+/// Formats each file in place, or with `check`, reports the ones that
+/// would change. Returns whether every file was already formatted.
+fn fmt(files: &[String], check: bool) -> bool {
+  let mut ok: bool = true;
 
-  let contents = contents.as_str();
+  for filename in files {
+    let source: String = read_input(filename);
 
-  let mut parser = Parser::from(contents);
-  let mut nodes: Vec<ParseNode> = Vec::new();
+    let formatted: String = match format(&source) {
+      Ok(formatted) => formatted,
+      Err(errors) => {
+        let mut sources = SourceMap::new();
+        sources.add_virtual(if filename == "-" { "<stdin>" } else { filename }, source);
+        render_errors(&errors, &sources);
 
-  loop {
-    match parser.next() {
-      Ok(Some(node)) => nodes.push(node),
-      Ok(None) => break,
-      Err(err) => {
-        println!("Error: {}", err);
-        exit(1);
+        ok = false;
+        continue;
+      }
+    };
+
+    if filename == "-" && !check {
+      print!("{}", formatted);
+    } else if formatted != source {
+      if check {
+        println!("{} is not formatted", filename);
+        ok = false;
+      } else if let Err(err) = fs::write(filename, formatted) {
+        eprintln!("error: could not write {}: {}", filename, err);
+        ok = false;
+      }
+    }
+  }
+
+  ok
+}
+
+/// Where a span starts, as `file:line:column`.
+fn location(span: Span, sources: &SourceMap) -> String {
+  let name: &str = sources.name(span.file).unwrap_or("<unknown>");
+
+  match sources.location(span.file, span.start) {
+    Some(Location { line, column, .. }) => format!("{}:{}:{}", name, line, column),
+    None => name.to_string()
+  }
+}
+
+/// Prints what was found for a query, laid out like the descriptions it
+/// went through, with each step or description followed by what was
+/// found for it in turn.
+fn print_found(found: &Found, depth: usize, sources: &SourceMap) {
+  let indent: String = "  ".repeat(depth);
+
+  match found {
+    Found::Nothing => println!("{}not described", indent),
+    Found::Recursive(span) => println!("{}recursive, see {}", indent, location(*span, sources)),
+    Found::HowTo { howto, bindings, steps } => {
+      let filled: Vec<String> = bindings.iter()
+        .map(|(name, value)| format!("%{} = {}", name, phrase(value)))
+        .collect();
+
+      print!("{}howto {}? ({})", indent, phrase(&howto.signature), location(howto.span, sources));
+      if !filled.is_empty() {
+        print!(" with {}", filled.join(", "));
+      }
+      println!();
+
+      for step in steps.iter() {
+        println!("{}- {}", indent, phrase(&step.subject));
+        print_found(&step.found, depth + 1, sources);
+      }
+    },
+    Found::WhatIs(described) => {
+      for (whatis, descriptions) in described.iter() {
+        println!("{}whatis {}? ({})", indent, phrase(&whatis.signature), location(whatis.span, sources));
+
+        for description in descriptions.iter() {
+          println!("{}- {}", indent, phrase(&description.subject));
+          print_found(&description.found, depth + 1, sources);
+        }
       }
     }
   }
+}
+
+fn compile(filename: &str, search: &[PathBuf]) {
+  let mut sources = SourceMap::new();
+
+  // Stdin can only be read once, so it is kept whole to be able to show
+  // the lines that errors point at. Files are streamed.
+  let file: io::Result<FileId> = if filename == "-" {
+    Ok(sources.add_virtual("<stdin>", read_input(filename)))
+  } else {
+    sources.add_file(filename)
+  };
+
+  let file: FileId = match file {
+    Ok(file) => file,
+    Err(err) => {
+      eprintln!("error: could not read {}: {}", filename, err);
+      exit(1);
+    }
+  };
+
+  let loader: Loader = search.iter().fold(Loader::new(), |loader, dir| loader.search(dir));
+  let (program, errors): (LoadedProgram, Vec<LoadError>) = loader.load(&mut sources, file);
+
+  if !errors.is_empty() {
+    render_errors(&errors, &sources);
+    exit(1);
+  }
+
+  let ast = convert(program.nodes);
+
+  let name_errors: Vec<NameError> = resolve(&ast);
+  render_errors(&name_errors, &sources);
+
+  if name_errors.iter().any(|err| err.to_diagnostic().severity == Severity::Error) {
+    exit(1);
+  }
+
+  let lexicon: Lexicon = Lexicon::default();
+
+  let (_, namespace_errors): (_, Vec<NamespaceError>) = check_namespaces(&ast, &lexicon, &sources);
+  if !namespace_errors.is_empty() {
+    render_errors(&namespace_errors, &sources);
+    exit(1);
+  }
+
+  let matcher: Matcher = Matcher::with_lexicon(&ast, lexicon);
+
+  for node in ast.iter() {
+    if let ProgramNode::Query(query) = node {
+      let answer: Answer = matcher.answer(query);
+      let keyword: &str = match query.kind {
+        QueryKind::HowTo => "howto",
+        QueryKind::WhatIs => "whatis"
+      };
+
+      println!("? {} {}", keyword, phrase(&answer.subject));
+      print_found(&answer.found, 0, &sources);
+    }
+  }
 
-  let ast = convert(nodes);
   let mut deducer = Deducer::new();
   for node in ast {
     deducer.add_node(node);
@@ -77,4 +245,21 @@ fn main() {
 
   let result = deducer.deduce();
   println!("{:?}", result);
-}
\ No newline at end of file
+}
+
+
+fn main() {
+  let cli = Cli::parse();
+
+  match cli.command {
+    Some(Command::Fmt { files, check }) => {
+      if !fmt(&files, check) {
+        exit(1);
+      }
+    },
+    None => {
+      // Clap makes sure there is a filename when there's no subcommand.
+      compile(cli.filename.as_deref().unwrap_or("-"), &cli.search);
+    }
+  }
+}
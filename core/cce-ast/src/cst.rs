@@ -0,0 +1,943 @@
+/*
+
+Copyright (C) 2023 Carlos Kieliszewski
+
+This file is part of the Circe Project.
+
+Circe is free software: you can redistribute it and/or modify it under
+the terms of the GNU General Public License as published by the Free
+Software Foundation, either version 3 of the License, or (at your option)
+any later version.
+
+Circe is distributed in the hope that it will be useful, but WITHOUT ANY
+WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+FOR A PARTICULAR PURPOSE. See the GNU General Public License for more details.
+
+You should have received a copy of the GNU General Public License along with
+Circe. If not, see <https://www.gnu.org/licenses/>. 
+
+*/
+
+
+//! A lossless concrete syntax tree.
+//!
+//! Unlike [`ParseNode`], which keeps only what a program means, the
+//! syntax tree keeps every character of the source: whitespace,
+//! comments, bullets, `?`s and `.`s included, so the exact text can
+//! always be regenerated from it.
+//!
+//! The tree is laid out by the parser: the source is parsed as usual,
+//! and the tokens the parser read, with the whitespace and comments
+//! between them, are grouped into nodes by the spans of what it parsed.
+//! The AST is then derived back out of the tree, by
+//! [`SyntaxTree::to_program`], so that the tree is all there is to keep.
+//!
+//! The tree comes in two layers. The green tree is immutable, knows
+//! only the kinds, text and widths of what it holds, and can be shared
+//! freely. The red tree is a cheap view over it that adds absolute
+//! positions and parent links, and is what is normally worked with.
+
+use cce_llast::parse;
+use cce_stream::{FileId, InputStream, Span};
+
+use crate::lexer::{Lexer, SpannedToken, Token};
+use crate::parser::{
+  Binding, Command, CommandComponent, Conditional, HowToCommand, HowToStatement,
+  Import, ImportKind, Loop, LoopKind, Module, ParseNode, Parser, ParserError, Program,
+  Qualified, Query, QueryKind, Slot, Visibility, WhatIsStatement
+};
+
+use std::fmt;
+use std::rc::Rc;
+use std::sync::Arc;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SyntaxKind {
+  // Trivia
+  Whitespace,
+  LineComment,
+  BlockComment,
+  /// Text the lexer could not make sense of.
+  Error,
+
+  // Tokens
+  Identifier,
+  Keyword,
+  Literal,
+  Number,
+  LowLevelSequence,
+  Newline,
+  Question,
+  Dot,
+  Percent,
+  Colon,
+  Comma,
+  Dash,
+  Pipe,
+  Star,
+  LeftAngle,
+  RightAngle,
+
+  // Nodes
+  Root,
+  Command,
+  Slot,
+  Conditional,
+  Loop,
+  Binding,
+  Import,
+  Module,
+  Qualified,
+  Query,
+  HowTo,
+  WhatIs,
+  Primitive
+}
+
+impl SyntaxKind {
+  /// Whether this kind can be skipped over without changing the
+  /// meaning of the program.
+  pub fn is_trivia(self) -> bool {
+    matches!(self,
+      SyntaxKind::Whitespace | SyntaxKind::LineComment |
+      SyntaxKind::BlockComment | SyntaxKind::Newline
+    )
+  }
+
+  fn from_token(token: &Token) -> SyntaxKind {
+    match token {
+      Token::Identifier(_) => SyntaxKind::Identifier,
+      Token::Keyword(_) => SyntaxKind::Keyword,
+      Token::Literal(_) => SyntaxKind::Literal,
+      Token::Number(_) => SyntaxKind::Number,
+      Token::LowLevelSequence(_) => SyntaxKind::LowLevelSequence,
+      Token::Newline => SyntaxKind::Newline,
+      Token::Question => SyntaxKind::Question,
+      Token::Dot => SyntaxKind::Dot,
+      Token::Percent => SyntaxKind::Percent,
+      Token::Punctuation('-') => SyntaxKind::Dash,
+      Token::Punctuation('|') => SyntaxKind::Pipe,
+      Token::Punctuation('*') => SyntaxKind::Star,
+      Token::Punctuation('<') => SyntaxKind::LeftAngle,
+      Token::Punctuation('>') => SyntaxKind::RightAngle,
+      Token::Punctuation(':') => SyntaxKind::Colon,
+      Token::Punctuation(',') => SyntaxKind::Comma,
+      Token::Punctuation(_) => SyntaxKind::Error
+    }
+  }
+}
+
+/// A token in the green tree: its kind and its exact text.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct GreenToken {
+  pub kind: SyntaxKind,
+  pub text: String
+}
+
+/// A node in the green tree, which doesn't know where it is.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct GreenNode {
+  pub kind: SyntaxKind,
+  pub width: usize,
+  pub children: Vec<GreenElement>
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum GreenElement {
+  Node(Arc<GreenNode>),
+  Token(Arc<GreenToken>)
+}
+
+impl GreenNode {
+  pub fn new(kind: SyntaxKind, children: Vec<GreenElement>) -> Self {
+    let width: usize = children.iter().map(GreenElement::width).sum();
+    GreenNode { kind, width, children }
+  }
+}
+
+impl GreenElement {
+  pub fn kind(&self) -> SyntaxKind {
+    match self {
+      GreenElement::Node(node) => node.kind,
+      GreenElement::Token(token) => token.kind
+    }
+  }
+
+  pub fn width(&self) -> usize {
+    match self {
+      GreenElement::Node(node) => node.width,
+      GreenElement::Token(token) => token.text.len()
+    }
+  }
+
+  fn write_text(&self, out: &mut String) {
+    match self {
+      GreenElement::Node(node) => {
+        for child in node.children.iter() {
+          child.write_text(out);
+        }
+      },
+      GreenElement::Token(token) => out.push_str(&token.text)
+    }
+  }
+}
+
+/// A node in the red tree, which knows where it is in its file and
+/// what its parent is.
+#[derive(Clone)]
+pub struct SyntaxNode(Rc<NodeData>);
+
+struct NodeData {
+  green: Arc<GreenNode>,
+  offset: usize,
+  file: FileId,
+  parent: Option<SyntaxNode>
+}
+
+#[derive(Clone)]
+pub struct SyntaxToken {
+  green: Arc<GreenToken>,
+  offset: usize,
+  parent: SyntaxNode
+}
+
+#[derive(Clone, Debug)]
+pub enum SyntaxElement {
+  Node(SyntaxNode),
+  Token(SyntaxToken)
+}
+
+impl SyntaxNode {
+  pub fn new_root(green: Arc<GreenNode>, file: FileId) -> Self {
+    SyntaxNode(Rc::new(NodeData { green, offset: 0, file, parent: None }))
+  }
+
+  pub fn kind(&self) -> SyntaxKind {
+    self.0.green.kind
+  }
+
+  pub fn green(&self) -> &Arc<GreenNode> {
+    &self.0.green
+  }
+
+  pub fn span(&self) -> Span {
+    Span::new(self.0.file, self.0.offset, self.0.offset + self.0.green.width)
+  }
+
+  pub fn parent(&self) -> Option<SyntaxNode> {
+    self.0.parent.clone()
+  }
+
+  /// The exact source text this node covers.
+  pub fn text(&self) -> String {
+    let mut out: String = String::new();
+    GreenElement::Node(self.0.green.clone()).write_text(&mut out);
+    out
+  }
+
+  pub fn children_with_tokens(&self) -> Vec<SyntaxElement> {
+    let mut offset: usize = self.0.offset;
+
+    self.0.green.children.iter().map(|child| {
+      let element: SyntaxElement = match child {
+        GreenElement::Node(node) => SyntaxElement::Node(SyntaxNode(Rc::new(NodeData {
+          green: node.clone(),
+          offset,
+          file: self.0.file,
+          parent: Some(self.clone())
+        }))),
+        GreenElement::Token(token) => SyntaxElement::Token(SyntaxToken {
+          green: token.clone(),
+          offset,
+          parent: self.clone()
+        })
+      };
+
+      offset += child.width();
+      element
+    }).collect()
+  }
+
+  pub fn children(&self) -> Vec<SyntaxNode> {
+    self.children_with_tokens().into_iter().filter_map(|child| match child {
+      SyntaxElement::Node(node) => Some(node),
+      SyntaxElement::Token(_) => None
+    }).collect()
+  }
+
+  /// Every token under this node, in source order.
+  pub fn tokens(&self) -> Vec<SyntaxToken> {
+    let mut tokens: Vec<SyntaxToken> = Vec::new();
+
+    for child in self.children_with_tokens() {
+      match child {
+        SyntaxElement::Node(node) => tokens.extend(node.tokens()),
+        SyntaxElement::Token(token) => tokens.push(token)
+      }
+    }
+
+    tokens
+  }
+}
+
+impl fmt::Debug for SyntaxNode {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    write!(f, "{:?}@{}..{}", self.kind(), self.0.offset, self.0.offset + self.0.green.width)
+  }
+}
+
+impl SyntaxToken {
+  pub fn kind(&self) -> SyntaxKind {
+    self.green.kind
+  }
+
+  pub fn text(&self) -> &str {
+    &self.green.text
+  }
+
+  pub fn span(&self) -> Span {
+    Span::new(self.parent.0.file, self.offset, self.offset + self.green.text.len())
+  }
+
+  pub fn parent(&self) -> SyntaxNode {
+    self.parent.clone()
+  }
+
+  /// The token this text lexes to, with literals unescaped.
+  fn value(&self) -> Option<Token> {
+    let stream: InputStream = InputStream::with_file(self.text(), self.parent.0.file);
+    Lexer::new(stream).next_token().ok().flatten().map(|token| token.token)
+  }
+}
+
+impl fmt::Debug for SyntaxToken {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    let span: Span = self.span();
+    write!(f, "{:?}@{}..{} {:?}", self.kind(), span.start, span.end, self.text())
+  }
+}
+
+impl SyntaxElement {
+  pub fn kind(&self) -> SyntaxKind {
+    match self {
+      SyntaxElement::Node(node) => node.kind(),
+      SyntaxElement::Token(token) => token.kind()
+    }
+  }
+}
+
+/// The syntax tree of a whole source file, along with any syntax errors
+/// found while building it. Input that couldn't be parsed is still in
+/// the tree, as tokens directly under the root.
+pub struct SyntaxTree {
+  root: SyntaxNode,
+  errors: Vec<ParserError>
+}
+
+impl SyntaxTree {
+  pub fn parse(text: &str) -> SyntaxTree {
+    SyntaxTree::parse_with_file(text, FileId::default())
+  }
+
+  pub fn parse_with_file(text: &str, file: FileId) -> SyntaxTree {
+    let mut lexer: Lexer = Lexer::new(InputStream::with_file(text, file));
+    lexer.kept = Some(Vec::new());
+
+    let mut parser: Parser = Parser::new(lexer);
+    let (nodes, errors) = parser.parse_with_recovery();
+    let leaves: Vec<Leaf> = leaves(text, parser.lexer.kept.take().unwrap_or_default());
+
+    let root: Shape = Shape {
+      kind: SyntaxKind::Root,
+      start: 0,
+      end: text.len(),
+      children: nodes.iter().map(Shape::of_node).collect()
+    };
+
+    let mut cursor: usize = 0;
+    let green: GreenNode = build(&root, &leaves, &mut cursor);
+
+    SyntaxTree {
+      root: SyntaxNode::new_root(Arc::new(green), file),
+      errors
+    }
+  }
+
+  pub fn root(&self) -> &SyntaxNode {
+    &self.root
+  }
+
+  pub fn errors(&self) -> &[ParserError] {
+    &self.errors
+  }
+
+  /// Takes the syntax errors, giving up the tree.
+  pub fn into_errors(self) -> Vec<ParserError> {
+    self.errors
+  }
+
+  /// Regenerates the source text, exactly as it was given.
+  pub fn text(&self) -> String {
+    self.root.text()
+  }
+
+  /// Derives the abstract syntax tree of everything that parsed.
+  pub fn to_program(&self) -> Program {
+    Program {
+      nodes: self.root.children().iter().filter_map(lower_node).collect()
+    }
+  }
+}
+
+/// A token or piece of trivia, before it is placed in the tree.
+struct Leaf {
+  kind: SyntaxKind,
+  start: usize,
+  text: String
+}
+
+/// Lays the tokens the parser read out as leaves, with the gaps
+/// between them broken up into whitespace, comments and anything the
+/// lexer rejected.
+fn leaves(text: &str, tokens: Vec<SpannedToken>) -> Vec<Leaf> {
+  let mut leaves: Vec<Leaf> = Vec::new();
+  let mut last: usize = 0;
+
+  for token in tokens {
+    split_gap(text, last, token.span.start, &mut leaves);
+    leaves.push(Leaf {
+      kind: SyntaxKind::from_token(&token.token),
+      start: token.span.start,
+      text: text[token.span.start..token.span.end].to_string()
+    });
+    last = token.span.end;
+  }
+
+  split_gap(text, last, text.len(), &mut leaves);
+  leaves
+}
+
+fn split_gap(text: &str, start: usize, end: usize, leaves: &mut Vec<Leaf>) {
+  let gap: &str = &text[start..end];
+  let mut offset: usize = 0;
+
+  while offset < gap.len() {
+    let rest: &str = &gap[offset..];
+
+    let (kind, length) = if rest.starts_with("//") {
+      (SyntaxKind::LineComment, rest.find('\n').unwrap_or(rest.len()))
+    } else if let Some(body) = rest.strip_prefix("/*") {
+      (SyntaxKind::BlockComment, body.find("*/").map_or(rest.len(), |i| i + 4))
+    } else if rest.starts_with(char::is_whitespace) {
+      let length: usize = rest.find(|c: char| !c.is_whitespace()).unwrap_or(rest.len());
+      (SyntaxKind::Whitespace, length)
+    } else {
+      let length: usize = rest.char_indices()
+        .skip(1)
+        .find(|(_, c)| c.is_whitespace() || *c == '/')
+        .map_or(rest.len(), |(i, _)| i);
+      (SyntaxKind::Error, length)
+    };
+
+    leaves.push(Leaf { kind, start: start + offset, text: rest[..length].to_string() });
+    offset += length;
+  }
+}
+
+/// Where a node goes in the tree, taken from the spans of the AST.
+struct Shape {
+  kind: SyntaxKind,
+  start: usize,
+  end: usize,
+  children: Vec<Shape>
+}
+
+impl Shape {
+  fn new(kind: SyntaxKind, span: Span, children: Vec<Shape>) -> Shape {
+    Shape { kind, start: span.start, end: span.end, children }
+  }
+
+  fn of_node(node: &ParseNode) -> Shape {
+    match node {
+      ParseNode::Command(command) => Shape::of_command(command),
+      ParseNode::Conditional(conditional) => Shape::of_conditional(conditional),
+      ParseNode::Loop(repeat) => Shape::of_loop(repeat),
+      ParseNode::Binding(binding) => Shape::of_binding(binding),
+      ParseNode::Import(import) => Shape::new(SyntaxKind::Import, import.span, vec![]),
+      ParseNode::Module(module) => Shape::new(SyntaxKind::Module, module.span, vec![]),
+      ParseNode::Qualified(qualified) => Shape::of_qualified(qualified),
+      ParseNode::Query(query) => Shape::new(SyntaxKind::Query, query.span, Shape::of_slots(&query.subject).collect()),
+      ParseNode::HowToStatement(howto) => Shape::of_howto(howto),
+      ParseNode::WhatIsStatement(whatis) => Shape::of_whatis(whatis)
+    }
+  }
+
+  fn of_slots(components: &[CommandComponent]) -> impl Iterator<Item = Shape> + '_ {
+    components.iter().filter_map(|component| match component {
+      CommandComponent::Slot(_, span) => Some(Shape::new(SyntaxKind::Slot, *span, vec![])),
+      _ => None
+    })
+  }
+
+  fn of_command_slots(command: &Command) -> impl Iterator<Item = Shape> + '_ {
+    Shape::of_slots(&command.components)
+      .chain(command.modifiers.iter().flat_map(|modifier| Shape::of_slots(modifier)))
+  }
+
+  /// A command's node also holds the bullets nested under it, so it
+  /// reaches past the command's own span to the end of the last one.
+  fn of_command(command: &Command) -> Shape {
+    let children: Vec<Shape> = Shape::of_command_slots(command)
+      .chain(command.children.iter().map(Shape::of_command))
+      .collect();
+
+    let end: usize = children.last().filter(|_| !command.children.is_empty()).map_or(command.span.end, |child| child.end);
+    Shape { kind: SyntaxKind::Command, start: command.span.start, end, children }
+  }
+
+  /// Low-level blocks are single tokens, so they have no shape of
+  /// their own.
+  fn of_step(step: &HowToCommand) -> Option<Shape> {
+    match step {
+      HowToCommand::HighLevel(command) => Some(Shape::of_command(command)),
+      HowToCommand::LowLevel(_, _) => None,
+      HowToCommand::Primitive(steps, span) => {
+        Some(Shape::new(SyntaxKind::Primitive, *span, steps.iter().map(Shape::of_command).collect()))
+      },
+      HowToCommand::Conditional(conditional) => Some(Shape::of_conditional(conditional)),
+      HowToCommand::Loop(repeat) => Some(Shape::of_loop(repeat)),
+      HowToCommand::Binding(binding) => Some(Shape::of_binding(binding)),
+      HowToCommand::Qualified(qualified) => Some(Shape::of_qualified(qualified))
+    }
+  }
+
+  /// Where a step ends, including anything nested under it.
+  fn end_of(step: &HowToCommand) -> usize {
+    match step {
+      HowToCommand::LowLevel(_, span) => span.end,
+      step => Shape::of_step(step).map_or(step.span().end, |shape| shape.end)
+    }
+  }
+
+  /// Like a command's, a conditional's node holds the bullets of both of
+  /// its branches.
+  fn of_conditional(conditional: &Conditional) -> Shape {
+    let steps = conditional.then.iter().chain(conditional.otherwise.iter());
+
+    let children: Vec<Shape> = Shape::of_slots(&conditional.condition)
+      .chain(steps.clone().filter_map(Shape::of_step))
+      .collect();
+
+    let end: usize = steps.map(Shape::end_of).fold(conditional.span.end, usize::max);
+    Shape { kind: SyntaxKind::Conditional, start: conditional.span.start, end, children }
+  }
+
+  /// A loop's node holds its header's slots and its body's bullets.
+  fn of_loop(repeat: &Loop) -> Shape {
+    let header: Vec<Shape> = match &repeat.kind {
+      LoopKind::ForEach { item_span, collection, .. } => {
+        std::iter::once(Shape::new(SyntaxKind::Slot, *item_span, vec![])).chain(Shape::of_slots(collection)).collect()
+      },
+      LoopKind::Repeat { count } => Shape::of_slots(count).collect(),
+      LoopKind::While { condition } => Shape::of_slots(condition).collect()
+    };
+
+    let children: Vec<Shape> = header.into_iter()
+      .chain(repeat.body.iter().filter_map(Shape::of_step))
+      .collect();
+
+    let end: usize = repeat.body.iter().map(Shape::end_of).fold(repeat.span.end, usize::max);
+    Shape { kind: SyntaxKind::Loop, start: repeat.span.start, end, children }
+  }
+
+  /// The `and call it <name>` comes between a binding's command and the
+  /// bullets under it, so the command's node stops at the end of its
+  /// line, and the bullets are left to the binding's node.
+  fn of_binding(binding: &Binding) -> Shape {
+    let command: &Command = &binding.command;
+    let line: Shape = Shape {
+      kind: SyntaxKind::Command,
+      start: command.span.start,
+      end: command.span.end,
+      children: Shape::of_command_slots(command).collect()
+    };
+
+    let children: Vec<Shape> = std::iter::once(line)
+      .chain(command.children.iter().map(Shape::of_command))
+      .collect();
+
+    let end: usize = children.last().map_or(binding.span.end, |child| child.end.max(binding.span.end));
+    Shape { kind: SyntaxKind::Binding, start: binding.span.start, end, children }
+  }
+
+  /// The command comes last, so it can keep its bullets.
+  fn of_qualified(qualified: &Qualified) -> Shape {
+    let command: Shape = Shape::of_command(&qualified.command);
+    Shape { kind: SyntaxKind::Qualified, start: qualified.span.start, end: command.end.max(qualified.span.end), children: vec![command] }
+  }
+
+  fn of_howto(howto: &HowToStatement) -> Shape {
+    let body = howto.body.iter().filter_map(Shape::of_step);
+
+    let children: Vec<Shape> = Shape::of_slots(&howto.signature).chain(body).collect();
+    Shape::new(SyntaxKind::HowTo, howto.span, children)
+  }
+
+  fn of_whatis(whatis: &WhatIsStatement) -> Shape {
+    let children: Vec<Shape> = Shape::of_slots(&whatis.signature)
+      .chain(whatis.body.iter().map(Shape::of_command))
+      .collect();
+
+    Shape::new(SyntaxKind::WhatIs, whatis.span, children)
+  }
+}
+
+/// Builds the green node for a shape, taking every leaf up to its end.
+fn build(shape: &Shape, leaves: &[Leaf], cursor: &mut usize) -> GreenNode {
+  let mut children: Vec<GreenElement> = Vec::new();
+
+  for child in shape.children.iter() {
+    take_leaves(child.start, leaves, cursor, &mut children);
+    children.push(GreenElement::Node(Arc::new(build(child, leaves, cursor))));
+  }
+
+  take_leaves(shape.end, leaves, cursor, &mut children);
+  GreenNode::new(shape.kind, children)
+}
+
+fn take_leaves(end: usize, leaves: &[Leaf], cursor: &mut usize, children: &mut Vec<GreenElement>) {
+  while let Some(leaf) = leaves.get(*cursor).filter(|leaf| leaf.start < end) {
+    children.push(GreenElement::Token(Arc::new(GreenToken {
+      kind: leaf.kind,
+      text: leaf.text.clone()
+    })));
+
+    *cursor += 1;
+  }
+}
+
+fn lower_node(node: &SyntaxNode) -> Option<ParseNode> {
+  match node.kind() {
+    SyntaxKind::Command => Some(ParseNode::Command(lower_command(node))),
+    SyntaxKind::Conditional => lower_conditional(node).map(ParseNode::Conditional),
+    SyntaxKind::Loop => lower_loop(node).map(ParseNode::Loop),
+    SyntaxKind::Binding => lower_binding(node).map(ParseNode::Binding),
+    SyntaxKind::Import => lower_import(node).map(ParseNode::Import),
+    SyntaxKind::Module => lower_module(node).map(ParseNode::Module),
+    SyntaxKind::Qualified => lower_qualified(node).map(ParseNode::Qualified),
+    SyntaxKind::Query => lower_query(node).map(ParseNode::Query),
+    SyntaxKind::HowTo => lower_howto(node).map(ParseNode::HowToStatement),
+    SyntaxKind::WhatIs => Some(ParseNode::WhatIsStatement(lower_whatis(node))),
+    _ => None
+  }
+}
+
+fn lower_component(element: &SyntaxElement) -> Option<CommandComponent> {
+  match element {
+    SyntaxElement::Node(node) if node.kind() == SyntaxKind::Slot => {
+      let elements: Vec<SyntaxElement> = node.children_with_tokens();
+      let name: &SyntaxElement = elements.iter().find(|element| element.kind() == SyntaxKind::Identifier)?;
+
+      let colon: Option<usize> = elements.iter().position(|element| element.kind() == SyntaxKind::Colon);
+      let kind: Option<Vec<CommandComponent>> = colon.map(|colon| elements[colon + 1..].iter().filter_map(lower_component).collect());
+
+      let name: String = match name {
+        SyntaxElement::Token(token) => token.text().to_string(),
+        SyntaxElement::Node(_) => return None
+      };
+
+      Some(CommandComponent::Slot(Slot { name, kind }, node.span()))
+    },
+    SyntaxElement::Token(token) => {
+      match token.kind() {
+        SyntaxKind::Identifier | SyntaxKind::Keyword => {
+          Some(CommandComponent::Keyword(token.text().to_string(), token.span()))
+        },
+        SyntaxKind::Literal => match token.value()? {
+          Token::Literal(literal) => Some(CommandComponent::Literal(literal, token.span())),
+          _ => None
+        },
+        SyntaxKind::Number => match token.value()? {
+          Token::Number(number) => Some(CommandComponent::Number(number, token.span())),
+          _ => None
+        },
+        _ => None
+      }
+    },
+    _ => None
+  }
+}
+
+fn lower_command(node: &SyntaxNode) -> Command {
+  let mut components: Vec<CommandComponent> = Vec::new();
+  let mut modifiers: Vec<Vec<CommandComponent>> = Vec::new();
+  let mut children: Vec<Command> = Vec::new();
+  let mut end: usize = node.span().start;
+
+  for child in node.children_with_tokens() {
+    match &child {
+      SyntaxElement::Node(nested) if nested.kind() == SyntaxKind::Command => {
+        children.push(lower_command(nested));
+      },
+      _ if !children.is_empty() => {},
+      SyntaxElement::Token(token) if token.kind() == SyntaxKind::Pipe => {
+        modifiers.push(Vec::new());
+        end = token.span().end;
+      },
+      _ => {
+        if let Some(component) = lower_component(&child) {
+          end = component.span().end;
+          modifiers.last_mut().unwrap_or(&mut components).push(component);
+        }
+      }
+    }
+  }
+
+  // The node runs on to cover any nested bullets, but the command itself
+  // ends with its last component.
+  let span: Span = if children.is_empty() { node.span() } else { Span::new(node.span().file, node.span().start, end) };
+
+  Command { components, modifiers, children, span }
+}
+
+/// Whether a statement starts with `private`.
+fn lower_visibility(elements: &[SyntaxElement]) -> Visibility {
+  match elements.first() {
+    Some(SyntaxElement::Token(token)) if token.text() == "private" => Visibility::Private,
+    _ => Visibility::Public
+  }
+}
+
+/// The components between a statement's keyword and its `?`.
+fn lower_signature(elements: &[SyntaxElement]) -> Vec<CommandComponent> {
+  let keyword: usize = match lower_visibility(elements) {
+    Visibility::Private => elements.iter().skip(1).position(|element| element.kind() == SyntaxKind::Keyword).map_or(1, |i| i + 2),
+    Visibility::Public => 1
+  };
+
+  elements.iter()
+    .skip(keyword)
+    .take_while(|element| element.kind() != SyntaxKind::Question)
+    .filter_map(lower_component)
+    .collect()
+}
+
+/// Lowers the steps among `elements`, skipping everything else.
+fn lower_steps(elements: &[SyntaxElement]) -> Option<Vec<HowToCommand>> {
+  let mut steps: Vec<HowToCommand> = Vec::new();
+
+  for element in elements.iter() {
+    match element {
+      SyntaxElement::Node(child) if child.kind() == SyntaxKind::Command => {
+        steps.push(HowToCommand::HighLevel(lower_command(child)));
+      },
+      SyntaxElement::Node(child) if child.kind() == SyntaxKind::Primitive => {
+        let primitive: Vec<Command> = child.children().iter().map(lower_command).collect();
+        steps.push(HowToCommand::Primitive(primitive, child.span()));
+      },
+      SyntaxElement::Node(child) if child.kind() == SyntaxKind::Conditional => {
+        steps.push(HowToCommand::Conditional(lower_conditional(child)?));
+      },
+      SyntaxElement::Node(child) if child.kind() == SyntaxKind::Loop => {
+        steps.push(HowToCommand::Loop(lower_loop(child)?));
+      },
+      SyntaxElement::Node(child) if child.kind() == SyntaxKind::Binding => {
+        steps.push(HowToCommand::Binding(lower_binding(child)?));
+      },
+      SyntaxElement::Node(child) if child.kind() == SyntaxKind::Qualified => {
+        steps.push(HowToCommand::Qualified(lower_qualified(child)?));
+      },
+      SyntaxElement::Token(token) if token.kind() == SyntaxKind::LowLevelSequence => {
+        let sequence: String = match token.value()? {
+          Token::LowLevelSequence(sequence) => sequence,
+          _ => return None
+        };
+
+        steps.push(HowToCommand::LowLevel(parse(&sequence).ok()?, token.span()));
+      },
+      _ => {}
+    }
+  }
+
+  Some(steps)
+}
+
+fn lower_howto(node: &SyntaxNode) -> Option<HowToStatement> {
+  let elements: Vec<SyntaxElement> = node.children_with_tokens();
+  let signature_end: usize = elements.iter().position(|element| element.kind() == SyntaxKind::Question)?;
+
+  Some(HowToStatement {
+    visibility: lower_visibility(&elements),
+    signature: lower_signature(&elements),
+    body: lower_steps(&elements[signature_end..])?,
+    span: node.span()
+  })
+}
+
+fn lower_conditional(node: &SyntaxNode) -> Option<Conditional> {
+  let elements: Vec<SyntaxElement> = node.children_with_tokens();
+
+  // The condition runs from after the `if` up to the comma or the end
+  // of the line.
+  let condition_end: usize = elements.iter().skip(1)
+    .position(|element| matches!(element.kind(), SyntaxKind::Comma | SyntaxKind::Newline | SyntaxKind::Dot | SyntaxKind::Dash))
+    .map_or(elements.len(), |i| i + 1);
+  let condition: Vec<CommandComponent> = elements[1..condition_end].iter().filter_map(lower_component).collect();
+
+  let otherwise: usize = elements[condition_end..].iter()
+    .position(|element| matches!(element, SyntaxElement::Token(token) if token.text() == "otherwise"))
+    .map_or(elements.len(), |i| i + condition_end);
+
+  let then: Vec<HowToCommand> = lower_steps(&elements[condition_end..otherwise])?;
+
+  // Only a step after a comma is covered by the span.
+  let end: usize = match elements.get(condition_end) {
+    Some(element) if element.kind() == SyntaxKind::Comma => then.first()?.span().end,
+    _ => condition.last()?.span().end
+  };
+
+  Some(Conditional {
+    condition,
+    then,
+    otherwise: lower_steps(elements.get(otherwise..).unwrap_or_default())?,
+    span: Span::new(node.span().file, node.span().start, end)
+  })
+}
+
+fn lower_loop(node: &SyntaxNode) -> Option<Loop> {
+  let elements: Vec<SyntaxElement> = node.children_with_tokens();
+
+  // As with a conditional, the header runs up to the comma or the end of
+  // the line.
+  let header_end: usize = elements.iter().skip(1)
+    .position(|element| matches!(element.kind(), SyntaxKind::Comma | SyntaxKind::Newline | SyntaxKind::Dot | SyntaxKind::Dash))
+    .map_or(elements.len(), |i| i + 1);
+  let mut header: Vec<CommandComponent> = elements[1..header_end].iter().filter_map(lower_component).collect();
+  let header_span_end: usize = header.last()?.span().end;
+
+  let kind: LoopKind = match elements.first()? {
+    SyntaxElement::Token(token) if token.text() == "for" => {
+      // `each %item in <collection>`
+      let collection: Vec<CommandComponent> = header.split_off(3.min(header.len()));
+
+      match header.get(1)? {
+        CommandComponent::Slot(item, item_span) => LoopKind::ForEach { item: item.clone(), item_span: *item_span, collection },
+        _ => return None
+      }
+    },
+    SyntaxElement::Token(token) if token.text() == "repeat" => {
+      header.pop();
+      LoopKind::Repeat { count: header }
+    },
+    _ => LoopKind::While { condition: header }
+  };
+
+  let body: Vec<HowToCommand> = lower_steps(&elements[header_end..])?;
+
+  // Only a step after a comma is covered by the span.
+  let end: usize = match elements.get(header_end) {
+    Some(element) if element.kind() == SyntaxKind::Comma => body.first()?.span().end,
+    _ => header_span_end
+  };
+
+  Some(Loop {
+    kind,
+    body,
+    span: Span::new(node.span().file, node.span().start, end)
+  })
+}
+
+fn lower_binding(node: &SyntaxNode) -> Option<Binding> {
+  let elements: Vec<SyntaxElement> = node.children_with_tokens();
+
+  // The command comes first, then `and call it <name>`, then any bullets.
+  let mut commands = elements.iter().filter_map(|element| match element {
+    SyntaxElement::Node(child) if child.kind() == SyntaxKind::Command => Some(child),
+    _ => None
+  });
+
+  let mut command: Command = lower_command(commands.next()?);
+  command.children = commands.map(lower_command).collect();
+
+  let name: &SyntaxToken = elements.iter()
+    .take_while(|element| !matches!(element, SyntaxElement::Node(child) if child.span().start > command.span.end))
+    .filter_map(|element| match element {
+      SyntaxElement::Token(token) if token.kind() == SyntaxKind::Identifier => Some(token),
+      _ => None
+    })
+    .last()?;
+
+  Some(Binding::new(command, (name.text().to_string(), name.span())))
+}
+
+fn lower_import(node: &SyntaxNode) -> Option<Import> {
+  let tokens: Vec<SyntaxToken> = node.tokens();
+
+  let kind: ImportKind = match tokens.first()?.text() {
+    "use" => ImportKind::Use,
+    _ => ImportKind::Include
+  };
+
+  let literal: &SyntaxToken = tokens.iter().find(|token| token.kind() == SyntaxKind::Literal)?;
+  let path: String = match literal.value()? {
+    Token::Literal(path) => path,
+    _ => return None
+  };
+
+  Some(Import { kind, path, path_span: literal.span(), span: node.span() })
+}
+
+/// The name is the word after `module`.
+fn lower_module(node: &SyntaxNode) -> Option<Module> {
+  let name: SyntaxToken = node.tokens().into_iter().filter(|token| token.kind() == SyntaxKind::Identifier).nth(1)?;
+  Some(Module { name: name.text().to_string(), name_span: name.span(), span: node.span() })
+}
+
+fn lower_qualified(node: &SyntaxNode) -> Option<Qualified> {
+  let elements: Vec<SyntaxElement> = node.children_with_tokens();
+
+  // The module is the word after `within`.
+  let module: &SyntaxToken = elements.iter().filter_map(|element| match element {
+    SyntaxElement::Token(token) if token.kind() == SyntaxKind::Identifier => Some(token),
+    _ => None
+  }).nth(1)?;
+
+  let command: Command = elements.iter().find_map(|element| match element {
+    SyntaxElement::Node(child) if child.kind() == SyntaxKind::Command => Some(lower_command(child)),
+    _ => None
+  })?;
+
+  Some(Qualified {
+    module: module.text().to_string(),
+    module_span: module.span(),
+    span: Span::new(node.span().file, node.span().start, command.span.end),
+    command
+  })
+}
+
+fn lower_query(node: &SyntaxNode) -> Option<Query> {
+  let elements: Vec<SyntaxElement> = node.children_with_tokens();
+  let keyword: usize = elements.iter().position(|element| element.kind() == SyntaxKind::Keyword)?;
+
+  let kind: QueryKind = match &elements[keyword] {
+    SyntaxElement::Token(token) if token.text() == "howto" => QueryKind::HowTo,
+    _ => QueryKind::WhatIs
+  };
+
+  Some(Query {
+    kind,
+    subject: elements[keyword + 1..].iter().filter_map(lower_component).collect(),
+    span: node.span()
+  })
+}
+
+fn lower_whatis(node: &SyntaxNode) -> WhatIsStatement {
+  let elements: Vec<SyntaxElement> = node.children_with_tokens();
+
+  WhatIsStatement {
+    visibility: lower_visibility(&elements),
+    signature: lower_signature(&elements),
+    body: node.children().iter().filter(|child| child.kind() == SyntaxKind::Command).map(lower_command).collect(),
+    span: node.span()
+  }
+}
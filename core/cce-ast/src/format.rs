@@ -0,0 +1,643 @@
+/*
+
+Copyright (C) 2023 Carlos Kieliszewski
+
+This file is part of the Circe Project.
+
+Circe is free software: you can redistribute it and/or modify it under
+the terms of the GNU General Public License as published by the Free
+Software Foundation, either version 3 of the License, or (at your option)
+any later version.
+
+Circe is distributed in the hope that it will be useful, but WITHOUT ANY
+WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+FOR A PARTICULAR PURPOSE. See the GNU General Public License for more details.
+
+You should have received a copy of the GNU General Public License along with
+Circe. If not, see <https://www.gnu.org/licenses/>. 
+
+*/
+
+
+//! Prints programs in one canonical layout:
+//!
+//! ```text
+//! print 'Hello, world!' to the console
+//! | add a newline.
+//!
+//! howto print a string to the console?
+//! - write the string to stdout
+//! | add a newline
+//!
+//! whatis 'stdout'?
+//! - a file stream
+//! ```
+//!
+//! Top-level commands end in a `.`, bullets are `- ` and modifiers are
+//! `| `, each on their own line, and statements are separated by a
+//! single blank line. Nested bullets are indented by two spaces for each
+//! level. Literals are kept as they were written. Comments are kept too:
+//! one after a command on the same line stays at the end of that line,
+//! and one on a line of its own stays on a line of its own, above what
+//! follows it and as deep as the bullets around it.
+
+use cce_stream::Span;
+
+use crate::cst::{SyntaxKind, SyntaxTree};
+use crate::parser::{
+  Binding, Command, CommandComponent, Conditional, HowToCommand, HowToStatement,
+  Import, ImportKind, Loop, LoopKind, ParseNode, ParserError, Qualified, Query,
+  QueryKind, Slot, Visibility, WhatIsStatement
+};
+
+use std::fmt::Write;
+
+/// Formats Circe source, or returns its syntax errors if it has any.
+pub fn format(source: &str) -> Result<String, Vec<ParserError>> {
+  let tree: SyntaxTree = SyntaxTree::parse(source);
+
+  if !tree.errors().is_empty() {
+    return Err(tree.into_errors());
+  }
+
+  let line_starts: Vec<usize> = std::iter::once(0)
+    .chain(source.match_indices('\n').map(|(i, _)| i + 1))
+    .collect();
+
+  // Where each `otherwise` is, since an `otherwise` bullet has no span
+  // of its own once it is folded into its conditional.
+  let otherwise: Vec<usize> = tree.root().tokens().iter()
+    .filter(|token| !token.kind().is_trivia() && token.text() == "otherwise")
+    .map(|token| token.span().start)
+    .collect();
+
+  let mut formatter: Formatter = Formatter {
+    source,
+    comments: Vec::new(),
+    otherwise,
+    line_starts,
+    next_comment: 0,
+    bullets: Vec::new(),
+    last: 0,
+    depth: 0,
+    out: String::new()
+  };
+
+  formatter.comments = collect_comments(&tree, &formatter);
+  formatter.program(&tree.to_program().nodes);
+  Ok(formatter.out)
+}
+
+/// A comment from the source, and where it was.
+struct Comment {
+  text: String,
+  start: usize,
+  line: usize,
+  /// Whether it comes after something else on its line.
+  trailing: bool,
+  /// How far its line is indented.
+  indent: usize
+}
+
+fn collect_comments(tree: &SyntaxTree, formatter: &Formatter) -> Vec<Comment> {
+  tree.root().tokens().iter()
+    .filter(|token| matches!(token.kind(), SyntaxKind::LineComment | SyntaxKind::BlockComment))
+    .map(|token| {
+      let start: usize = token.span().start;
+      let line: usize = formatter.line_of(start);
+      let before: &str = &formatter.source[formatter.line_starts[line]..start];
+
+      Comment {
+        text: token.text().trim_end().to_string(),
+        start,
+        line,
+        trailing: !before.trim().is_empty(),
+        indent: formatter.indent_of(start)
+      }
+    })
+    .collect()
+}
+
+struct Formatter<'s> {
+  source: &'s str,
+  comments: Vec<Comment>,
+  otherwise: Vec<usize>,
+  /// Where each line of the source starts.
+  line_starts: Vec<usize>,
+  next_comment: usize,
+  /// The source indentation and written depth of each bullet of the
+  /// statement being written, which comments on lines of their own are
+  /// lined up with.
+  bullets: Vec<(usize, usize)>,
+  /// Where in the source the last thing written ends.
+  last: usize,
+  /// How deeply nested the bullet being written is.
+  depth: usize,
+  out: String
+}
+
+impl<'s> Formatter<'s> {
+  fn line_of(&self, offset: usize) -> usize {
+    self.line_starts.partition_point(|start| *start <= offset) - 1
+  }
+
+  /// How far the line holding `offset` is indented.
+  fn indent_of(&self, offset: usize) -> usize {
+    let line: &str = &self.source[self.line_starts[self.line_of(offset)]..];
+    line.len() - line.trim_start_matches([' ', '\t']).len()
+  }
+
+  /// Writes every comment that comes before `offset`, each on a line of
+  /// its own.
+  fn comments_before(&mut self, offset: usize) {
+    while let Some(comment) = self.comments.get(self.next_comment) {
+      if comment.start >= offset {
+        break;
+      }
+
+      let depth: usize = self.comment_depth(comment.indent);
+      let text: String = format!("{}{}\n", "  ".repeat(depth), comment.text);
+      self.out.push_str(&text);
+      self.next_comment += 1;
+    }
+  }
+
+  fn has_comment_before(&self, offset: usize) -> bool {
+    self.comments.get(self.next_comment).is_some_and(|comment| comment.start < offset)
+  }
+
+  /// A comment is as deep as the last bullet indented no further than it
+  /// is, or one deeper if it is indented further than that bullet.
+  fn comment_depth(&self, indent: usize) -> usize {
+    match self.bullets.iter().rev().find(|(bullet, _)| *bullet <= indent) {
+      Some((bullet, depth)) if indent > *bullet => depth + 1,
+      Some((_, depth)) => *depth,
+      None => 0
+    }
+  }
+
+  /// Ends the line being written, after any comments that came after
+  /// what was last written on its line of the source, with nothing but
+  /// punctuation between them.
+  fn newline(&mut self) {
+    let line: usize = self.line_of(self.last);
+    let mut last: usize = self.last;
+
+    while let Some(comment) = self.comments.get(self.next_comment) {
+      let between: &str = &self.source[last.min(comment.start)..comment.start];
+      let follows: bool = between.trim_matches(|c: char| c.is_whitespace() || ".?,".contains(c)).is_empty();
+
+      if !comment.trailing || comment.line > line || !follows {
+        break;
+      }
+
+      last = comment.start + comment.text.len();
+      self.out.push(' ');
+      self.out.push_str(&comment.text);
+      self.next_comment += 1;
+    }
+
+    self.out.push('\n');
+  }
+
+  /// Starts a bullet for something that starts at `start` in the source.
+  fn bullet_start(&mut self, start: usize, bullet: &str) {
+    self.comments_before(start);
+    self.indent();
+    self.out.push_str(bullet);
+
+    // Only a bullet that starts its line can have comments lined up with
+    // it, and not, say, a step after the comma of an `if`.
+    let line_start: usize = self.line_starts[self.line_of(start)];
+    if matches!(self.source[line_start..start].trim(), "" | "-") {
+      self.bullets.push((self.indent_of(start), self.depth));
+    }
+
+    self.last = start;
+  }
+
+  /// Starts the next `otherwise`, on a bullet unless `bullet` is empty.
+  fn otherwise_start(&mut self, bullet: &str) {
+    let last: usize = self.last;
+    let start: usize = self.otherwise.iter().copied().find(|start| *start >= last).unwrap_or(last);
+
+    if bullet.is_empty() {
+      self.comments_before(start);
+    } else {
+      self.bullet_start(start, bullet);
+    }
+
+    self.out.push_str("otherwise");
+    self.last = start + "otherwise".len();
+  }
+
+  fn indent(&mut self) {
+    self.out.push_str(&"  ".repeat(self.depth));
+  }
+
+  fn program(&mut self, nodes: &[ParseNode]) {
+    let mut previous: Option<&ParseNode> = None;
+
+    for node in nodes {
+      // Commands in a row stay together, as do imports and queries, and
+      // anything else gets a blank line.
+      let together: bool = previous.is_some_and(|previous| {
+        (is_command(previous) && is_command(node)) || (is_import(previous) && is_import(node)) ||
+          (is_query(previous) && is_query(node))
+      });
+      if previous.is_some() && !together {
+        self.out.push('\n');
+      }
+
+      self.comments_before(node.span().start);
+      self.bullets.clear();
+      self.last = node.span().start;
+
+      match node {
+        ParseNode::Command(command) => {
+          self.command(command);
+          self.end_top_level();
+        },
+        ParseNode::Conditional(conditional) => self.top_level_conditional(conditional),
+        ParseNode::Loop(repeat) => self.top_level_loop(repeat),
+        ParseNode::Binding(binding) => {
+          self.binding(binding);
+          self.end_top_level();
+        },
+        ParseNode::Import(import) => self.import(import),
+        ParseNode::Module(module) => {
+          let _ = write!(self.out, "module {}", module.name);
+          self.last = module.span.end;
+          self.end_top_level();
+        },
+        ParseNode::Qualified(qualified) => {
+          self.qualified(qualified);
+          self.end_top_level();
+        },
+        ParseNode::Query(query) => self.query(query),
+        ParseNode::HowToStatement(howto) => self.howto(howto),
+        ParseNode::WhatIsStatement(whatis) => self.whatis(whatis)
+      }
+
+      previous = Some(node);
+    }
+
+    if previous.is_some() && self.next_comment < self.comments.len() {
+      self.out.push('\n');
+    }
+
+    self.comments_before(usize::MAX);
+  }
+
+  /// Ends a top-level command with its `.`.
+  fn end_top_level(&mut self) {
+    self.out.push('.');
+    self.newline();
+  }
+
+  /// Writes source text as it is, like a literal in the way its author
+  /// spelled it.
+  fn verbatim(&mut self, span: Span) {
+    self.out.push_str(&self.source[span.start..span.end]);
+    self.last = span.end;
+  }
+
+  fn components(&mut self, components: &[CommandComponent]) {
+    for (i, component) in components.iter().enumerate() {
+      if i > 0 {
+        self.out.push(' ');
+      }
+
+      match component {
+        CommandComponent::Keyword(keyword, _) => self.out.push_str(keyword),
+        CommandComponent::Literal(_, span) | CommandComponent::Number(_, span) => self.verbatim(*span),
+        CommandComponent::Slot(slot, _) => self.slot(slot)
+      }
+
+      self.last = self.last.max(component.span().end);
+    }
+  }
+
+  fn slot(&mut self, slot: &Slot) {
+    let _ = write!(self.out, "%{}", slot.name);
+
+    if let Some(kind) = &slot.kind {
+      self.out.push_str(": ");
+      self.components(kind);
+    }
+  }
+
+  /// Writes a command and its modifiers, without a line break after.
+  fn command(&mut self, command: &Command) {
+    self.components(&command.components);
+
+    for modifier in command.modifiers.iter() {
+      self.newline();
+      self.indent();
+      self.out.push('|');
+
+      if !modifier.is_empty() {
+        self.out.push(' ');
+        self.components(modifier);
+      }
+    }
+  }
+
+  fn signature(&mut self, visibility: Visibility, keyword: &str, signature: &[CommandComponent]) {
+    if visibility == Visibility::Private {
+      self.out.push_str("private ");
+    }
+
+    self.out.push_str(keyword);
+
+    if !signature.is_empty() {
+      self.out.push(' ');
+      self.components(signature);
+    }
+
+    self.out.push('?');
+    self.newline();
+  }
+
+  fn query(&mut self, query: &Query) {
+    self.out.push_str(match query.kind {
+      QueryKind::HowTo => "? howto ",
+      QueryKind::WhatIs => "? whatis "
+    });
+
+    self.components(&query.subject);
+    self.newline();
+  }
+
+  fn import(&mut self, import: &Import) {
+    let keyword: &str = match import.kind {
+      ImportKind::Use => "use",
+      ImportKind::Include => "include"
+    };
+
+    let _ = write!(self.out, "{} ", keyword);
+    self.verbatim(import.path_span);
+    self.end_top_level();
+  }
+
+  /// Writes a qualified command, without a line break after.
+  fn qualified(&mut self, qualified: &Qualified) {
+    let _ = write!(self.out, "within {}, ", qualified.module);
+    self.command(&qualified.command);
+  }
+
+  /// Writes a command with its name, without a line break after.
+  fn binding(&mut self, binding: &Binding) {
+    self.command(&binding.command);
+    let _ = write!(self.out, " and call it {}", binding.name);
+  }
+
+  fn bullet(&mut self, command: &Command) {
+    self.bullet_start(command.span.start, "- ");
+    self.command(command);
+    self.newline();
+    self.sub_bullets(command);
+  }
+
+  fn sub_bullets(&mut self, command: &Command) {
+    self.depth += 1;
+    for child in command.children.iter() {
+      self.bullet(child);
+    }
+    self.depth -= 1;
+  }
+
+  fn howto(&mut self, howto: &HowToStatement) {
+    self.signature(howto.visibility, "howto", &howto.signature);
+
+    for step in howto.body.iter() {
+      self.step(step);
+    }
+  }
+
+  fn step(&mut self, step: &HowToCommand) {
+    match step {
+      HowToCommand::HighLevel(command) => self.bullet(command),
+      HowToCommand::LowLevel(_, span) => self.low_level(*span),
+      HowToCommand::Primitive(steps, span) => self.primitive(steps, *span),
+      HowToCommand::Conditional(conditional) => {
+        self.bullet_start(conditional.span.start, "- ");
+        self.conditional(conditional);
+      },
+      HowToCommand::Qualified(qualified) => {
+        self.bullet_start(qualified.span.start, "- ");
+        self.qualified(qualified);
+        self.newline();
+        self.sub_bullets(&qualified.command);
+      },
+      HowToCommand::Binding(binding) => {
+        self.bullet_start(binding.span.start, "- ");
+        self.binding(binding);
+        self.newline();
+        self.sub_bullets(&binding.command);
+      },
+      HowToCommand::Loop(repeat) => {
+        self.bullet_start(repeat.span.start, "- ");
+        self.loop_header(&repeat.kind);
+        self.branch(&repeat.body);
+      }
+    }
+  }
+
+  fn loop_header(&mut self, kind: &LoopKind) {
+    match kind {
+      LoopKind::ForEach { item, collection, .. } => {
+        self.out.push_str("for each ");
+        self.slot(item);
+        self.out.push_str(" in ");
+        self.components(collection);
+      },
+      LoopKind::Repeat { count } => {
+        self.out.push_str("repeat ");
+        self.components(count);
+        self.out.push_str(" times");
+      },
+      LoopKind::While { condition } => {
+        self.out.push_str("while ");
+        self.components(condition);
+      }
+    }
+  }
+
+  /// Writes a conditional from its `if`, with a branch after a comma
+  /// when it is a single command, and on indented bullets otherwise.
+  fn conditional(&mut self, conditional: &Conditional) {
+    self.out.push_str("if ");
+    self.components(&conditional.condition);
+    self.branch(&conditional.then);
+
+    match conditional.otherwise.as_slice() {
+      [] => {},
+      [HowToCommand::Conditional(inner)] => {
+        self.otherwise_start("- ");
+        self.out.push(' ');
+        self.conditional(inner);
+      },
+      steps => {
+        self.otherwise_start("- ");
+        self.branch(steps);
+      }
+    }
+  }
+
+  fn branch(&mut self, steps: &[HowToCommand]) {
+    // A comment in the branch keeps it on bullets, to have somewhere to go.
+    if steps.first().is_some_and(|step| self.has_comment_before(step.span().start)) {
+      return self.branch_bullets(steps);
+    }
+
+    match steps {
+      // Bullets under the `if` would join the branch, so a command with
+      // sub-steps of its own has to go on a bullet of its own.
+      [HowToCommand::HighLevel(command)] if command.children.is_empty() => {
+        self.out.push_str(", ");
+        self.command(command);
+        self.newline();
+      },
+      [HowToCommand::Binding(binding)] if binding.command.children.is_empty() => {
+        self.out.push_str(", ");
+        self.binding(binding);
+        self.newline();
+      },
+      [HowToCommand::Qualified(qualified)] if qualified.command.children.is_empty() => {
+        self.out.push_str(", ");
+        self.qualified(qualified);
+        self.newline();
+      },
+      steps => self.branch_bullets(steps)
+    }
+  }
+
+  fn branch_bullets(&mut self, steps: &[HowToCommand]) {
+    self.newline();
+
+    self.depth += 1;
+    for step in steps {
+      self.step(step);
+    }
+    self.depth -= 1;
+  }
+
+  /// At the top level, each branch is a single command and each
+  /// `otherwise` is written as a command of its own.
+  fn top_level_conditional(&mut self, conditional: &Conditional) {
+    self.out.push_str("if ");
+    self.top_level_branch(conditional);
+
+    let mut otherwise: &[HowToCommand] = &conditional.otherwise;
+
+    loop {
+      match otherwise {
+        [HowToCommand::Conditional(inner)] => {
+          self.otherwise_start("");
+          self.out.push_str(" if ");
+          self.top_level_branch(inner);
+          otherwise = &inner.otherwise;
+        },
+        [HowToCommand::HighLevel(command)] => {
+          self.otherwise_start("");
+          self.out.push_str(", ");
+          self.command(command);
+          self.end_top_level();
+          break;
+        },
+        _ => break
+      }
+    }
+  }
+
+  fn top_level_branch(&mut self, conditional: &Conditional) {
+    self.components(&conditional.condition);
+    self.top_level_step(&conditional.then);
+  }
+
+  fn top_level_loop(&mut self, repeat: &Loop) {
+    self.loop_header(&repeat.kind);
+    self.top_level_step(&repeat.body);
+  }
+
+  /// Writes the single step that follows a comma at the top level.
+  fn top_level_step(&mut self, steps: &[HowToCommand]) {
+    match steps {
+      [HowToCommand::HighLevel(command)] => {
+        self.out.push_str(", ");
+        self.command(command);
+        self.end_top_level();
+      },
+      [HowToCommand::Conditional(inner)] => {
+        self.out.push_str(", if ");
+        self.top_level_branch(inner);
+      },
+      [HowToCommand::Loop(inner)] => {
+        self.out.push_str(", ");
+        self.top_level_loop(inner);
+      },
+      [HowToCommand::Binding(binding)] => {
+        self.out.push_str(", ");
+        self.binding(binding);
+        self.end_top_level();
+      },
+      [HowToCommand::Qualified(qualified)] => {
+        self.out.push_str(", ");
+        self.qualified(qualified);
+        self.end_top_level();
+      },
+      _ => {}
+    }
+  }
+
+  /// Low-level blocks are Rust, and are kept exactly as they were written.
+  fn low_level(&mut self, span: Span) {
+    self.bullet_start(span.start, "- ");
+    self.verbatim(span);
+    self.newline();
+  }
+
+  fn primitive(&mut self, steps: &[Command], span: Span) {
+    self.bullet_start(span.start, "-*");
+    self.newline();
+
+    for step in steps {
+      self.comments_before(step.span.start);
+      self.indent();
+      self.out.push_str("< ");
+      self.command(step);
+      self.newline();
+      self.indent();
+      self.out.push('>');
+      self.newline();
+    }
+
+    self.indent();
+    self.out.push_str("-*");
+    self.newline();
+  }
+
+  fn whatis(&mut self, whatis: &WhatIsStatement) {
+    self.signature(whatis.visibility, "whatis", &whatis.signature);
+
+    for command in whatis.body.iter() {
+      self.bullet(command);
+    }
+  }
+}
+
+fn is_command(node: &ParseNode) -> bool {
+  matches!(node,
+    ParseNode::Command(_) | ParseNode::Conditional(_) | ParseNode::Loop(_) |
+    ParseNode::Binding(_) | ParseNode::Qualified(_)
+  )
+}
+
+fn is_import(node: &ParseNode) -> bool {
+  matches!(node, ParseNode::Import(_))
+}
+
+fn is_query(node: &ParseNode) -> bool {
+  matches!(node, ParseNode::Query(_))
+}
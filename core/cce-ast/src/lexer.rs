@@ -18,13 +18,20 @@ Circe. If not, see <https://www.gnu.org/licenses/>.
 
 */
 
-use cce_stream::InputStream;
+use cce_diagnostics::{Diagnostic, Label, ToDiagnostic};
+use cce_stream::{InputStream, Span};
 
+use std::fmt;
 use thiserror::Error;
 
 pub struct Lexer<'s> {
   pub(crate) stream: InputStream<'s>,
-  pub(crate) peeked: Option<Token>
+  pub(crate) peeked: Option<SpannedToken>,
+  pub(crate) at_line_start: bool,
+  pub(crate) trailing_trivia: Vec<Trivia>,
+  pub(crate) failed: bool,
+  /// Every token lexed so far, if they are being kept for a syntax tree.
+  pub(crate) kept: Option<Vec<SpannedToken>>
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -32,6 +39,7 @@ pub enum Token {
   Identifier(String),
   Keyword(String),
   Literal(String),
+  Number(u64),
   Punctuation(char),
   LowLevelSequence(String),
   Newline,
@@ -40,22 +48,205 @@ pub enum Token {
   Percent
 }
 
+impl Token {
+  pub fn kind(&self) -> TokenKind {
+    match self {
+      Token::Identifier(_) => TokenKind::Identifier,
+      Token::Keyword(_) => TokenKind::Keyword,
+      Token::Literal(_) => TokenKind::Literal,
+      Token::Number(_) => TokenKind::Number,
+      Token::Punctuation(c) => TokenKind::Punctuation(*c),
+      Token::LowLevelSequence(_) => TokenKind::LowLevelSequence,
+      Token::Newline => TokenKind::Newline,
+      Token::Question => TokenKind::Question,
+      Token::Dot => TokenKind::Dot,
+      Token::Percent => TokenKind::Percent
+    }
+  }
+}
+
+impl fmt::Display for Token {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      Token::Identifier(ident) => write!(f, "identifier '{}'", ident),
+      Token::Keyword(kw) => write!(f, "keyword '{}'", kw),
+      Token::Literal(lit) => write!(f, "literal '{}'", lit),
+      Token::Number(n) => write!(f, "number {}", n),
+      _ => write!(f, "{}", self.kind())
+    }
+  }
+}
+
+/// The kind of a [`Token`], without its contents.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TokenKind {
+  Identifier,
+  Keyword,
+  Literal,
+  Number,
+  Punctuation(char),
+  LowLevelSequence,
+  Newline,
+  Question,
+  Dot,
+  Percent
+}
+
+impl fmt::Display for TokenKind {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      TokenKind::Identifier => write!(f, "identifier"),
+      TokenKind::Keyword => write!(f, "keyword"),
+      TokenKind::Literal => write!(f, "literal"),
+      TokenKind::Number => write!(f, "number"),
+      TokenKind::Punctuation(c) => write!(f, "'{}'", c),
+      TokenKind::LowLevelSequence => write!(f, "low-level block"),
+      TokenKind::Newline => write!(f, "newline"),
+      TokenKind::Question => write!(f, "'?'"),
+      TokenKind::Dot => write!(f, "'.'"),
+      TokenKind::Percent => write!(f, "'%'")
+    }
+  }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SpannedToken {
+  pub token: Token,
+  pub span: Span,
+  /// Comments between the previous token and this one.
+  pub trivia: Vec<Trivia>,
+  /// The whitespace before this token, if it is the first thing on its
+  /// line. Bullets use it to tell how deeply they are nested.
+  pub indent: Option<String>
+}
+
+/// Source text that doesn't change the meaning of a program, but is
+/// kept around so that tools like formatters don't lose it.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Trivia {
+  /// A `// ...` comment, up to the end of the line.
+  LineComment(String, Span),
+  /// A `/* ... */` comment, which may span several lines.
+  BlockComment(String, Span)
+}
+
+impl Trivia {
+  pub fn span(&self) -> Span {
+    match self {
+      Trivia::LineComment(_, span) => *span,
+      Trivia::BlockComment(_, span) => *span
+    }
+  }
+}
+
 #[derive(Error, Debug)]
 pub enum LexerError {
   #[error("{0}")]
   InputStreamError(#[from] cce_stream::InputStreamError),
   #[error("Unexpected end of stream")]
-  UnexpectedEndOfStream,
+  UnexpectedEndOfStream(Span),
   #[error("Unexpected character: {0}")]
-  UnexpectedCharacter(char)
+  UnexpectedCharacter(char, Span),
+  #[error("Invalid digit '{0}' in base {1} number")]
+  InvalidDigit(char, u32, Span),
+  #[error("Number has no digits")]
+  MissingDigits(Span),
+  #[error("Number is too large")]
+  NumberOverflow(Span),
+  #[error("Byte literals must contain exactly one ASCII character")]
+  InvalidByteLiteral(Span),
+  #[error("Unknown escape sequence: \\{0}")]
+  InvalidEscape(char, Span),
+  #[error("Invalid hex escape, expected two hex digits up to 7f")]
+  InvalidHexEscape(Span),
+  #[error("Invalid unicode escape, expected up to six hex digits in braces")]
+  InvalidUnicodeEscape(Span)
+}
+
+impl LexerError {
+  pub fn span(&self) -> Option<Span> {
+    match self {
+      LexerError::InputStreamError(_) => None,
+      LexerError::UnexpectedEndOfStream(span) => Some(*span),
+      LexerError::UnexpectedCharacter(_, span) => Some(*span),
+      LexerError::InvalidDigit(_, _, span) => Some(*span),
+      LexerError::MissingDigits(span) => Some(*span),
+      LexerError::NumberOverflow(span) => Some(*span),
+      LexerError::InvalidByteLiteral(span) => Some(*span),
+      LexerError::InvalidEscape(_, span) => Some(*span),
+      LexerError::InvalidHexEscape(span) => Some(*span),
+      LexerError::InvalidUnicodeEscape(span) => Some(*span)
+    }
+  }
+}
+
+impl ToDiagnostic for LexerError {
+  fn to_diagnostic(&self) -> Diagnostic {
+    match self {
+      LexerError::InputStreamError(err) => {
+        Diagnostic::error(err.to_string())
+          .with_code("E0001")
+      },
+      LexerError::UnexpectedEndOfStream(span) => {
+        Diagnostic::error("unexpected end of input")
+          .with_code("E0002")
+          .with_label(Label::primary(*span, "this is never closed"))
+      },
+      LexerError::UnexpectedCharacter(c, span) => {
+        Diagnostic::error(format!("unexpected character {:?}", c))
+          .with_code("E0003")
+          .with_label(Label::primary(*span, "not valid here"))
+      },
+      LexerError::InvalidDigit(c, radix, span) => {
+        Diagnostic::error(format!("invalid digit {:?} in base {} number", c, radix))
+          .with_code("E0004")
+          .with_label(Label::primary(*span, "not a valid digit"))
+      },
+      LexerError::MissingDigits(span) => {
+        Diagnostic::error("number has no digits")
+          .with_code("E0005")
+          .with_label(Label::primary(*span, "expected digits after this"))
+      },
+      LexerError::NumberOverflow(span) => {
+        Diagnostic::error("number is too large")
+          .with_code("E0006")
+          .with_label(Label::primary(*span, format!("must be at most {}", u64::MAX)))
+      },
+      LexerError::InvalidByteLiteral(span) => {
+        Diagnostic::error("invalid byte literal")
+          .with_code("E0007")
+          .with_label(Label::primary(*span, "must contain exactly one ASCII character"))
+      },
+      LexerError::InvalidEscape(c, span) => {
+        Diagnostic::error(format!("unknown escape sequence \\{}", c))
+          .with_code("E0008")
+          .with_label(Label::primary(*span, "unknown escape"))
+          .with_help("valid escapes are \\', \\\\, \\n, \\t, \\x.. and \\u{...}")
+      },
+      LexerError::InvalidHexEscape(span) => {
+        Diagnostic::error("invalid hex escape")
+          .with_code("E0009")
+          .with_label(Label::primary(*span, "expected two hex digits up to 7f"))
+      },
+      LexerError::InvalidUnicodeEscape(span) => {
+        Diagnostic::error("invalid unicode escape")
+          .with_code("E0010")
+          .with_label(Label::primary(*span, "expected a character code of up to six hex digits in braces"))
+      }
+    }
+  }
 }
 
 
 impl<'s> Lexer<'s> {
-  pub fn new(stream: InputStream) -> Lexer {
+  pub fn new(stream: InputStream<'s>) -> Lexer<'s> {
     Lexer {
       stream,
-      peeked: None
+      peeked: None,
+      at_line_start: true,
+      trailing_trivia: Vec::new(),
+      failed: false,
+      kept: None
     }
   }
 
@@ -83,29 +274,243 @@ impl<'s> Lexer<'s> {
     }
   }
 
-  fn create_string_literal(&mut self) -> Result<Token, LexerError> {
+  /// A span covering just the next character of the stream.
+  fn char_span(&mut self) -> Span {
+    let length: usize = self.stream.peek().map_or(0, char::len_utf8);
+    Span::new(self.stream.file, self.stream.pos, self.stream.pos + length)
+  }
+
+  /// Reads a quoted literal, the opening `'` already consumed. The
+  /// escapes `\'`, `\\`, `\n`, `\t`, `\x7f` and `\u{1F600}` are
+  /// replaced by the characters they stand for.
+  fn create_string_literal(&mut self, start: usize) -> Result<Token, LexerError> {
     let mut literal: String = String::new();
-    let mut c: Option<char> = self.stream.peek();
+    let mut error: Option<LexerError> = None;
 
+    // A bad escape doesn't end the literal, so that lexing can
+    // carry on after the closing quote.
     loop {
-      if let Some(ch) = c {
-        if ch == '\'' {
+      match self.stream.peek() {
+        Some('\'') => {
           self.stream.next();
           break;
-        } else {
+        },
+        Some('\\') => {
+          match self.create_escape() {
+            Ok(ch) => literal.push(ch),
+            Err(err) => { error.get_or_insert(err); }
+          }
+        },
+        Some(ch) => {
           literal.push(ch);
           self.stream.next();
-          c = self.stream.peek();
+        },
+        None => {
+          return Err(LexerError::UnexpectedEndOfStream(
+            Span::new(self.stream.file, start, self.stream.pos)
+          ));
         }
-      } else {
-        return Err(LexerError::UnexpectedEndOfStream);
       }
     };
 
+    match error {
+      Some(err) => Err(err),
+      None => Ok(Token::Literal(literal))
+    }
+  }
+
+  /// Reads an escape sequence starting at the `\`.
+  fn create_escape(&mut self) -> Result<char, LexerError> {
+    let start: usize = self.stream.pos;
+    self.stream.next();
+
+    let c: Option<char> = self.stream.peek();
+    if c.is_some_and(|c| c != '\n') {
+      self.stream.next();
+    }
+
+    let span = |lexer: &Self| Span::new(lexer.stream.file, start, lexer.stream.pos);
+
+    match c {
+      Some('\'') => Ok('\''),
+      Some('\\') => Ok('\\'),
+      Some('n') => Ok('\n'),
+      Some('t') => Ok('\t'),
+      Some('x') => {
+        let mut value: u32 = 0;
+
+        for _ in 0..2 {
+          match self.stream.peek().and_then(|c| c.to_digit(16)) {
+            Some(digit) => {
+              value = value * 16 + digit;
+              self.stream.next();
+            },
+            None => return Err(LexerError::InvalidHexEscape(span(self)))
+          }
+        }
+
+        if value > 0x7f {
+          return Err(LexerError::InvalidHexEscape(span(self)));
+        }
+
+        Ok(value as u8 as char)
+      },
+      Some('u') => {
+        if self.stream.peek() != Some('{') {
+          return Err(LexerError::InvalidUnicodeEscape(span(self)));
+        }
+        self.stream.next();
+
+        let mut value: u32 = 0;
+        let mut digits: usize = 0;
+
+        loop {
+          match self.stream.peek() {
+            Some('}') => {
+              self.stream.next();
+              break;
+            },
+            Some(c) if c.is_ascii_hexdigit() && digits < 6 => {
+              value = value * 16 + c.to_digit(16).unwrap();
+              digits += 1;
+              self.stream.next();
+            },
+            _ => return Err(LexerError::InvalidUnicodeEscape(span(self)))
+          }
+        }
+
+        match char::from_u32(value) {
+          Some(ch) if digits > 0 => Ok(ch),
+          _ => Err(LexerError::InvalidUnicodeEscape(span(self)))
+        }
+      },
+      Some(c) => Err(LexerError::InvalidEscape(c, span(self))),
+      None => Err(LexerError::UnexpectedEndOfStream(span(self)))
+    }
+  }
+
+  /// Reads a `'''` literal, the opening quotes already consumed. Its
+  /// text is taken as written, newlines included, except for a newline
+  /// right after the opening quotes.
+  fn create_raw_string_literal(&mut self, start: usize) -> Result<Token, LexerError> {
+    let mut literal: String = String::new();
+
+    if self.stream.peek() == Some('\n') {
+      self.stream.next();
+    }
+
+    loop {
+      match self.stream.peek() {
+        Some('\'') if self.stream.peek_n(1) == Some('\'') && self.stream.peek_n(2) == Some('\'') => {
+          self.stream.next();
+          self.stream.next();
+          self.stream.next();
+          break;
+        },
+        Some(ch) => {
+          literal.push(ch);
+          self.stream.next();
+        },
+        None => {
+          return Err(LexerError::UnexpectedEndOfStream(
+            Span::new(self.stream.file, start, self.stream.pos)
+          ));
+        }
+      }
+    }
+
     Ok(Token::Literal(literal))
   }
 
-  fn create_low_level_sequence(&mut self) -> Result<Token, LexerError> {
+  /// Reads a number, with an optional `0x`, `0o` or `0b` prefix and
+  /// any number of `_` separators between digits.
+  fn create_number(&mut self, start: usize) -> Result<Token, LexerError> {
+    let mut radix: u32 = 10;
+
+    if self.stream.peek() == Some('0') {
+      radix = match self.stream.peek_n(1) {
+        Some('x') => 16,
+        Some('o') => 8,
+        Some('b') => 2,
+        _ => 10
+      };
+
+      if radix != 10 {
+        self.stream.next();
+        self.stream.next();
+      }
+    }
+
+    let mut value: Option<u64> = Some(0);
+    let mut digits: usize = 0;
+    let mut invalid: Option<(char, Span)> = None;
+
+    // Letters are consumed too, so that `12ab` is one bad number
+    // rather than a number followed by a word.
+    while let Some(ch) = self.stream.peek() {
+      if ch == '_' {
+        self.stream.next();
+        continue;
+      }
+
+      if !ch.is_alphanumeric() {
+        break;
+      }
+
+      let span: Span = self.char_span();
+      self.stream.next();
+
+      match ch.to_digit(radix) {
+        Some(digit) => {
+          digits += 1;
+          value = value
+            .and_then(|v| v.checked_mul(radix as u64))
+            .and_then(|v| v.checked_add(digit as u64));
+        },
+        None => {
+          invalid = invalid.or(Some((ch, span)));
+        }
+      }
+    }
+
+    let span: Span = Span::new(self.stream.file, start, self.stream.pos);
+
+    if let Some((ch, span)) = invalid {
+      return Err(LexerError::InvalidDigit(ch, radix, span));
+    }
+
+    if digits == 0 {
+      return Err(LexerError::MissingDigits(span));
+    }
+
+    match value {
+      Some(value) => Ok(Token::Number(value)),
+      None => Err(LexerError::NumberOverflow(span))
+    }
+  }
+
+  /// Reads a byte literal like `b'a'`, which is the number of its character.
+  fn create_byte_literal(&mut self, start: usize) -> Result<Token, LexerError> {
+    self.stream.next();
+    self.stream.next();
+
+    let literal: Token = self.create_string_literal(start)?;
+    let span: Span = Span::new(self.stream.file, start, self.stream.pos);
+
+    match literal {
+      Token::Literal(text) => {
+        let mut chars = text.chars();
+
+        match (chars.next(), chars.next()) {
+          (Some(c), None) if c.is_ascii() => Ok(Token::Number(c as u64)),
+          _ => Err(LexerError::InvalidByteLiteral(span))
+        }
+      },
+      _ => Err(LexerError::InvalidByteLiteral(span))
+    }
+  }
+
+  fn create_low_level_sequence(&mut self, start: usize) -> Result<Token, LexerError> {
     let mut sequence: String = String::new();
     let mut c: Option<char> = self.stream.peek();
     let mut dollars = 0;
@@ -119,6 +524,8 @@ impl<'s> Lexer<'s> {
           if dollars == 2 {
             break;
           }
+
+          c = self.stream.peek();
         } else {
           if dollars == 1 {
             sequence.push('$');
@@ -129,60 +536,134 @@ impl<'s> Lexer<'s> {
           self.stream.next();
           c = self.stream.peek();
         }
+      } else {
+        return Err(LexerError::UnexpectedEndOfStream(
+          Span::new(self.stream.file, start, self.stream.pos)
+        ));
       }
     };
 
     Ok(Token::LowLevelSequence(sequence))
   }
 
-  // TODO: Move this to an iterator
-  pub fn next(&mut self) -> Result<Option<Token>, LexerError> {
-    if self.peeked.is_some() {
-      let tok = self.peeked.clone();
-      self.peeked = None;
-      return Ok(tok);
-    };
+  /// Skips whitespace and collects comments up to the next token, along
+  /// with the indentation of the next token if it starts a line. A line
+  /// holding nothing but comments takes its newline with it, so that it
+  /// doesn't count as a blank line.
+  fn skip_trivia(&mut self) -> Result<(Vec<Trivia>, Option<String>), LexerError> {
+    let mut trivia: Vec<Trivia> = Vec::new();
+    let mut after_comment: bool = false;
+    let mut indent: String = String::new();
 
-    let mut c: char = match self.stream.peek() {
-      Some(c) => c,
-      None => {
-        return Ok(None)
+    loop {
+      indent.clear();
+
+      while let Some(c) = self.stream.peek().filter(|c| c.is_whitespace() && *c != '\n') {
+        indent.push(c);
+        self.stream.next();
       }
-    };
 
-    while c.is_whitespace() && c != '\n' {
+      let comment: Trivia = match (self.stream.peek(), self.stream.peek_n(1)) {
+        (Some('/'), Some('/')) => self.create_line_comment(),
+        (Some('/'), Some('*')) => self.create_block_comment()?,
+        (Some('\n'), _) if self.at_line_start && after_comment => {
+          self.stream.next();
+          after_comment = false;
+          continue;
+        },
+        _ => break
+      };
+
+      trivia.push(comment);
+      after_comment = true;
+    }
+
+    // A comment before the token on the same line means the token doesn't
+    // start the line, and neither does anything after the first token.
+    let indent: Option<String> = (self.at_line_start && !after_comment).then_some(indent);
+
+    Ok((trivia, indent))
+  }
+
+  fn create_line_comment(&mut self) -> Trivia {
+    let start: usize = self.stream.pos;
+    let mut text: String = String::new();
+
+    while let Some(c) = self.stream.peek() {
+      if c == '\n' {
+        break;
+      }
+
+      text.push(c);
       self.stream.next();
-      c = match self.stream.peek() {
-        Some(c) => c,
+    }
+
+    Trivia::LineComment(text, Span::new(self.stream.file, start, self.stream.pos))
+  }
+
+  fn create_block_comment(&mut self) -> Result<Trivia, LexerError> {
+    let start: usize = self.stream.pos;
+    let mut text: String = String::new();
+
+    loop {
+      match self.stream.peek() {
+        Some('*') if self.stream.peek_n(1) == Some('/') && text.len() >= 2 => {
+          text.push_str("*/");
+          self.stream.next();
+          self.stream.next();
+          break;
+        },
+        Some(c) => {
+          text.push(c);
+          self.stream.next();
+        },
         None => {
-          return Ok(None)
+          return Err(LexerError::UnexpectedEndOfStream(
+            Span::new(self.stream.file, start, self.stream.pos)
+          ));
         }
-      };
-    };
+      }
+    }
+
+    Ok(Trivia::BlockComment(text, Span::new(self.stream.file, start, self.stream.pos)))
+  }
 
+  fn create_token(&mut self, c: char, start: usize) -> Result<Token, LexerError> {
     match c {
+      'b' if self.stream.peek_n(1) == Some('\'') => {
+        self.create_byte_literal(start)
+      },
+      '0'..='9' => {
+        self.create_number(start)
+      },
       'a'..='z' | 'A'..='Z' | '_' => {
-        Ok(Some(self.create_ident_or_keyword()?))
+        self.create_ident_or_keyword()
+      },
+      '\'' if self.stream.peek_n(1) == Some('\'') && self.stream.peek_n(2) == Some('\'') => {
+        self.stream.next();
+        self.stream.next();
+        self.stream.next();
+        self.create_raw_string_literal(start)
       },
       '\'' => {
         self.stream.next();
-        Ok(Some(self.create_string_literal()?))
+        self.create_string_literal(start)
       },
-      '-' | '|' => {
+      '-' | '|' | '*' | '<' | '>' | ':' | ',' => {
         self.stream.next();
-        Ok(Some(Token::Punctuation(c)))
+        Ok(Token::Punctuation(c))
       },
       '.' => {
         self.stream.next();
-        Ok(Some(Token::Dot))
+        Ok(Token::Dot)
       },
       '%' => {
         self.stream.next();
-        Ok(Some(Token::Percent))
+        Ok(Token::Percent)
       },
       '?' => {
         self.stream.next();
-        Ok(Some(Token::Question))
+        Ok(Token::Question)
       },
       '$' => {
         self.stream.next();
@@ -191,31 +672,109 @@ impl<'s> Lexer<'s> {
           if ch == '$' {
             self.stream.next();
           } else {
-            return Err(LexerError::UnexpectedCharacter(ch));
+            return Err(LexerError::UnexpectedCharacter(ch, self.char_span()));
           }
         }
 
-        Ok(Some(self.create_low_level_sequence()?))
+        self.create_low_level_sequence(start)
       },
       '\n' => {
         self.stream.next();
-        Ok(Some(Token::Newline))
+        Ok(Token::Newline)
       },
       _ => {
-        Err(LexerError::UnexpectedCharacter(c))
+        let span: Span = self.char_span();
+        self.stream.next();
+        Err(LexerError::UnexpectedCharacter(c, span))
+      }
+    }
+  }
+
+  /// Lexes the next token. Unlike the [`Iterator`] implementation, this
+  /// can be called again after an error to carry on past the bad input.
+  pub fn next_token(&mut self) -> Result<Option<SpannedToken>, LexerError> {
+    if self.peeked.is_some() {
+      return Ok(self.peeked.take());
+    };
+
+    let (trivia, indent) = self.skip_trivia().map_err(|err| self.stream_error_or(err))?;
+
+    let c: char = match self.stream.peek() {
+      Some(c) => c,
+      None => {
+        if let Some(err) = self.stream.take_error() {
+          return Err(err.into());
+        }
+
+        self.trailing_trivia.extend(trivia);
+        return Ok(None)
       }
+    };
+
+    let start: usize = self.stream.pos;
+    let token: Token = self.create_token(c, start).map_err(|err| self.stream_error_or(err))?;
+    self.at_line_start = token == Token::Newline;
+
+    let token: SpannedToken = SpannedToken {
+      token,
+      span: Span::new(self.stream.file, start, self.stream.pos),
+      trivia,
+      indent
+    };
+
+    if let Some(kept) = &mut self.kept {
+      kept.push(token.clone());
     }
+
+    Ok(Some(token))
   }
 
-  pub fn peek(&mut self) -> Result<Option<Token>, LexerError> {
+  /// A token cut short by a failed read is reported as the read error,
+  /// not as whatever the truncated input looks like.
+  fn stream_error_or(&mut self, err: LexerError) -> LexerError {
+    match self.stream.take_error() {
+      Some(stream_err) => stream_err.into(),
+      None => err
+    }
+  }
+
+  /// Comments after the last token of the input, which have no token
+  /// to be attached to.
+  pub fn trailing_trivia(&self) -> &[Trivia] {
+    &self.trailing_trivia
+  }
+
+  pub fn peek(&mut self) -> Result<Option<SpannedToken>, LexerError> {
     if self.peeked.is_none() {
-      self.peeked = self.next()?;
+      self.peeked = self.next_token()?;
     };
 
     Ok(self.peeked.clone())
   }
 }
 
+/// Yields tokens until the end of the input or the first error,
+/// after which it only yields `None`.
+impl<'s> Iterator for Lexer<'s> {
+  type Item = Result<SpannedToken, LexerError>;
+
+  fn next(&mut self) -> Option<Self::Item> {
+    if self.failed {
+      return None;
+    }
+
+    match self.next_token() {
+      Ok(token) => token.map(Ok),
+      Err(err) => {
+        self.failed = true;
+        Some(Err(err))
+      }
+    }
+  }
+}
+
+impl<'s> std::iter::FusedIterator for Lexer<'s> {}
+
 impl<'s> From<&'s str> for Lexer<'s> {
   fn from(s: &'s str) -> Lexer<'s> {
     Lexer::new(InputStream::new(s))
@@ -0,0 +1,123 @@
+/*
+
+Copyright (C) 2023 Carlos Kieliszewski
+
+This file is part of the Circe Project.
+
+Circe is free software: you can redistribute it and/or modify it under
+the terms of the GNU General Public License as published by the Free
+Software Foundation, either version 3 of the License, or (at your option)
+any later version.
+
+Circe is distributed in the hope that it will be useful, but WITHOUT ANY
+WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+FOR A PARTICULAR PURPOSE. See the GNU General Public License for more details.
+
+You should have received a copy of the GNU General Public License along with
+Circe. If not, see <https://www.gnu.org/licenses/>. 
+
+*/
+
+
+//! Sorts the words of commands into classes, so that "a string" and
+//! "the string" can be told apart from "to string" and still be seen as
+//! the same thing.
+
+use std::collections::HashMap;
+use std::fmt;
+
+
+/// What part a word plays in a phrase.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum WordClass {
+  /// `a`, `an`, `the`: which of something is meant, not what it is.
+  Article,
+  /// `to`, `from`, `with`: how the rest of a phrase relates to a command.
+  Preposition,
+  /// `it`, `them`: something mentioned before.
+  Pronoun,
+  /// Every other word, the ones that say what a phrase is about.
+  Content
+}
+
+impl fmt::Display for WordClass {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      WordClass::Article => write!(f, "article"),
+      WordClass::Preposition => write!(f, "preposition"),
+      WordClass::Pronoun => write!(f, "pronoun"),
+      WordClass::Content => write!(f, "content word")
+    }
+  }
+}
+
+const ARTICLES: &[&str] = &["a", "an", "the"];
+
+const PREPOSITIONS: &[&str] = &[
+  "about", "after", "as", "at", "before", "between", "by", "for", "from",
+  "in", "into", "of", "on", "onto", "over", "through", "to", "under",
+  "until", "with", "without"
+];
+
+const PRONOUNS: &[&str] = &["it", "its", "itself", "them", "they", "these", "this", "those"];
+
+/// The word lists used to classify words. Words are matched without
+/// regard to case, and any word that isn't listed is a content word.
+///
+/// [`Lexicon::default`] has lists for English, which can be added to
+/// or taken from, while [`Lexicon::new`] starts out empty.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Lexicon {
+  pub(crate) words: HashMap<String, WordClass>
+}
+
+impl Lexicon {
+  pub fn new() -> Self {
+    Self {
+      words: HashMap::new()
+    }
+  }
+
+  /// Adds `word` to the list for `class`, taking it off any other list.
+  pub fn insert(&mut self, word: &str, class: WordClass) {
+    self.words.insert(word.to_lowercase(), class);
+  }
+
+  /// Takes `word` off its list, making it a content word.
+  pub fn remove(&mut self, word: &str) {
+    self.words.remove(&word.to_lowercase());
+  }
+
+  pub fn classify(&self, word: &str) -> WordClass {
+    self.words.get(&word.to_lowercase()).copied().unwrap_or(WordClass::Content)
+  }
+
+  pub fn is_article(&self, word: &str) -> bool {
+    self.classify(word) == WordClass::Article
+  }
+
+  /// Every listed word of a class, in no particular order.
+  pub fn words(&self, class: WordClass) -> impl Iterator<Item = &str> + '_ {
+    self.words.iter().filter(move |(_, c)| **c == class).map(|(word, _)| word.as_str())
+  }
+}
+
+impl Default for Lexicon {
+  fn default() -> Self {
+    let mut lexicon: Lexicon = Lexicon::new();
+
+    let lists = [
+      (ARTICLES, WordClass::Article),
+      (PREPOSITIONS, WordClass::Preposition),
+      (PRONOUNS, WordClass::Pronoun)
+    ];
+
+    for (words, class) in lists {
+      for word in words.iter() {
+        lexicon.insert(word, class);
+      }
+    }
+
+    lexicon
+  }
+}
@@ -19,11 +19,19 @@ Circe. If not, see <https://www.gnu.org/licenses/>.
 */
 
 mod lexer;
+mod lexicon;
 mod parser;
-
-pub use lexer::{Lexer, Token, LexerError};
+pub mod cst;
+mod format;
+mod loader;
+
+pub use format::format;
+pub use lexer::{Lexer, Token, TokenKind, SpannedToken, Trivia, LexerError};
+pub use lexicon::{Lexicon, WordClass};
+pub use loader::{Loader, LoadedProgram, LoadedFile, LoadError};
 pub use parser::{
-  Parser, ParseNode, ParserError, Command,
-  CommandComponent, HowToStatement, WhatIsStatement,
-  HowToCommand
+  Parser, ParseNode, Program, ParserError, Command,
+  CommandComponent, Slot, HowToStatement, WhatIsStatement,
+  HowToCommand, Conditional, Loop, LoopKind, Binding, Import, ImportKind, Module, Qualified,
+  Query, QueryKind, Visibility
 };
\ No newline at end of file
@@ -0,0 +1,368 @@
+/*
+
+Copyright (C) 2023 Carlos Kieliszewski
+
+This file is part of the Circe Project.
+
+Circe is free software: you can redistribute it and/or modify it under
+the terms of the GNU General Public License as published by the Free
+Software Foundation, either version 3 of the License, or (at your option)
+any later version.
+
+Circe is distributed in the hope that it will be useful, but WITHOUT ANY
+WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+FOR A PARTICULAR PURPOSE. See the GNU General Public License for more details.
+
+You should have received a copy of the GNU General Public License along with
+Circe. If not, see <https://www.gnu.org/licenses/>. 
+
+*/
+
+
+//! Loading programs spread over several files.
+
+use cce_diagnostics::{Diagnostic, Label, ToDiagnostic};
+use cce_stream::{FileId, FileName, SourceMap, Span};
+use thiserror::Error;
+
+use crate::lexer::Lexer;
+use crate::lexicon::Lexicon;
+use crate::parser::{Import, ImportKind, ParseNode, Parser, ParserError};
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+
+
+#[derive(Error, Debug)]
+pub enum LoadError {
+  #[error(transparent)]
+  Parse(#[from] ParserError),
+  #[error("Couldn't find '{path}'")]
+  NotFound {
+    path: String,
+    span: Span,
+    /// Every place the file was looked for.
+    searched: Vec<PathBuf>
+  },
+  #[error("Couldn't read '{path}': {message}")]
+  Unreadable {
+    path: String,
+    /// The import that refers to the file, or `None` if it was the file
+    /// the program was loaded from.
+    span: Option<Span>,
+    message: String
+  },
+  #[error("'{path}' is imported in a cycle")]
+  Cycle {
+    path: String,
+    span: Span,
+    /// The paths and spans of the other imports of the cycle, starting
+    /// from the one in the file it leads back to.
+    chain: Vec<(String, Span)>
+  }
+}
+
+impl LoadError {
+  pub fn span(&self) -> Option<Span> {
+    match self {
+      LoadError::Parse(err) => err.span(),
+      LoadError::NotFound { span, .. } => Some(*span),
+      LoadError::Unreadable { span, .. } => *span,
+      LoadError::Cycle { span, .. } => Some(*span)
+    }
+  }
+}
+
+impl ToDiagnostic for LoadError {
+  fn to_diagnostic(&self) -> Diagnostic {
+    match self {
+      LoadError::Parse(err) => err.to_diagnostic(),
+      LoadError::NotFound { path, span, searched } => {
+        let diagnostic = Diagnostic::error(format!("couldn't find '{}'", path))
+          .with_code("E0300")
+          .with_label(Label::primary(*span, "imported here"));
+
+        searched.iter().fold(diagnostic, |diagnostic, place| {
+          diagnostic.with_note(format!("looked for {}", place.display()))
+        })
+      },
+      LoadError::Unreadable { path, span, message } => {
+        let diagnostic = Diagnostic::error(format!("couldn't read '{}': {}", path, message))
+          .with_code("E0301");
+
+        match span {
+          Some(span) => diagnostic.with_label(Label::primary(*span, "imported here")),
+          None => diagnostic
+        }
+      },
+      LoadError::Cycle { path, span, chain } => {
+        let cycle: Vec<String> = std::iter::once(path)
+          .chain(chain.iter().map(|(path, _)| path))
+          .chain(std::iter::once(path))
+          .map(|path| format!("'{}'", path))
+          .collect();
+
+        let diagnostic = Diagnostic::error(format!("'{}' is imported in a cycle", path))
+          .with_code("E0302")
+          .with_label(Label::primary(*span, "this leads back to a file that is still being loaded"))
+          .with_note(format!("the cycle is {}", cycle.join(" -> ")));
+
+        chain.iter().fold(diagnostic, |diagnostic, (_, import)| {
+          diagnostic.with_label(Label::secondary(*import, "part of the cycle"))
+        })
+      }
+    }
+  }
+}
+
+/// A program gathered from all of its files.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LoadedProgram {
+  /// The nodes of the program, each import replaced by the nodes it
+  /// brings in. The file each one came from is in its span.
+  pub nodes: Vec<ParseNode>,
+  /// Every file that was loaded, in the order they were first imported.
+  pub files: Vec<LoadedFile>
+}
+
+/// Where a file of a program came from.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LoadedFile {
+  pub id: FileId,
+  /// The import that first brought the file in, or `None` for the file
+  /// the program was loaded from.
+  pub imported_at: Option<Span>
+}
+
+impl LoadedProgram {
+  pub fn file(&self, id: FileId) -> Option<&LoadedFile> {
+    self.files.iter().find(|file| file.id == id)
+  }
+
+  /// The imports that led to a file being loaded, the one in the file
+  /// the program was loaded from last.
+  pub fn import_chain(&self, id: FileId) -> Vec<Span> {
+    let mut chain: Vec<Span> = Vec::new();
+    let mut imported_at: Option<Span> = self.file(id).and_then(|file| file.imported_at);
+
+    while let Some(span) = imported_at {
+      chain.push(span);
+      imported_at = self.file(span.file).and_then(|file| file.imported_at);
+    }
+
+    chain
+  }
+}
+
+/// Follows the `use` and `include` statements of a program, parsing
+/// every file it imports.
+///
+/// A path starting with `./` or `../` is found relative to the file that
+/// imports it. Any other relative path is looked for next to that file,
+/// and then in each directory of the search path in turn. A path without
+/// an extension is given `.cce`.
+///
+/// Each file is only loaded once, so a file imported a second time, by
+/// the same file or another, brings nothing more in. The exception is an
+/// `include` of a file that was only `use`d before, which brings in the
+/// rest of it.
+#[derive(Debug, Clone, Default)]
+pub struct Loader {
+  search_path: Vec<PathBuf>,
+  lexicon: Lexicon
+}
+
+impl Loader {
+  pub fn new() -> Self {
+    Loader::default()
+  }
+
+  pub fn with_lexicon(mut self, lexicon: Lexicon) -> Self {
+    self.lexicon = lexicon;
+    self
+  }
+
+  /// Adds a directory to the end of the search path.
+  pub fn search(mut self, dir: impl Into<PathBuf>) -> Self {
+    self.search_path.push(dir.into());
+    self
+  }
+
+  pub fn search_path(&self) -> &[PathBuf] {
+    &self.search_path
+  }
+
+  /// Loads the program starting at `root`, which must already be in
+  /// `sources`. Imported files are added to `sources` as they are found.
+  ///
+  /// Like [`Parser::parse_with_recovery`], this keeps going after errors,
+  /// so the program is returned along with all of them.
+  pub fn load(&self, sources: &mut SourceMap, root: FileId) -> (LoadedProgram, Vec<LoadError>) {
+    let mut load = Load {
+      loader: self,
+      sources,
+      files: vec![LoadedFile { id: root, imported_at: None }],
+      loaded: HashMap::new(),
+      stack: Vec::new(),
+      errors: Vec::new()
+    };
+
+    let key: Option<PathBuf> = load.key(root);
+    if let Some(key) = &key {
+      load.loaded.insert(key.clone(), Vec::new());
+    }
+
+    let nodes: Vec<ParseNode> = load.file(root, key, None);
+    let program = LoadedProgram { nodes, files: load.files };
+
+    (program, load.errors)
+  }
+
+  /// Finds the file an import refers to, or returns everywhere it was
+  /// looked for.
+  pub fn find(&self, path: &str, dir: &Path) -> Result<PathBuf, Vec<PathBuf>> {
+    let mut path: PathBuf = PathBuf::from(path);
+    if path.extension().is_none() {
+      path.set_extension("cce");
+    }
+
+    // `./` is dropped so that it doesn't show up in the middle of paths.
+    let join = |dir: &Path| -> PathBuf {
+      dir.join(&path).components().filter(|component| *component != Component::CurDir).collect()
+    };
+
+    let candidates: Vec<PathBuf> = if path.is_absolute() {
+      vec![path.clone()]
+    } else if matches!(path.components().next(), Some(Component::CurDir | Component::ParentDir)) {
+      vec![join(dir)]
+    } else {
+      std::iter::once(join(dir))
+        .chain(self.search_path.iter().map(|search| join(search)))
+        .collect()
+    };
+
+    match candidates.iter().find(|candidate| candidate.is_file()) {
+      Some(found) => Ok(found.clone()),
+      None => Err(candidates)
+    }
+  }
+}
+
+struct Load<'l> {
+  loader: &'l Loader,
+  sources: &'l mut SourceMap,
+  files: Vec<LoadedFile>,
+  /// The files loaded so far, by their canonical paths, each with the
+  /// nodes a `use` of it left out, for a later `include` to bring in.
+  loaded: HashMap<PathBuf, Vec<ParseNode>>,
+  /// The files being loaded, each with the import that brought it in,
+  /// the innermost last.
+  stack: Vec<(Option<PathBuf>, Option<Import>)>,
+  errors: Vec<LoadError>
+}
+
+impl Load<'_> {
+  /// What identifies a file on disk, whatever path it was reached by.
+  /// Virtual files can't be imported, so they have none.
+  fn key(&self, id: FileId) -> Option<PathBuf> {
+    match &self.sources.file(id)?.name {
+      FileName::Real(path) => fs::canonicalize(path).ok(),
+      FileName::Virtual(_) => None
+    }
+  }
+
+  /// The directory the imports of a file are relative to.
+  fn dir(&self, id: FileId) -> PathBuf {
+    match self.sources.file(id).map(|file| &file.name) {
+      Some(FileName::Real(path)) => path.parent().map_or_else(|| PathBuf::from("."), Path::to_path_buf),
+      _ => PathBuf::from(".")
+    }
+  }
+
+  fn file(&mut self, id: FileId, key: Option<PathBuf>, imported_at: Option<&Import>) -> Vec<ParseNode> {
+    let parsed = self.sources.stream(id).map(|stream| {
+      Parser::with_lexicon(Lexer::new(stream), self.loader.lexicon.clone()).parse_with_recovery()
+    });
+
+    let (nodes, errors) = match parsed {
+      Ok(parsed) => parsed,
+      Err(err) => {
+        let path: String = self.sources.name(id).unwrap_or_default().to_string();
+        self.errors.push(LoadError::Unreadable { path, span: imported_at.map(|import| import.span), message: err.to_string() });
+        return Vec::new();
+      }
+    };
+
+    self.errors.extend(errors.into_iter().map(LoadError::Parse));
+    self.stack.push((key, imported_at.cloned()));
+
+    let mut merged: Vec<ParseNode> = Vec::new();
+    for node in nodes {
+      match node {
+        ParseNode::Import(import) => merged.extend(self.import(&import)),
+        node => merged.push(node)
+      }
+    }
+
+    self.stack.pop();
+    merged
+  }
+
+  fn import(&mut self, import: &Import) -> Vec<ParseNode> {
+    let dir: PathBuf = self.dir(import.span.file);
+
+    let path: PathBuf = match self.loader.find(&import.path, &dir) {
+      Ok(path) => path,
+      Err(searched) => {
+        self.errors.push(LoadError::NotFound { path: import.path.clone(), span: import.path_span, searched });
+        return Vec::new();
+      }
+    };
+
+    let key: PathBuf = fs::canonicalize(&path).unwrap_or_else(|_| path.clone());
+
+    if let Some(at) = self.stack.iter().position(|(loading, _)| loading.as_ref() == Some(&key)) {
+      let chain: Vec<(String, Span)> = self.stack[at + 1..].iter()
+        .filter_map(|(_, import)| import.as_ref().map(|import| (import.path.clone(), import.span)))
+        .collect();
+
+      self.errors.push(LoadError::Cycle { path: import.path.clone(), span: import.span, chain });
+      return Vec::new();
+    }
+
+    if let Some(left_out) = self.loaded.get_mut(&key) {
+      return match import.kind {
+        ImportKind::Include => std::mem::take(left_out),
+        ImportKind::Use => Vec::new()
+      };
+    }
+
+    self.loaded.insert(key.clone(), Vec::new());
+
+    let id: FileId = match self.sources.add_file(&path) {
+      Ok(id) => id,
+      Err(err) => {
+        self.errors.push(LoadError::Unreadable { path: import.path.clone(), span: Some(import.span), message: err.to_string() });
+        return Vec::new();
+      }
+    };
+
+    self.files.push(LoadedFile { id, imported_at: Some(import.span) });
+    let nodes: Vec<ParseNode> = self.file(id, Some(key.clone()), Some(import));
+
+    match import.kind {
+      ImportKind::Include => nodes,
+      // The header stays, so that the file's descriptions are still in
+      // its module.
+      ImportKind::Use => {
+        let (used, left_out): (Vec<ParseNode>, Vec<ParseNode>) = nodes.into_iter().partition(|node| {
+          matches!(node, ParseNode::HowToStatement(_) | ParseNode::WhatIsStatement(_) | ParseNode::Module(_))
+        });
+
+        self.loaded.insert(key, left_out);
+        used
+      }
+    }
+  }
+}
@@ -19,53 +19,388 @@ Circe. If not, see <https://www.gnu.org/licenses/>.
 */
 
 
-use crate::lexer::{Lexer, Token, LexerError};
-use cce_llast::{ast::*, parse};
+use crate::lexer::{Lexer, Token, TokenKind, SpannedToken, LexerError};
+use crate::lexicon::{Lexicon, WordClass};
+use cce_diagnostics::{Diagnostic, Label, ToDiagnostic};
+use cce_llast::{ast::*, parse, LLError};
+use cce_stream::Span;
 use circelang_hash::CirceHash;
 
 use thiserror::Error;
 
 pub struct Parser<'s> {
   pub(crate) lexer: Lexer<'s>,
-  pub(crate) peeked: Option<ParseNode>
+  pub(crate) peeked: Option<ParseNode>,
+  pub(crate) prev_span: Span,
+  pub(crate) failed: bool,
+  pub(crate) lexicon: Lexicon,
+  /// A clause read ahead that turned out to start the next statement.
+  pub(crate) clause: Option<Clause>
+}
+
+/// The words of a statement or step up to its first `,`, `.`, `?`, `|`
+/// or newline. Words like `if` and `repeat` only start their statements
+/// when the rest of the clause fits them, so the clause is read before
+/// deciding what it is.
+pub(crate) struct Clause {
+  start: Span,
+  components: Vec<CommandComponent>
+}
+
+impl Clause {
+  /// The `index`th component if it is a word, or an empty string.
+  fn word(&self, index: usize) -> &str {
+    match self.components.get(index) {
+      Some(CommandComponent::Keyword(word, _)) => word,
+      _ => ""
+    }
+  }
+
+  /// The word heading the clause, if the rest of the clause, ended by
+  /// `end`, fits the statement it starts. Otherwise the clause is just
+  /// the start of a command, so that `repeat the song.` is still one. A
+  /// comma after a loop that isn't quite right still makes it a loop, so
+  /// that it gets an error saying what's wrong with it.
+  fn head(&self, end: Option<&Token>) -> Option<String> {
+    let rest: &[CommandComponent] = self.components.get(1..).unwrap_or_default();
+    let ends_line: bool = matches!(end, None | Some(Token::Newline));
+    let comma: bool = end == Some(&Token::Punctuation(','));
+
+    let fits: bool = match self.word(0) {
+      "for" => comma || (self.word(1) == "each" && matches!(rest.get(1), Some(CommandComponent::Slot(..)))),
+      "repeat" => comma || (rest.len() > 1 && self.word(self.components.len() - 1) == "times"),
+      "if" | "while" => !rest.is_empty() && (ends_line || comma),
+      "otherwise" => self.word(1) == "if" || (rest.is_empty() && (ends_line || comma)),
+      "module" => matches!(rest, [CommandComponent::Keyword(..)]) && (ends_line || end == Some(&Token::Dot)),
+      "within" => matches!(rest, [CommandComponent::Keyword(..)]) && comma,
+      "private" => self.word(1) == "howto" || self.word(1) == "whatis",
+      _ => false
+    };
+
+    fits.then(|| self.word(0).to_string())
+  }
+
+  /// The components after the first `count`.
+  fn skip(self, count: usize) -> Vec<CommandComponent> {
+    self.components.into_iter().skip(count).collect()
+  }
+}
+
+/// Every top-level node of a source file, in order.
+#[derive(Debug, Clone, PartialEq, CirceHash)]
+pub struct Program {
+  pub nodes: Vec<ParseNode>
+}
+
+impl IntoIterator for Program {
+  type Item = ParseNode;
+  type IntoIter = std::vec::IntoIter<ParseNode>;
+
+  fn into_iter(self) -> Self::IntoIter {
+    self.nodes.into_iter()
+  }
 }
 
 #[derive(Debug, Clone, PartialEq, CirceHash)]
 pub enum ParseNode {
   Command(Command),
+  Conditional(Conditional),
+  Loop(Loop),
+  Binding(Binding),
+  Import(Import),
+  Module(Module),
+  Qualified(Qualified),
+  Query(Query),
   HowToStatement(HowToStatement),
   WhatIsStatement(WhatIsStatement)
 }
 
+impl ParseNode {
+  pub fn span(&self) -> Span {
+    match self {
+      ParseNode::Command(command) => command.span,
+      ParseNode::Conditional(conditional) => conditional.span,
+      ParseNode::Loop(repeat) => repeat.span,
+      ParseNode::Binding(binding) => binding.span,
+      ParseNode::Import(import) => import.span,
+      ParseNode::Module(module) => module.span,
+      ParseNode::Qualified(qualified) => qualified.span,
+      ParseNode::Query(query) => query.span,
+      ParseNode::HowToStatement(howto) => howto.span,
+      ParseNode::WhatIsStatement(whatis) => whatis.span
+    }
+  }
+}
+
 #[derive(Debug, Clone, PartialEq, CirceHash)]
 pub struct Command {
   pub components: Vec<CommandComponent>,
-  pub modifiers: Vec<Vec<CommandComponent>>
+  pub modifiers: Vec<Vec<CommandComponent>>,
+  /// Bullets indented under this one, for a step made of smaller steps
+  /// or a description with more detail. Always empty outside of bodies.
+  pub children: Vec<Command>,
+  pub span: Span
 }
 
 #[derive(Debug, Clone, PartialEq, CirceHash)]
 pub enum CommandComponent {
-  Literal(String),
-  Keyword(String),
-  Slot(String)
+  Literal(String, Span),
+  Number(u64, Span),
+  Keyword(String, Span),
+  Slot(Slot, Span)
+}
+
+impl CommandComponent {
+  pub fn span(&self) -> Span {
+    match self {
+      CommandComponent::Literal(_, span) => *span,
+      CommandComponent::Number(_, span) => *span,
+      CommandComponent::Keyword(_, span) => *span,
+      CommandComponent::Slot(_, span) => *span
+    }
+  }
+
+  /// The class of a word, or `None` for literals, numbers and slots.
+  pub fn word_class(&self, lexicon: &Lexicon) -> Option<WordClass> {
+    match self {
+      CommandComponent::Keyword(word, _) => Some(lexicon.classify(word)),
+      _ => None
+    }
+  }
+}
+
+/// A `%name` placeholder, optionally constrained to a kind of value, as
+/// in `%text: a string` or `%n: a number`.
+#[derive(Debug, Clone, PartialEq, CirceHash)]
+pub struct Slot {
+  pub name: String,
+  /// The kind as written after the `:`: an optional article followed by
+  /// a single word or literal, like `a string` or `a 'file stream'`.
+  pub kind: Option<Vec<CommandComponent>>
+}
+
+/// Who can use a howto or whatis statement.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, CirceHash)]
+pub enum Visibility {
+  /// Any file that loads this one.
+  #[default]
+  Public,
+  /// Only the file it is in, when written `private howto ...?`.
+  Private
 }
 
 #[derive(Debug, Clone, PartialEq, CirceHash)]
 pub struct HowToStatement {
+  pub visibility: Visibility,
   pub signature: Vec<CommandComponent>,
-  pub body: Vec<HowToCommand>
+  pub body: Vec<HowToCommand>,
+  pub span: Span
 }
 
 #[derive(Debug, Clone, PartialEq, CirceHash)]
 pub enum HowToCommand {
   HighLevel(Command),
-  LowLevel(Vec<LLTopStatement>)
+  LowLevel(Vec<LLTopStatement>, Span),
+  /// A `-*` fenced block of `< ... >` steps, each describing a primitive
+  /// operation (like a system call) rather than a command to infer.
+  Primitive(Vec<Command>, Span),
+  Conditional(Conditional),
+  Loop(Loop),
+  Binding(Binding),
+  Qualified(Qualified)
+}
+
+impl HowToCommand {
+  pub fn span(&self) -> Span {
+    match self {
+      HowToCommand::HighLevel(command) => command.span,
+      HowToCommand::LowLevel(_, span) => *span,
+      HowToCommand::Primitive(_, span) => *span,
+      HowToCommand::Conditional(conditional) => conditional.span,
+      HowToCommand::Loop(repeat) => repeat.span,
+      HowToCommand::Binding(binding) => binding.span,
+      HowToCommand::Qualified(qualified) => qualified.span
+    }
+  }
+}
+
+/// Brings another file into this one. It is written as a command of
+/// just `use` or `include` and a literal, so either word can still
+/// start other commands:
+///
+/// ```text
+/// use 'console'.
+/// include './greetings.cce'.
+/// ```
+#[derive(Debug, Clone, PartialEq, CirceHash)]
+pub struct Import {
+  pub kind: ImportKind,
+  pub path: String,
+  pub path_span: Span,
+  pub span: Span
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, CirceHash)]
+pub enum ImportKind {
+  /// Brings in only the file's howto and whatis statements, along with
+  /// its module header.
+  Use,
+  /// Brings in the whole file, commands and all.
+  Include
+}
+
+impl Import {
+  fn from_command(command: &Command) -> Option<Import> {
+    if !command.modifiers.is_empty() {
+      return None;
+    }
+
+    match command.components.as_slice() {
+      [CommandComponent::Keyword(word, _), CommandComponent::Literal(path, path_span)] => {
+        let kind: ImportKind = match word.as_str() {
+          "use" => ImportKind::Use,
+          "include" => ImportKind::Include,
+          _ => return None
+        };
+
+        Some(Import { kind, path: path.clone(), path_span: *path_span, span: command.span })
+      },
+      _ => None
+    }
+  }
+}
+
+/// `module <name>.`, which puts the howto and whatis statements of the
+/// file it starts into a module of their own, so that they don't clash
+/// with those of other modules.
+#[derive(Debug, Clone, PartialEq, CirceHash)]
+pub struct Module {
+  pub name: String,
+  pub name_span: Span,
+  pub span: Span
+}
+
+/// A question for the engine, about how it would carry out a command or
+/// what it knows a phrase to be:
+///
+/// ```text
+/// ? howto print a number
+/// ? whatis stdout
+/// ```
+#[derive(Debug, Clone, PartialEq, CirceHash)]
+pub struct Query {
+  pub kind: QueryKind,
+  pub subject: Vec<CommandComponent>,
+  pub span: Span
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, CirceHash)]
+pub enum QueryKind {
+  HowTo,
+  WhatIs
+}
+
+/// `within <module>, <command>`, a command that only uses the howto and
+/// whatis statements of the given module.
+#[derive(Debug, Clone, PartialEq, CirceHash)]
+pub struct Qualified {
+  pub module: String,
+  pub module_span: Span,
+  pub command: Command,
+  /// Covers the `within` and the command, but not any bullets nested
+  /// under the command.
+  pub span: Span
+}
+
+/// A command whose result is given a name, which later commands can
+/// refer to as a slot:
+///
+/// ```text
+/// read a line from the console and call it name.
+/// print %name.
+/// ```
+#[derive(Debug, Clone, PartialEq, CirceHash)]
+pub struct Binding {
+  pub command: Command,
+  pub name: String,
+  pub name_span: Span,
+  /// Covers the command and its `and call it <name>`, but not any
+  /// bullets nested under it.
+  pub span: Span
+}
+
+impl Binding {
+  pub(crate) fn new(command: Command, (name, name_span): (String, Span)) -> Self {
+    Binding { span: command.span.to(name_span), command, name, name_span }
+  }
+}
+
+/// `if <condition>, <command>`, optionally followed by `otherwise`.
+///
+/// In a body, either branch can also be given as bullets indented under
+/// the `if` or `otherwise`:
+///
+/// ```text
+/// - if the file exists
+///   - open it
+/// - otherwise, create it
+/// ```
+///
+/// `otherwise if` chains are an `otherwise` branch holding just another
+/// conditional.
+#[derive(Debug, Clone, PartialEq, CirceHash)]
+pub struct Conditional {
+  pub condition: Vec<CommandComponent>,
+  pub then: Vec<HowToCommand>,
+  pub otherwise: Vec<HowToCommand>,
+  /// Covers the `if` and its condition, and the command after the comma
+  /// if there is one, but not any bullets or `otherwise`.
+  pub span: Span
+}
+
+/// Steps that run over and over. As with a conditional, the body is
+/// either a command after a comma, or bullets indented under the loop:
+///
+/// ```text
+/// - for each %line in the file
+///   - print the line
+/// - repeat 3 times, beep
+/// - while the door is open, wait
+/// ```
+#[derive(Debug, Clone, PartialEq, CirceHash)]
+pub struct Loop {
+  pub kind: LoopKind,
+  pub body: Vec<HowToCommand>,
+  /// Covers the loop's header, and the command after the comma if there
+  /// is one, but not any bullets.
+  pub span: Span
+}
+
+#[derive(Debug, Clone, PartialEq, CirceHash)]
+pub enum LoopKind {
+  /// `for each %item in <collection>`, running the body once for every
+  /// item, with the slot standing for the current one.
+  ForEach {
+    item: Slot,
+    item_span: Span,
+    collection: Vec<CommandComponent>
+  },
+  /// `repeat <count> times`
+  Repeat {
+    count: Vec<CommandComponent>
+  },
+  /// `while <condition>`, running the body for as long as it holds.
+  While {
+    condition: Vec<CommandComponent>
+  }
 }
 
 #[derive(Debug, Clone, PartialEq, CirceHash)]
 pub struct WhatIsStatement {
+  pub visibility: Visibility,
   pub signature: Vec<CommandComponent>,
-  pub body: Vec<Command>
+  pub body: Vec<Command>,
+  pub span: Span
 }
 
 
@@ -73,52 +408,646 @@ pub struct WhatIsStatement {
 pub enum ParserError {
   #[error("{0}")]
   LexerError(#[from] LexerError),
-  #[error("Syntax error: {0}")]
-  SyntaxError(String),
-  #[error("Internal error: {0}")]
-  InternalError(String)
+  #[error("Expected {}, found {}", describe_expected(.expected), describe_found(.found))]
+  Expected {
+    expected: Vec<TokenKind>,
+    found: Option<Token>,
+    span: Span
+  },
+  #[error("Unexpected keyword '{keyword}'")]
+  UnexpectedKeyword {
+    keyword: String,
+    span: Span
+  },
+  #[error("Unexpected {found}")]
+  UnexpectedToken {
+    found: Token,
+    span: Span
+  },
+  #[error("A howto statement needs at least one step")]
+  EmptyHowToBody {
+    span: Span
+  },
+  #[error("A whatis statement needs at least one description")]
+  EmptyWhatIsBody {
+    span: Span
+  },
+  #[error("Low-level blocks are only allowed as howto steps")]
+  LowLevelNotAllowed {
+    span: Span
+  },
+  #[error("Invalid low-level block: {error}")]
+  LowLevel {
+    error: LLError,
+    span: Span,
+    block: Span
+  },
+  #[error("Bullet doesn't line up with any bullet above it")]
+  InconsistentIndentation {
+    span: Span
+  },
+  #[error("Only commands can be sub-steps of a command")]
+  NestedBlock {
+    span: Span
+  },
+  #[error("Low-level and primitive blocks can't have sub-steps")]
+  SubStepOfBlock {
+    span: Span,
+    block: Span
+  },
+  #[error("'otherwise' without an 'if' before it")]
+  OtherwiseWithoutIf {
+    span: Span
+  },
+  #[error("A branch of a conditional needs at least one step")]
+  EmptyBranch {
+    span: Span
+  },
+  #[error("A loop needs at least one step")]
+  EmptyLoop {
+    span: Span
+  },
+  #[error("Expected '{word}', found {}", describe_found(.found))]
+  ExpectedWord {
+    word: String,
+    found: Option<Token>,
+    span: Span
+  }
+}
+
+fn describe_expected(expected: &[TokenKind]) -> String {
+  match expected {
+    [] => "nothing".to_string(),
+    [only] => only.to_string(),
+    [rest @ .., last] => {
+      let rest: Vec<String> = rest.iter().map(TokenKind::to_string).collect();
+      format!("{} or {}", rest.join(", "), last)
+    }
+  }
+}
+
+fn describe_found(found: &Option<Token>) -> String {
+  match found {
+    Some(tok) => tok.to_string(),
+    None => "end of input".to_string()
+  }
+}
+
+impl ParserError {
+  pub fn span(&self) -> Option<Span> {
+    match self {
+      ParserError::LexerError(err) => err.span(),
+      ParserError::Expected { span, .. } => Some(*span),
+      ParserError::UnexpectedKeyword { span, .. } => Some(*span),
+      ParserError::UnexpectedToken { span, .. } => Some(*span),
+      ParserError::EmptyHowToBody { span } => Some(*span),
+      ParserError::EmptyWhatIsBody { span } => Some(*span),
+      ParserError::LowLevelNotAllowed { span } => Some(*span),
+      ParserError::LowLevel { span, .. } => Some(*span),
+      ParserError::InconsistentIndentation { span } => Some(*span),
+      ParserError::NestedBlock { span } => Some(*span),
+      ParserError::SubStepOfBlock { span, .. } => Some(*span),
+      ParserError::OtherwiseWithoutIf { span } => Some(*span),
+      ParserError::EmptyBranch { span } => Some(*span),
+      ParserError::EmptyLoop { span } => Some(*span),
+      ParserError::ExpectedWord { span, .. } => Some(*span)
+    }
+  }
+}
+
+impl ToDiagnostic for ParserError {
+  fn to_diagnostic(&self) -> Diagnostic {
+    match self {
+      ParserError::LexerError(err) => err.to_diagnostic(),
+      ParserError::Expected { expected, found, span } => {
+        Diagnostic::error(format!("expected {}, found {}", describe_expected(expected), describe_found(found)))
+          .with_code("E0100")
+          .with_label(Label::primary(*span, format!("expected {}", describe_expected(expected))))
+      },
+      ParserError::UnexpectedKeyword { keyword, span } => {
+        Diagnostic::error(format!("unexpected keyword '{}'", keyword))
+          .with_code("E0101")
+          .with_label(Label::primary(*span, "a statement cannot start with this keyword"))
+      },
+      ParserError::UnexpectedToken { found, span } => {
+        Diagnostic::error(format!("unexpected {}", found))
+          .with_code("E0102")
+          .with_label(Label::primary(*span, "expected a command, howto or whatis"))
+      },
+      ParserError::EmptyHowToBody { span } => {
+        Diagnostic::error("howto statement has no steps")
+          .with_code("E0103")
+          .with_label(Label::primary(*span, "this needs at least one step"))
+          .with_help("add steps on the following lines, each starting with '-'")
+      },
+      ParserError::EmptyWhatIsBody { span } => {
+        Diagnostic::error("whatis statement has no descriptions")
+          .with_code("E0104")
+          .with_label(Label::primary(*span, "this needs at least one description"))
+          .with_help("add descriptions on the following lines, each starting with '-'")
+      },
+      ParserError::LowLevelNotAllowed { span } => {
+        Diagnostic::error("low-level block outside of a howto step")
+          .with_code("E0105")
+          .with_label(Label::primary(*span, "not allowed here"))
+          .with_help("low-level blocks must be a step of their own, directly after '-'")
+      },
+      ParserError::LowLevel { error, span, block } => {
+        Diagnostic::error("invalid low-level block")
+          .with_code("E0106")
+          .with_label(Label::primary(*span, error.to_string()))
+          .with_label(Label::secondary(*block, "in this low-level block"))
+      },
+      ParserError::InconsistentIndentation { span } => {
+        Diagnostic::error("inconsistent indentation")
+          .with_code("E0107")
+          .with_label(Label::primary(*span, "this bullet doesn't line up with any bullet above it"))
+          .with_help("indent sub-steps further than their parent, and line up siblings using the same tabs and spaces")
+      },
+      ParserError::NestedBlock { span } => {
+        Diagnostic::error("step nested under a command")
+          .with_code("E0108")
+          .with_label(Label::primary(*span, "only plain commands can be sub-steps of a command"))
+          .with_help("make this a step of its own, or indent it under an 'if' or 'otherwise'")
+      },
+      ParserError::SubStepOfBlock { span, block } => {
+        Diagnostic::error("sub-step nested under a block")
+          .with_code("E0109")
+          .with_label(Label::primary(*span, "this is indented under a block"))
+          .with_label(Label::secondary(*block, "blocks can't have sub-steps"))
+      },
+      ParserError::OtherwiseWithoutIf { span } => {
+        Diagnostic::error("'otherwise' without an 'if'")
+          .with_code("E0110")
+          .with_label(Label::primary(*span, "there's no 'if' for this to be the other branch of"))
+          .with_help("'otherwise' has to come right after an 'if', at the same level")
+      },
+      ParserError::EmptyBranch { span } => {
+        Diagnostic::error("branch has no steps")
+          .with_code("E0111")
+          .with_label(Label::primary(*span, "this needs at least one step"))
+          .with_help("follow it with a comma and a command, or indent steps under it")
+      },
+      ParserError::EmptyLoop { span } => {
+        Diagnostic::error("loop has no steps")
+          .with_code("E0112")
+          .with_label(Label::primary(*span, "this needs at least one step"))
+          .with_help("follow it with a comma and a command, or indent steps under it")
+      },
+      ParserError::ExpectedWord { word, found, span } => {
+        Diagnostic::error(format!("expected '{}', found {}", word, describe_found(found)))
+          .with_code("E0113")
+          .with_label(Label::primary(*span, format!("expected '{}'", word)))
+      }
+    }
+  }
 }
 
 
+/// Converts a 1-based line and 0-based character column inside a
+/// low-level sequence into a byte offset from the start of the sequence.
+fn low_level_offset(sequence: &str, line: usize, column: usize) -> usize {
+  let mut offset: usize = 0;
+
+  for (i, text) in sequence.split('\n').enumerate() {
+    if i + 1 == line {
+      return offset + text.char_indices().nth(column).map_or(text.len(), |(i, _)| i);
+    }
+
+    offset += text.len() + 1;
+  }
+
+  sequence.len()
+}
+
+/// Maps the location reported for an error inside a `$$ ... $$`
+/// block back into the file that contains the block.
+fn remap_low_level_span(sequence: &str, block: Span, error: &LLError) -> Span {
+  let start = error.span().start();
+  let end = error.span().end();
+
+  // `syn` reports errors it cannot place, like running out of input,
+  // at line 0. The best we can do is blame the whole block.
+  if start.line == 0 {
+    return block;
+  }
+
+  // Skip over the opening `$$`.
+  let content: usize = block.start + 2;
+  let start: usize = low_level_offset(sequence, start.line, start.column);
+  let end: usize = low_level_offset(sequence, end.line, end.column).max(start);
+
+  Span::new(block.file, content + start, content + end)
+}
+
+/// One bullet of a howto or whatis body, before it has been placed
+/// under the bullet it is indented beneath.
+struct Bullet<T> {
+  indent: Option<String>,
+  span: Span,
+  item: T
+}
+
+/// Turns the bullets of a body into a tree, going by their indentation.
+/// A bullet indented further than the one before it is a child of that
+/// bullet, and one that goes back out has to line up exactly with an
+/// earlier bullet. `attach` gives an item its children.
+fn nest<T>(bullets: Vec<Bullet<T>>, attach: &dyn Fn(T, Vec<T>) -> Result<T, ParserError>) -> Result<Vec<T>, ParserError> {
+  // The indentation of each bullet from the outermost to the current one.
+  let mut levels: Vec<String> = Vec::new();
+  let mut items: Vec<(usize, T)> = Vec::new();
+
+  for bullet in bullets {
+    let depth: usize = match bullet.indent {
+      // Bullets that share a line are siblings.
+      None => levels.len().saturating_sub(1),
+      Some(indent) => {
+        match levels.iter().position(|level| *level == indent) {
+          Some(depth) => {
+            levels.truncate(depth + 1);
+            depth
+          },
+          None if levels.last().is_none_or(|level| indent.starts_with(level.as_str())) => {
+            levels.push(indent);
+            levels.len() - 1
+          },
+          None => {
+            return Err(ParserError::InconsistentIndentation { span: bullet.span });
+          }
+        }
+      }
+    };
+
+    items.push((depth, bullet.item));
+  }
+
+  nest_level(&mut items.into_iter().peekable(), 0, attach)
+}
+
+fn nest_level<T>(
+  items: &mut std::iter::Peekable<std::vec::IntoIter<(usize, T)>>,
+  depth: usize,
+  attach: &dyn Fn(T, Vec<T>) -> Result<T, ParserError>
+) -> Result<Vec<T>, ParserError> {
+  let mut level: Vec<T> = Vec::new();
+
+  while let Some((_, item)) = items.next_if(|(item_depth, _)| *item_depth >= depth) {
+    let children: Vec<T> = nest_level(items, depth + 1, attach)?;
+
+    if children.is_empty() {
+      level.push(item);
+    } else {
+      level.push(attach(item, children)?);
+    }
+  }
+
+  Ok(level)
+}
+
+/// A bullet of a howto body. An `otherwise` bullet isn't a step of its
+/// own, and is folded into the `if` before it once the body is nested.
+enum Step {
+  Do(HowToCommand),
+  Otherwise(Vec<HowToCommand>, Span)
+}
+
+/// Commands can only have plain commands as sub-steps. The steps under
+/// a conditional's bullet are added to the branch it starts.
+fn nest_step(step: Step, children: Vec<Step>) -> Result<Step, ParserError> {
+  let children: Vec<HowToCommand> = fold_otherwise(children)?;
+
+  match step {
+    Step::Do(HowToCommand::HighLevel(mut command)) => {
+      command.children = sub_commands(children)?;
+      Ok(Step::Do(HowToCommand::HighLevel(command)))
+    },
+    Step::Do(HowToCommand::Binding(mut binding)) => {
+      binding.command.children = sub_commands(children)?;
+      Ok(Step::Do(HowToCommand::Binding(binding)))
+    },
+    Step::Do(HowToCommand::Qualified(mut qualified)) => {
+      qualified.command.children = sub_commands(children)?;
+      Ok(Step::Do(HowToCommand::Qualified(qualified)))
+    },
+    Step::Do(HowToCommand::Conditional(mut conditional)) => {
+      attach_steps(&mut conditional.then, children);
+      Ok(Step::Do(HowToCommand::Conditional(conditional)))
+    },
+    Step::Do(HowToCommand::Loop(mut repeat)) => {
+      attach_steps(&mut repeat.body, children);
+      Ok(Step::Do(HowToCommand::Loop(repeat)))
+    },
+    Step::Otherwise(mut branch, span) => {
+      attach_steps(&mut branch, children);
+      Ok(Step::Otherwise(branch, span))
+    },
+    Step::Do(block) => {
+      Err(ParserError::SubStepOfBlock { span: children[0].span(), block: block.span() })
+    }
+  }
+}
+
+/// Only plain commands can be sub-steps of a command.
+fn sub_commands(children: Vec<HowToCommand>) -> Result<Vec<Command>, ParserError> {
+  children.into_iter().map(|child| match child {
+    HowToCommand::HighLevel(child) => Ok(child),
+    other => Err(ParserError::NestedBlock { span: other.span() })
+  }).collect()
+}
+
+/// Adds the steps under a bullet to the steps after its comma. Those
+/// under `otherwise if x` or `repeat 2 times, if x` belong to the inner
+/// `if`, so they go to the innermost block written on the line.
+fn attach_steps(steps: &mut Vec<HowToCommand>, children: Vec<HowToCommand>) {
+  match steps.as_mut_slice() {
+    [HowToCommand::Conditional(conditional)] => attach_steps(&mut conditional.then, children),
+    [HowToCommand::Loop(repeat)] => attach_steps(&mut repeat.body, children),
+    _ => steps.extend(children)
+  }
+}
+
+/// Gives each `otherwise` to the `if` on the bullet before it, making
+/// sure neither branch is left empty.
+fn fold_otherwise(steps: Vec<Step>) -> Result<Vec<HowToCommand>, ParserError> {
+  let mut folded: Vec<HowToCommand> = Vec::new();
+
+  for step in steps {
+    match step {
+      Step::Do(command) => {
+        check_step(&command)?;
+        folded.push(command);
+      },
+      Step::Otherwise(branch, span) => {
+        if branch.is_empty() {
+          return Err(ParserError::EmptyBranch { span });
+        }
+
+        match folded.last_mut() {
+          Some(HowToCommand::Conditional(conditional)) => attach_otherwise(conditional, branch, span)?,
+          _ => return Err(ParserError::OtherwiseWithoutIf { span })
+        }
+      }
+    }
+  }
+
+  Ok(folded)
+}
+
+/// Sets the `otherwise` branch of a conditional, or of the last `if` in
+/// an `otherwise if` chain.
+fn attach_otherwise(conditional: &mut Conditional, branch: Vec<HowToCommand>, span: Span) -> Result<(), ParserError> {
+  match conditional.otherwise.as_mut_slice() {
+    [] => {
+      conditional.otherwise = branch;
+      check_conditional(conditional)
+    },
+    [HowToCommand::Conditional(inner)] => attach_otherwise(inner, branch, span),
+    _ => Err(ParserError::OtherwiseWithoutIf { span })
+  }
+}
+
+/// Makes sure no branch or loop in a step, however deeply nested, was
+/// left without steps.
+fn check_step(step: &HowToCommand) -> Result<(), ParserError> {
+  match step {
+    HowToCommand::Conditional(conditional) => check_conditional(conditional),
+    HowToCommand::Loop(repeat) => {
+      if repeat.body.is_empty() {
+        return Err(ParserError::EmptyLoop { span: repeat.span });
+      }
+
+      repeat.body.iter().try_for_each(check_step)
+    },
+    _ => Ok(())
+  }
+}
+
+fn check_conditional(conditional: &Conditional) -> Result<(), ParserError> {
+  if conditional.then.is_empty() {
+    return Err(ParserError::EmptyBranch { span: conditional.span });
+  }
+
+  conditional.then.iter().chain(conditional.otherwise.iter()).try_for_each(check_step)
+}
+
+/// Takes a trailing `and call it <name>` off a command, returning the
+/// name if there was one.
+fn split_binding(command: &mut Command) -> Option<(String, Span)> {
+  let group: &mut Vec<CommandComponent> = command.modifiers.last_mut().unwrap_or(&mut command.components);
+
+  // There has to be something left to name.
+  let at: usize = group.len().checked_sub(4).filter(|at| *at > 0)?;
+
+  let name: (String, Span) = match &group[at..] {
+    [
+      CommandComponent::Keyword(and, _),
+      CommandComponent::Keyword(call, _),
+      CommandComponent::Keyword(it, _),
+      CommandComponent::Keyword(name, name_span)
+    ] if and == "and" && call == "call" && it == "it" => (name.clone(), *name_span),
+    _ => return None
+  };
+
+  group.truncate(at);
+  let end: usize = group[at - 1].span().end;
+
+  command.span = Span::new(command.span.file, command.span.start, end);
+  Some(name)
+}
+
+/// Whether a word starts a loop.
+fn is_loop_start(word: &str) -> bool {
+  matches!(word, "for" | "repeat" | "while")
+}
+
+/// Whether a token can only appear at the start of a statement: `howto`
+/// and `whatis` anywhere, and `private` and `module` at the start of a line.
+fn is_statement_start(token: &SpannedToken) -> bool {
+  match &token.token {
+    Token::Keyword(kw) => kw == "howto" || kw == "whatis",
+    Token::Identifier(word) => token.indent.is_some() && (word == "private" || word == "module"),
+    _ => false
+  }
+}
+
+/// The token a component of a clause was read from.
+fn component_token(component: &CommandComponent) -> Token {
+  match component {
+    CommandComponent::Keyword(word, _) => Token::Identifier(word.clone()),
+    CommandComponent::Literal(lit, _) => Token::Literal(lit.clone()),
+    CommandComponent::Number(n, _) => Token::Number(*n),
+    CommandComponent::Slot(..) => Token::Percent
+  }
+}
+
 impl<'s> Parser<'s> {
-  pub fn new(lexer: Lexer<'s>) -> Parser {
+  pub fn new(lexer: Lexer<'s>) -> Parser<'s> {
     Parser {
       lexer,
-      peeked: None
+      peeked: None,
+      prev_span: Span::default(),
+      failed: false,
+      lexicon: Lexicon::default(),
+      clause: None
+    }
+  }
+
+  /// A parser that uses `lexicon` to tell which words are articles, like
+  /// the `a` in `%n: a number`.
+  pub fn with_lexicon(lexer: Lexer<'s>, lexicon: Lexicon) -> Parser<'s> {
+    Parser {
+      lexicon,
+      ..Parser::new(lexer)
+    }
+  }
+
+  fn peek_token(&mut self) -> Result<Option<Token>, ParserError> {
+    Ok(self.lexer.peek()?.map(|tok| tok.token))
+  }
+
+  fn bump(&mut self) -> Result<Option<SpannedToken>, ParserError> {
+    let tok: Option<SpannedToken> = self.lexer.next_token()?;
+
+    if let Some(SpannedToken { token, span, .. }) = &tok {
+      if *token != Token::Newline {
+        self.prev_span = *span;
+      }
+    }
+
+    Ok(tok)
+  }
+
+  /// The span of the last peeked token, or an empty span at the end of the input.
+  fn peeked_span(&self) -> Span {
+    match &self.lexer.peeked {
+      Some(tok) => tok.span,
+      None => {
+        let pos: usize = self.lexer.stream.pos;
+        Span::new(self.lexer.stream.file, pos, pos)
+      }
+    }
+  }
+
+  /// The span of the next token, or an empty span at the end of the input.
+  fn next_span(&mut self) -> Result<Span, ParserError> {
+    self.lexer.peek()?;
+    Ok(self.peeked_span())
+  }
+
+  /// An error pointing at the token that was just peeked.
+  fn expected(&self, expected: &[TokenKind]) -> ParserError {
+    ParserError::Expected {
+      expected: expected.to_vec(),
+      found: self.lexer.peeked.as_ref().map(|tok| tok.token.clone()),
+      span: self.peeked_span()
+    }
+  }
+
+  /// An error for a clause that has `found` where one of `expected`
+  /// should be, or that ends before it if `found` is `None`.
+  fn expected_in(&self, expected: &[TokenKind], found: Option<&CommandComponent>) -> ParserError {
+    let (found, span) = self.found(found);
+    ParserError::Expected { expected: expected.to_vec(), found, span }
+  }
+
+  /// Like [`Parser::expected_in`], for a word that isn't a keyword, like
+  /// the `in` of `for each %item in <collection>`.
+  fn expected_word(&self, word: &str, found: Option<&CommandComponent>) -> ParserError {
+    let (found, span) = self.found(found);
+    ParserError::ExpectedWord { word: word.to_string(), found, span }
+  }
+
+  /// The token `component` was read from and its span, or the token
+  /// after the clause if there is no component.
+  fn found(&self, component: Option<&CommandComponent>) -> (Option<Token>, Span) {
+    match component {
+      Some(component) => (Some(component_token(component)), component.span()),
+      None => (self.lexer.peeked.as_ref().map(|tok| tok.token.clone()), self.peeked_span())
+    }
+  }
+
+  /// Whether the next token is the plain word `word`.
+  fn peek_word(&mut self, word: &str) -> Result<bool, ParserError> {
+    Ok(matches!(self.peek_token()?, Some(Token::Identifier(next)) if next == word))
+  }
+
+  /// Consumes the `?` and newline ending a signature, stopping at the `-`
+  /// opening the first bullet of the body. Returns `false` if there is no body.
+  fn parse_body_start(&mut self) -> Result<bool, ParserError> {
+    if self.peek_token()? != Some(Token::Question) {
+      return Err(self.expected(&[TokenKind::Question]));
+    }
+
+    self.bump()?;
+
+    match self.peek_token()? {
+      Some(Token::Newline) => {
+        self.bump()?;
+      },
+      None => {
+        return Ok(false);
+      },
+      _ => {
+        return Err(self.expected(&[TokenKind::Newline]));
+      }
+    }
+
+    match self.lexer.peek()? {
+      Some(SpannedToken { token: Token::Punctuation('-'), .. }) => {
+        Ok(true)
+      },
+      Some(tok) if tok.token == Token::Newline || is_statement_start(&tok) => {
+        Ok(false)
+      },
+      None => {
+        Ok(false)
+      },
+      _ => {
+        Err(self.expected(&[TokenKind::Punctuation('-')]))
+      }
+    }
+  }
+
+  /// Covers everything from `start` up to the last consumed token.
+  fn span_from(&self, start: Span) -> Span {
+    if self.prev_span.end <= start.start {
+      Span::new(start.file, start.start, start.start)
+    } else {
+      Span::new(start.file, start.start, self.prev_span.end)
     }
   }
 
   fn parse_vec_command_component(&mut self) -> Result<Vec<CommandComponent>, ParserError> {
     let mut components: Vec<CommandComponent> = Vec::new();
 
-    let mut tok: Option<Token> = self.lexer.peek()?;
+    let mut tok: Option<SpannedToken> = self.lexer.peek()?;
 
-    while let Some(token) = tok.clone() {
+    while let Some(SpannedToken { token, span, .. }) = tok.clone() {
       match token {
         Token::Identifier(ident) => {
-          components.push(CommandComponent::Keyword(ident));
+          components.push(CommandComponent::Keyword(ident, span));
         },
         Token::Keyword(kw) => {
-          components.push(CommandComponent::Keyword(kw));
+          components.push(CommandComponent::Keyword(kw, span));
         },
         Token::Literal(lit) => {
-          components.push(CommandComponent::Literal(lit));
+          components.push(CommandComponent::Literal(lit, span));
+        },
+        Token::Number(n) => {
+          components.push(CommandComponent::Number(n, span));
         },
         Token::Percent => {
-          self.lexer.next()?;
-          tok = self.lexer.peek()?;
+          self.bump()?;
+          components.push(self.parse_slot(span)?);
 
-          if let Some(Token::Identifier(ident)) = tok {
-            components.push(CommandComponent::Slot(ident));
-          } else {
-            return Err(ParserError::SyntaxError("Expected identifier".to_string()));
-          }
+          tok = self.lexer.peek()?;
+          continue;
         },
         Token::Punctuation(_) => {
           break;
         },
         Token::LowLevelSequence(_) => {
-          return Err(ParserError::SyntaxError("Low level sequences are not allowed here".to_string()));
+          return Err(ParserError::LowLevelNotAllowed { span });
         },
         Token::Newline => {
           break;
@@ -131,43 +1060,113 @@ impl<'s> Parser<'s> {
         }
       }
 
-      self.lexer.next()?;
+      self.bump()?;
       tok = self.lexer.peek()?;
     }
 
     Ok(components)
   }
 
-  fn parse_command(&mut self) -> Result<Command, ParserError> {
+  /// Parses a slot's name and kind, after its `%`.
+  fn parse_slot(&mut self, percent: Span) -> Result<CommandComponent, ParserError> {
+    match self.peek_token()? {
+      Some(Token::Identifier(name)) => {
+        self.bump()?;
+
+        let kind: Option<Vec<CommandComponent>> = self.parse_slot_kind()?;
+        Ok(CommandComponent::Slot(Slot { name, kind }, percent.to(self.prev_span)))
+      },
+      _ => {
+        Err(self.expected(&[TokenKind::Identifier]))
+      }
+    }
+  }
+
+  /// Parses the `: a string` that may follow a slot's name.
+  fn parse_slot_kind(&mut self) -> Result<Option<Vec<CommandComponent>>, ParserError> {
+    if self.peek_token()? != Some(Token::Punctuation(':')) {
+      return Ok(None);
+    }
+
+    self.bump()?;
+
+    let mut kind: Vec<CommandComponent> = Vec::new();
+
+    loop {
+      let tok: Option<SpannedToken> = self.lexer.peek()?;
+
+      let component: CommandComponent = match tok {
+        Some(SpannedToken { token: Token::Identifier(word), span, .. }) => CommandComponent::Keyword(word, span),
+        Some(SpannedToken { token: Token::Literal(lit), span, .. }) => CommandComponent::Literal(lit, span),
+        _ => return Err(self.expected(&[TokenKind::Identifier, TokenKind::Literal]))
+      };
+
+      self.bump()?;
+
+      // The article is kept so that the kind reads back as it was written.
+      let article: bool = kind.is_empty() && component.word_class(&self.lexicon) == Some(WordClass::Article);
+      kind.push(component);
+
+      if !article {
+        break;
+      }
+    }
+
+    Ok(Some(kind))
+  }
+
+  /// Reads the first clause of a statement or step.
+  fn parse_clause(&mut self) -> Result<Clause, ParserError> {
+    let start: Span = self.next_span()?;
     let components: Vec<CommandComponent> = self.parse_vec_command_component()?;
+
+    Ok(Clause { start, components })
+  }
+
+  /// The word heading `clause`, if it starts a statement.
+  fn clause_head(&mut self, clause: &Clause) -> Result<Option<String>, ParserError> {
+    let end: Option<Token> = self.peek_token()?;
+    Ok(clause.head(end.as_ref()))
+  }
+
+  fn parse_command(&mut self) -> Result<Command, ParserError> {
+    let clause: Clause = self.parse_clause()?;
+    self.parse_command_from(clause)
+  }
+
+  /// Parses the modifiers and end of a command after its first clause.
+  fn parse_command_from(&mut self, clause: Clause) -> Result<Command, ParserError> {
+    let Clause { start, components } = clause;
     let mut modifiers: Vec<Vec<CommandComponent>> = Vec::new();
+    let mut span: Span = self.span_from(start);
 
-    let mut tok: Option<Token> = self.lexer.peek()?;
+    let mut tok: Option<Token> = self.peek_token()?;
 
     while let Some(token) = tok.clone() {
       match token {
         Token::Punctuation(punc) => {
           match punc {
             '|' => {
-              self.lexer.next()?;
+              self.bump()?;
               modifiers.push(self.parse_vec_command_component()?);
-              tok = self.lexer.peek()?;
+              span = self.span_from(start);
+              tok = self.peek_token()?;
             },
-            '-' => {
+            '-' | '>' => {
               break;
             },
             _ => {
-              return Err(ParserError::SyntaxError("Expected '|'".to_string()));
+              return Err(self.expected(&[TokenKind::Punctuation('|')]));
             }
           }
         },
         Token::Dot => {
-          self.lexer.next()?;
+          self.bump()?;
           break;
         },
         Token::Newline => {
-          self.lexer.next()?;
-          tok = self.lexer.peek()?;
+          self.bump()?;
+          tok = self.peek_token()?;
         },
         _ => {
           break;
@@ -177,180 +1176,551 @@ impl<'s> Parser<'s> {
 
     Ok(Command {
       components,
-      modifiers
+      modifiers,
+      children: Vec::new(),
+      span
     })
   }
 
+  /// Consumes the `-` opening a bullet, returning its indentation, or
+  /// `None` if it follows another bullet on the same line.
+  fn bump_bullet(&mut self) -> Result<(Option<String>, Span), ParserError> {
+    match self.lexer.peek()? {
+      Some(SpannedToken { token: Token::Punctuation('-'), span, indent, .. }) => {
+        self.bump()?;
+        Ok((indent, span))
+      },
+      _ => Err(self.expected(&[TokenKind::Punctuation('-')]))
+    }
+  }
+
+  fn skip_newlines(&mut self) -> Result<(), ParserError> {
+    while self.peek_token()? == Some(Token::Newline) {
+      self.bump()?;
+    }
+
+    Ok(())
+  }
+
+  fn expect(&mut self, kind: TokenKind) -> Result<(), ParserError> {
+    match self.peek_token()? {
+      Some(tok) if tok.kind() == kind => {
+        self.bump()?;
+        Ok(())
+      },
+      _ => {
+        Err(self.expected(&[kind]))
+      }
+    }
+  }
+
+  /// Parses a primitive block, starting at the `*` after a step's `-`:
+  ///
+  /// ```text
+  /// -*
+  /// < run the 'write' system call
+  /// | the first argument is the file descriptor
+  /// >
+  /// -*
+  /// ```
+  ///
+  /// Each `< ... >` item is a command with optional modifiers, and the
+  /// block is closed by another `-*`.
+  fn parse_primitive_block(&mut self, start: Span) -> Result<HowToCommand, ParserError> {
+    self.expect(TokenKind::Punctuation('*'))?;
+
+    let mut steps: Vec<Command> = Vec::new();
+
+    loop {
+      self.skip_newlines()?;
+
+      match self.peek_token()? {
+        Some(Token::Punctuation('<')) => {
+          self.bump()?;
+          steps.push(self.parse_command()?);
+          self.expect(TokenKind::Punctuation('>'))?;
+        },
+        Some(Token::Punctuation('-')) => {
+          self.bump()?;
+          self.expect(TokenKind::Punctuation('*'))?;
+          break;
+        },
+        _ => {
+          return Err(self.expected(&[TokenKind::Punctuation('<'), TokenKind::Punctuation('-')]));
+        }
+      }
+    }
+
+    let span: Span = self.span_from(start);
+    self.skip_newlines()?;
+
+    Ok(HowToCommand::Primitive(steps, span))
+  }
+
   fn parse_howto_command(&mut self) -> Result<HowToCommand, ParserError> {
-    let tok: Option<Token> = self.lexer.peek()?;
+    let tok: Option<SpannedToken> = self.lexer.peek()?;
 
     match tok {
-      Some(Token::LowLevelSequence(seq)) => {
-        self.lexer.next()?;
+      Some(SpannedToken { token: Token::Punctuation('*'), .. }) => {
+        let start: Span = self.prev_span;
+        self.parse_primitive_block(start)
+      },
+      Some(SpannedToken { token: Token::LowLevelSequence(seq), span, .. }) => {
+        self.bump()?;
+
+        // As after a primitive block, the next bullet may be on a new line.
+        self.skip_newlines()?;
 
-        return match parse(seq.as_str()) {
+        match parse(seq.as_str()) {
           Ok(ast) => {
-            Ok(HowToCommand::LowLevel(ast))
+            Ok(HowToCommand::LowLevel(ast, span))
           },
-          Err(_) => {
-            unimplemented!();
+          Err(error) => {
+            let error_span: Span = remap_low_level_span(&seq, span, &error);
+            Err(ParserError::LowLevel { error, span: error_span, block: span })
           }
         }
       },
       _ => {
-        Ok(HowToCommand::HighLevel(self.parse_command()?))
+        self.parse_inline_step()
       }
     }
   }
 
-  fn parse_howto_statement(&mut self) -> Result<HowToStatement, ParserError> {
-    let signature: Vec<CommandComponent> = self.parse_vec_command_component()?;
+  /// Parses a step that can also follow a comma: a command, a binding,
+  /// a qualified command, a conditional or a loop.
+  fn parse_inline_step(&mut self) -> Result<HowToCommand, ParserError> {
+    let clause: Clause = self.parse_clause()?;
+    self.parse_inline_step_from(clause)
+  }
+
+  /// Parses an inline step from its first clause.
+  fn parse_inline_step_from(&mut self, clause: Clause) -> Result<HowToCommand, ParserError> {
+    match self.clause_head(&clause)?.as_deref() {
+      Some("if") => {
+        Ok(HowToCommand::Conditional(self.parse_conditional(clause.start, clause.skip(1))?))
+      },
+      Some(word) if is_loop_start(word) => {
+        Ok(HowToCommand::Loop(self.parse_loop(word, clause.start, clause.skip(1))?))
+      },
+      Some("within") => {
+        Ok(HowToCommand::Qualified(self.parse_qualified(clause.start, clause.skip(1))?))
+      },
+      _ => {
+        let mut command: Command = self.parse_command_from(clause)?;
 
-    if self.lexer.peek()? != Some(Token::Question) {
-      return Err(ParserError::SyntaxError("Expected '?'".to_string()));
+        match split_binding(&mut command) {
+          Some(name) => Ok(HowToCommand::Binding(Binding::new(command, name))),
+          None => Ok(HowToCommand::HighLevel(command))
+        }
+      }
     }
+  }
 
-    self.lexer.next()?;
+  /// The module named by the words after `module` or `within`.
+  fn module_name(&self, rest: Vec<CommandComponent>) -> Result<(String, Span), ParserError> {
+    match rest.as_slice() {
+      [CommandComponent::Keyword(name, span)] => Ok((name.clone(), *span)),
+      rest => Err(self.expected_in(&[TokenKind::Identifier], rest.first()))
+    }
+  }
+
+  /// Parses a qualified command from the words after its `within`.
+  fn parse_qualified(&mut self, start: Span, rest: Vec<CommandComponent>) -> Result<Qualified, ParserError> {
+    let (module, module_span) = self.module_name(rest)?;
+    self.expect(TokenKind::Punctuation(','))?;
+
+    let command: Command = self.parse_command()?;
+
+    Ok(Qualified {
+      module,
+      module_span,
+      span: start.to(command.span),
+      command
+    })
+  }
 
-    if self.lexer.peek()? != Some(Token::Newline) {
-      return Err(ParserError::SyntaxError("Expected newline".to_string()));
+  /// Parses a module header from the words after its `module`.
+  fn parse_module(&mut self, start: Span, rest: Vec<CommandComponent>) -> Result<Module, ParserError> {
+    let (name, name_span) = self.module_name(rest)?;
+
+    match self.peek_token()? {
+      Some(Token::Dot) => {
+        self.bump()?;
+      },
+      None | Some(Token::Newline) => {},
+      Some(_) => {
+        return Err(self.expected(&[TokenKind::Dot]));
+      }
     }
 
-    self.lexer.next()?;
+    Ok(Module { name, name_span, span: start.to(name_span) })
+  }
 
-    let mut body: Vec<HowToCommand> = Vec::new();
+  /// Parses a query from after its `?`. The subject can end with a `.` or
+  /// a `?`, or just at the end of the line.
+  fn parse_query(&mut self, start: Span) -> Result<Query, ParserError> {
+    let kind: QueryKind = match self.peek_token()? {
+      Some(Token::Keyword(kw)) if kw == "howto" => QueryKind::HowTo,
+      Some(Token::Keyword(kw)) if kw == "whatis" => QueryKind::WhatIs,
+      _ => {
+        return Err(self.expected(&[TokenKind::Keyword]));
+      }
+    };
 
-    let mut tok: Option<Token> = self.lexer.peek()?;
+    self.bump()?;
 
-    match tok {
-      Some(Token::Punctuation(punc)) => {
-        if punc == '-' {
-          self.lexer.next()?;
-        } else {
-          return Err(ParserError::SyntaxError("Expected '-'".to_string()));
+    let subject: Vec<CommandComponent> = self.parse_vec_command_component()?;
+
+    let end: Span = match subject.last() {
+      Some(component) => component.span(),
+      None => {
+        return Err(self.expected(&[TokenKind::Identifier]));
+      }
+    };
+
+    match self.peek_token()? {
+      Some(Token::Dot) | Some(Token::Question) => {
+        self.bump()?;
+      },
+      None | Some(Token::Newline) => {},
+      Some(_) => {
+        return Err(self.expected(&[TokenKind::Newline]));
+      }
+    }
+
+    Ok(Query { kind, subject, span: start.to(end) })
+  }
+
+  /// Parses a loop from the words after the one that starts it. As with
+  /// a conditional, the body is left empty if it is on the bullets that
+  /// follow.
+  fn parse_loop(&mut self, word: &str, start: Span, rest: Vec<CommandComponent>) -> Result<Loop, ParserError> {
+    let kind: LoopKind = match word {
+      "for" => {
+        let mut rest = rest.into_iter();
+
+        match rest.next() {
+          Some(CommandComponent::Keyword(word, _)) if word == "each" => {},
+          found => return Err(self.expected_word("each", found.as_ref()))
+        }
+
+        let (item, item_span) = match rest.next() {
+          Some(CommandComponent::Slot(item, span)) => (item, span),
+          found => return Err(self.expected_in(&[TokenKind::Percent], found.as_ref()))
+        };
+
+        match rest.next() {
+          Some(CommandComponent::Keyword(word, _)) if word == "in" => {},
+          found => return Err(self.expected_word("in", found.as_ref()))
+        }
+
+        let collection: Vec<CommandComponent> = rest.collect();
+        if collection.is_empty() {
+          return Err(self.expected(&[TokenKind::Identifier]));
+        }
+
+        LoopKind::ForEach { item, item_span, collection }
+      },
+      "repeat" => {
+        let mut count: Vec<CommandComponent> = rest;
+
+        match count.pop() {
+          Some(CommandComponent::Keyword(word, _)) if word == "times" && !count.is_empty() => {},
+          _ => {
+            return Err(self.expected_word("times", None));
+          }
         }
+
+        LoopKind::Repeat { count }
       },
       _ => {
-        return Err(ParserError::SyntaxError("Expected '-'".to_string()));
+        let condition: Vec<CommandComponent> = rest;
+        if condition.is_empty() {
+          return Err(self.expected(&[TokenKind::Identifier]));
+        }
+
+        LoopKind::While { condition }
       }
     };
 
+    let mut span: Span = self.span_from(start);
+    let body: Vec<HowToCommand> = self.parse_branch()?;
+
+    if let Some(step) = body.last() {
+      span = start.to(step.span());
+    }
+
+    Ok(Loop {
+      kind,
+      body,
+      span
+    })
+  }
+
+  /// Parses what comes after a bullet's `-` in a howto body.
+  fn parse_step(&mut self) -> Result<Step, ParserError> {
+    if !self.peek_word("otherwise")? {
+      return Ok(Step::Do(self.parse_howto_command()?));
+    }
+
+    let clause: Clause = self.parse_clause()?;
+
+    match self.clause_head(&clause)?.as_deref() {
+      Some("otherwise") => {
+        let span: Span = clause.start;
+        let branch: Vec<HowToCommand> = self.parse_otherwise(clause)?;
+        let span: Span = span.to(branch.last().map_or(span, HowToCommand::span));
+        Ok(Step::Otherwise(branch, span))
+      },
+      _ => {
+        Ok(Step::Do(self.parse_inline_step_from(clause)?))
+      }
+    }
+  }
+
+  /// Parses a conditional from the condition after its `if`. The steps
+  /// of the `then` branch are left empty if they are on the bullets that
+  /// follow rather than after a comma.
+  fn parse_conditional(&mut self, start: Span, condition: Vec<CommandComponent>) -> Result<Conditional, ParserError> {
+    if condition.is_empty() {
+      return Err(self.expected(&[TokenKind::Identifier]));
+    }
+
+    let mut span: Span = self.span_from(start);
+    let then: Vec<HowToCommand> = self.parse_branch()?;
+
+    if let Some(step) = then.last() {
+      span = start.to(step.span());
+    }
+
+    Ok(Conditional {
+      condition,
+      then,
+      otherwise: Vec::new(),
+      span
+    })
+  }
+
+  /// Parses the branch of an `otherwise` clause: another `if`, a comma
+  /// and a command, or nothing if the steps are on the bullets that follow.
+  fn parse_otherwise(&mut self, clause: Clause) -> Result<Vec<HowToCommand>, ParserError> {
+    let mut rest: Vec<CommandComponent> = clause.skip(1);
+
+    match rest.first() {
+      Some(CommandComponent::Keyword(word, span)) if word == "if" => {
+        let start: Span = *span;
+        let condition: Vec<CommandComponent> = rest.split_off(1);
+        Ok(vec![HowToCommand::Conditional(self.parse_conditional(start, condition)?)])
+      },
+      _ => self.parse_branch()
+    }
+  }
+
+  fn parse_branch(&mut self) -> Result<Vec<HowToCommand>, ParserError> {
+    if self.peek_token()? != Some(Token::Punctuation(',')) {
+      self.skip_newlines()?;
+      return Ok(Vec::new());
+    }
+
+    self.bump()?;
+    Ok(vec![self.parse_inline_step()?])
+  }
+
+  /// Parses a conditional at the top level, where each branch is a
+  /// single command and `otherwise` starts a command of its own.
+  fn parse_top_level_conditional(&mut self, start: Span, condition: Vec<CommandComponent>) -> Result<Conditional, ParserError> {
+    let mut conditional: Conditional = self.parse_conditional(start, condition)?;
+    check_conditional(&conditional)?;
+
     loop {
-      let cmd: HowToCommand = self.parse_howto_command()?;
-      body.push(cmd);
+      self.skip_newlines()?;
 
-      tok = self.lexer.peek()?;
+      if !self.peek_word("otherwise")? {
+        return Ok(conditional);
+      }
+
+      let clause: Clause = self.parse_clause()?;
+
+      if self.clause_head(&clause)?.as_deref() != Some("otherwise") {
+        // It's a command that happens to start with `otherwise`, and the
+        // next statement.
+        self.clause = Some(clause);
+        return Ok(conditional);
+      }
+
+      let span: Span = clause.start;
+      let branch: Vec<HowToCommand> = self.parse_otherwise(clause)?;
+      let span: Span = span.to(branch.last().map_or(span, HowToCommand::span));
+
+      if branch.is_empty() {
+        return Err(ParserError::EmptyBranch { span });
+      }
+
+      attach_otherwise(&mut conditional, branch, span)?;
+    }
+  }
+
+  fn parse_howto_statement(&mut self, start: Span, signature: Vec<CommandComponent>) -> Result<HowToStatement, ParserError> {
+    if !self.parse_body_start()? {
+      return Err(ParserError::EmptyHowToBody { span: self.span_from(start) });
+    }
+
+    let mut bullets: Vec<Bullet<Step>> = Vec::new();
+
+    loop {
+      let (indent, bullet) = self.bump_bullet()?;
+      let item: Step = self.parse_step()?;
+      bullets.push(Bullet { indent, span: bullet, item });
+
+      let tok: Option<Token> = self.peek_token()?;
       match tok {
-        Some(Token::Punctuation('-')) => {
-          self.lexer.next()?;
-        },
+        Some(Token::Punctuation('-')) => {},
         Some(Token::Dot) => {
-          self.lexer.next()?;
+          self.bump()?;
           break;
         },
         Some(Token::Punctuation(_)) => {
-          return Err(ParserError::SyntaxError("Expected '-' or '.'".to_string()));
+          return Err(self.expected(&[TokenKind::Punctuation('-'), TokenKind::Dot]));
         },
         _ => { break }
       }
     };
 
+    let span: Span = start.to(bullets.last().map_or(start, |bullet| match &bullet.item {
+      Step::Do(command) => command.span(),
+      Step::Otherwise(_, span) => *span
+    }));
+    let body: Vec<HowToCommand> = fold_otherwise(nest(bullets, &nest_step)?)?;
+
     Ok(HowToStatement {
+      visibility: Visibility::Public,
       signature,
-      body
+      body,
+      span
     })
   }
 
-  fn parse_whatis_statement(&mut self) -> Result<WhatIsStatement, ParserError> {
-    let signature: Vec<CommandComponent> = self.parse_vec_command_component()?;
-
-    if self.lexer.peek()? != Some(Token::Question) {
-      return Err(ParserError::SyntaxError("Expected '?'".to_string()));
-    }
-
-    self.lexer.next()?;
-
-    if self.lexer.peek()? != Some(Token::Newline) {
-      return Err(ParserError::SyntaxError("Expected newline".to_string()));
+  fn parse_whatis_statement(&mut self, start: Span, signature: Vec<CommandComponent>) -> Result<WhatIsStatement, ParserError> {
+    if !self.parse_body_start()? {
+      return Err(ParserError::EmptyWhatIsBody { span: self.span_from(start) });
     }
 
-    self.lexer.next()?;
-
-    let mut body: Vec<Command> = Vec::new();
-
-    let mut tok: Option<Token> = self.lexer.peek()?;
-
-    match tok {
-      Some(Token::Punctuation(punc)) => {
-        if punc == '-' {
-          self.lexer.next()?;
-        } else {
-          return Err(ParserError::SyntaxError("Expected '-'".to_string()));
-        }
-      },
-      _ => {
-        return Err(ParserError::SyntaxError("Expected '-'".to_string()));
-      }
-    };
+    let mut bullets: Vec<Bullet<Command>> = Vec::new();
 
     loop {
-      let cmd: Command = self.parse_command()?;
-      body.push(cmd);
+      let (indent, bullet) = self.bump_bullet()?;
+      let item: Command = self.parse_command()?;
+      bullets.push(Bullet { indent, span: bullet, item });
 
-      tok = self.lexer.peek()?;
+      let tok: Option<Token> = self.peek_token()?;
       match tok {
-        Some(Token::Punctuation('-')) => {
-          self.lexer.next()?;
-        },
+        Some(Token::Punctuation('-')) => {},
         Some(Token::Newline) => {
-          self.lexer.next()?;
+          self.bump()?;
 
-          match self.lexer.peek()? {
+          match self.peek_token()? {
             Some(Token::Newline) => {
-              self.lexer.next()?;
+              self.bump()?;
               break;
             },
-            Some(Token::Punctuation('-')) => {
-              self.lexer.next()?;
-            },
+            Some(Token::Punctuation('-')) => {},
             None => {
               break;
             },
             _ => {
-              return Err(ParserError::SyntaxError("Expected newline or '-'".to_string()));
+              return Err(self.expected(&[TokenKind::Newline, TokenKind::Punctuation('-')]));
             }
           }
         },
         Some(Token::Punctuation(_)) => {
-          return Err(ParserError::SyntaxError("Expected '-'".to_string()));
+          return Err(self.expected(&[TokenKind::Punctuation('-')]));
         },
         _ => { break }
       }
     };
 
+    let span: Span = start.to(bullets.last().map_or(start, |bullet| bullet.item.span));
+    let body: Vec<Command> = nest(bullets, &|mut command: Command, children| {
+      command.children = children;
+      Ok(command)
+    })?;
+
     Ok(WhatIsStatement {
+      visibility: Visibility::Public,
       signature,
-      body
+      body,
+      span
     })
   }
 
-  // TODO: Move this to an iterator
-  pub fn next(&mut self) -> Result<Option<ParseNode>, ParserError> {
+  /// Parses a top-level statement from its first clause.
+  fn parse_statement(&mut self, clause: Clause) -> Result<ParseNode, ParserError> {
+    match self.clause_head(&clause)?.as_deref() {
+      Some("if") => {
+        let conditional: Conditional = self.parse_top_level_conditional(clause.start, clause.skip(1))?;
+        Ok(ParseNode::Conditional(conditional))
+      },
+      Some("otherwise") => {
+        Err(ParserError::OtherwiseWithoutIf { span: clause.start })
+      },
+      Some(word) if is_loop_start(word) => {
+        let repeat: Loop = self.parse_loop(word, clause.start, clause.skip(1))?;
+        check_step(&HowToCommand::Loop(repeat.clone()))?;
+        Ok(ParseNode::Loop(repeat))
+      },
+      Some("private") if clause.word(1) == "howto" => {
+        let mut howto: HowToStatement = self.parse_howto_statement(clause.start, clause.skip(2))?;
+        howto.visibility = Visibility::Private;
+        Ok(ParseNode::HowToStatement(howto))
+      },
+      Some("private") => {
+        let mut whatis: WhatIsStatement = self.parse_whatis_statement(clause.start, clause.skip(2))?;
+        whatis.visibility = Visibility::Private;
+        Ok(ParseNode::WhatIsStatement(whatis))
+      },
+      Some("module") => {
+        Ok(ParseNode::Module(self.parse_module(clause.start, clause.skip(1))?))
+      },
+      Some("within") => {
+        Ok(ParseNode::Qualified(self.parse_qualified(clause.start, clause.skip(1))?))
+      },
+      _ => {
+        let mut command: Command = self.parse_command_from(clause)?;
+
+        if let Some(import) = Import::from_command(&command) {
+          return Ok(ParseNode::Import(import));
+        }
+
+        match split_binding(&mut command) {
+          Some(name) => Ok(ParseNode::Binding(Binding::new(command, name))),
+          None => Ok(ParseNode::Command(command))
+        }
+      }
+    }
+  }
+
+  /// Parses the next top-level node. Unlike the [`Iterator`]
+  /// implementation, this can be called again after an error, though
+  /// [`Parser::parse_with_recovery`] is usually what's wanted then.
+  pub fn next_node(&mut self) -> Result<Option<ParseNode>, ParserError> {
     if self.peeked.is_some() {
-      let peeked: Option<ParseNode> = self.peeked.clone();
-      self.peeked = None;
-      return Ok(peeked);
+      return Ok(self.peeked.take());
     }
 
-    let mut token: Token = match self.lexer.peek()? {
+    if let Some(clause) = self.clause.take() {
+      return self.parse_statement(clause).map(Some);
+    }
+
+    let mut token: SpannedToken = match self.lexer.peek()? {
       Some(tok) => tok,
       None => {
         return Ok(None);
       }
     };
 
-    while token == Token::Newline {
-      self.lexer.next()?;
+    while token.token == Token::Newline {
+      self.bump()?;
       token = match self.lexer.peek()? {
         Some(tok) => tok,
         None => {
@@ -359,42 +1729,142 @@ impl<'s> Parser<'s> {
       };
     }
 
-    match token {
+    match token.token {
       Token::Keyword(kw) => {
         match kw.as_str() {
           "howto" => {
-            self.lexer.next()?;
-            let howto: HowToStatement = self.parse_howto_statement()?;
+            self.bump()?;
+            let signature: Vec<CommandComponent> = self.parse_vec_command_component()?;
+            let howto: HowToStatement = self.parse_howto_statement(token.span, signature)?;
             Ok(Some(ParseNode::HowToStatement(howto)))
           },
           "whatis" => {
-            self.lexer.next()?;
-            let whatis: WhatIsStatement = self.parse_whatis_statement()?;
+            self.bump()?;
+            let signature: Vec<CommandComponent> = self.parse_vec_command_component()?;
+            let whatis: WhatIsStatement = self.parse_whatis_statement(token.span, signature)?;
             Ok(Some(ParseNode::WhatIsStatement(whatis)))
           },
           _ => {
-            Err(ParserError::InternalError("Unexpected keyword".to_string()))
+            Err(ParserError::UnexpectedKeyword { keyword: kw, span: token.span })
           }
         }
       },
+      Token::Question => {
+        self.bump()?;
+        Ok(Some(ParseNode::Query(self.parse_query(token.span)?)))
+      },
       Token::Identifier(_) => {
-        Ok(Some(ParseNode::Command(self.parse_command()?)))
+        let clause: Clause = self.parse_clause()?;
+        self.parse_statement(clause).map(Some)
       },
-      _ => {
-        Err(ParserError::InternalError("Unexpected token".to_string()))
+      found => {
+        Err(ParserError::UnexpectedToken { found, span: token.span })
       }
     }
   }
 
   pub fn peek(&mut self) -> Result<Option<ParseNode>, ParserError> {
     if self.peeked.is_none() {
-      self.peeked = self.next()?;
+      self.peeked = self.next_node()?;
     }
 
     Ok(self.peeked.clone())
   }
+
+  /// Skips ahead to a point where parsing can safely resume after an error:
+  /// a blank line, or the next `howto`, `whatis`, or `private` or `module`
+  /// at the start of a line.
+  fn synchronize(&mut self) {
+    self.peeked = None;
+    self.clause = None;
+
+    let mut after_newline: bool = false;
+
+    loop {
+      match self.lexer.peek() {
+        Ok(Some(tok)) => {
+          if is_statement_start(&tok) {
+            return;
+          }
+
+          match tok.token {
+            Token::Newline => {
+              let _ = self.bump();
+
+              if after_newline {
+                return;
+              }
+
+              after_newline = true;
+            },
+            _ => {
+              let _ = self.bump();
+              after_newline = false;
+            }
+          }
+        },
+        Ok(None) => {
+          return;
+        },
+        Err(_) => {
+          // The lexer has already stepped over the bad input, and
+          // anything in here is being thrown away regardless.
+          after_newline = false;
+        }
+      }
+    }
+  }
+
+  /// Parses the whole input, recovering from syntax errors instead of
+  /// stopping at the first one. Returns every node that could be parsed
+  /// along with every error that was encountered.
+  pub fn parse_with_recovery(&mut self) -> (Vec<ParseNode>, Vec<ParserError>) {
+    let mut nodes: Vec<ParseNode> = Vec::new();
+    let mut errors: Vec<ParserError> = Vec::new();
+
+    loop {
+      match self.next_node() {
+        Ok(Some(node)) => nodes.push(node),
+        Ok(None) => break,
+        Err(err) => {
+          errors.push(err);
+          self.synchronize();
+        }
+      }
+    }
+
+    (nodes, errors)
+  }
+
+  /// Parses the whole input, stopping at the first syntax error.
+  pub fn parse_all(&mut self) -> Result<Program, ParserError> {
+    let nodes: Vec<ParseNode> = self.collect::<Result<_, _>>()?;
+    Ok(Program { nodes })
+  }
 }
 
+/// Yields nodes until the end of the input or the first error, after
+/// which it only yields `None`.
+impl<'s> Iterator for Parser<'s> {
+  type Item = Result<ParseNode, ParserError>;
+
+  fn next(&mut self) -> Option<Self::Item> {
+    if self.failed {
+      return None;
+    }
+
+    match self.next_node() {
+      Ok(node) => node.map(Ok),
+      Err(err) => {
+        self.failed = true;
+        Some(Err(err))
+      }
+    }
+  }
+}
+
+impl<'s> std::iter::FusedIterator for Parser<'s> {}
+
 impl<'s> From<&'s str> for Parser<'s> {
   fn from(data: &'s str) -> Self {
     Parser::new(Lexer::from(data))
@@ -0,0 +1,247 @@
+/*
+
+Copyright (C) 2023 Carlos Kieliszewski
+
+This file is part of the Circe Project.
+
+Circe is free software: you can redistribute it and/or modify it under
+the terms of the GNU General Public License as published by the Free
+Software Foundation, either version 3 of the License, or (at your option)
+any later version.
+
+Circe is distributed in the hope that it will be useful, but WITHOUT ANY
+WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+FOR A PARTICULAR PURPOSE. See the GNU General Public License for more details.
+
+You should have received a copy of the GNU General Public License along with
+Circe. If not, see <https://www.gnu.org/licenses/>. 
+
+*/
+
+
+use cce_ast::cst::*;
+use cce_ast::{Parser, Program};
+use cce_stream::{Span, FileId};
+
+fn span(start: usize, end: usize) -> Span {
+  Span::new(FileId(0), start, end)
+}
+
+fn assert_lossless(source: &str) {
+  let tree = SyntaxTree::parse(source);
+
+  assert_eq!(tree.text(), source);
+  assert_eq!(tree.root().span(), span(0, source.len()));
+  assert!(tree.errors().is_empty());
+
+  let program: Program = Parser::from(source).parse_all().unwrap();
+  assert_eq!(tree.to_program(), program);
+}
+
+#[test]
+fn test_cst_examples() {
+  assert_lossless(include_str!("./examples/hello.cce"));
+  assert_lossless(include_str!("./examples/full_hello.cce"));
+  assert_lossless(include_str!("./examples/lowlevel.cce"));
+}
+
+#[test]
+fn test_cst_comments_and_literals() {
+  assert_lossless(concat!(
+    "// greetings\n",
+    "howto greet %name?\n",
+    "- print 'hi, \\'friend\\'' /* inline */ | add 0x0a\n",
+    "// between\n",
+    "- print the name.\n",
+    "\n",
+    "whatis a greeting?\n",
+    "- a '''\nlong\n''' message\n"
+  ));
+}
+
+#[test]
+fn test_cst_nested_bullets() {
+  let source: &str = "howto greet?\n- say hi | loud\n  // first\n  - wave\n    - twice\n  - smile\n- leave.";
+  assert_lossless(source);
+
+  let tree: SyntaxTree = SyntaxTree::parse(source);
+  let howto: SyntaxNode = tree.root().children()[0].clone();
+  let steps: Vec<SyntaxNode> = howto.children();
+  assert_eq!(steps.len(), 2);
+  assert_eq!(steps[0].text(), "say hi | loud\n  // first\n  - wave\n    - twice\n  - smile");
+  assert_eq!(steps[0].children().iter().map(SyntaxNode::text).collect::<Vec<_>>(), vec!["wave\n    - twice", "smile"]);
+}
+
+#[test]
+fn test_cst_typed_slots() {
+  let source: &str = "howto print %text : a string to %out: 'file stream'?\n- write %text.";
+  assert_lossless(source);
+
+  let tree: SyntaxTree = SyntaxTree::parse(source);
+  let slots: Vec<String> = tree.root().children()[0].children().iter()
+    .filter(|node| node.kind() == SyntaxKind::Slot)
+    .map(SyntaxNode::text)
+    .collect();
+  assert_eq!(slots, vec!["%text : a string".to_string(), "%out: 'file stream'".to_string()]);
+}
+
+#[test]
+fn test_cst_conditionals() {
+  assert_lossless(concat!(
+    "howto greet?\n",
+    "- if it is late, say bye\n",
+    "| quietly\n",
+    "  - wave\n",
+    "- otherwise if %t: a time\n",
+    "  // early\n",
+    "  -$$ fn yawn() {} $$\n",
+    "- otherwise\n",
+    "  - if it is sunny, smile\n",
+    "- say hi.\n",
+    "\n",
+    "if it rains, stay.\n",
+    "otherwise, go out.\n"
+  ));
+}
+
+#[test]
+fn test_cst_loops() {
+  assert_lossless(concat!(
+    "howto print the file %f?\n",
+    "- for each %line: a line in %f\n",
+    "  - print %line\n",
+    "  // skip blanks\n",
+    "  - if %line is empty, stop\n",
+    "- repeat %n times, beep | loudly\n",
+    "- while the door is open\n",
+    "  -$$ fn knock() {} $$\n",
+    "- say done.\n",
+    "\n",
+    "repeat 2 times, if it rains, stay.\n"
+  ));
+}
+
+#[test]
+fn test_cst_statement_words() {
+  assert_lossless(concat!(
+    "repeat the song.
+",
+    "module the house.
+",
+    "if late, run.
+",
+    "otherwise engaged.
+",
+    "
+",
+    "howto rest?
+",
+    "- if tired
+",
+    "  - otherwise engaged
+",
+    "- otherwise, print it if it fails
+",
+    "- within reason, for now.
+"
+  ));
+}
+
+#[test]
+fn test_cst_bindings() {
+  assert_lossless(concat!(
+    "read a line | from the console and call it name.\n",
+    "print %name.\n",
+    "\n",
+    "howto greet?\n",
+    "- ask for %x and  call it who\n",
+    "  // be nice\n",
+    "  - politely\n",
+    "- if %who is empty, ask again and call it who\n",
+    "- say %who.\n"
+  ));
+}
+
+#[test]
+fn test_cst_imports() {
+  assert_lossless("use 'console'.\ninclude  './greet' .\nuse the hammer.\n");
+}
+
+#[test]
+fn test_cst_modules() {
+  assert_lossless("module  console .\nprivate  howto print %s?\n- within io ,write %s\n  - loudly.\nwithin console, print 'hi'.\n");
+
+  let tree = SyntaxTree::parse("module console.\nwithin console, print 'hi'.\n");
+  let kinds: Vec<SyntaxKind> = tree.root().children().iter().map(SyntaxNode::kind).collect();
+  assert_eq!(kinds, vec![SyntaxKind::Module, SyntaxKind::Qualified]);
+}
+
+#[test]
+fn test_cst_queries() {
+  assert_lossless("?  whatis stdout\n? howto print %n: a number .\n?howto greet 'Ann'?\n");
+
+  let tree = SyntaxTree::parse("? howto print %n\n");
+  let query: SyntaxNode = tree.root().children().remove(0);
+  assert_eq!(query.kind(), SyntaxKind::Query);
+  assert_eq!(query.children().iter().map(SyntaxNode::kind).collect::<Vec<SyntaxKind>>(), vec![SyntaxKind::Slot]);
+}
+
+#[test]
+fn test_cst_structure() {
+  let tree = SyntaxTree::parse("say %x | loud.\n");
+  let root: &SyntaxNode = tree.root();
+
+  let kinds: Vec<SyntaxKind> = root.children_with_tokens().iter().map(SyntaxElement::kind).collect();
+  assert_eq!(kinds, vec![SyntaxKind::Command, SyntaxKind::Dot, SyntaxKind::Newline]);
+
+  let command: SyntaxNode = root.children().remove(0);
+  assert_eq!(command.span(), span(0, 13));
+  assert_eq!(command.text(), "say %x | loud");
+  assert_eq!(command.parent().unwrap().kind(), SyntaxKind::Root);
+
+  let tokens: Vec<(SyntaxKind, String)> = command.tokens().iter()
+    .map(|token| (token.kind(), token.text().to_string()))
+    .collect();
+
+  assert_eq!(tokens, vec![
+    (SyntaxKind::Identifier, "say".to_string()),
+    (SyntaxKind::Whitespace, " ".to_string()),
+    (SyntaxKind::Percent, "%".to_string()),
+    (SyntaxKind::Identifier, "x".to_string()),
+    (SyntaxKind::Whitespace, " ".to_string()),
+    (SyntaxKind::Pipe, "|".to_string()),
+    (SyntaxKind::Whitespace, " ".to_string()),
+    (SyntaxKind::Identifier, "loud".to_string())
+  ]);
+
+  let slot: SyntaxNode = command.children().remove(0);
+  assert_eq!(slot.kind(), SyntaxKind::Slot);
+  assert_eq!(slot.span(), span(4, 6));
+  assert_eq!(slot.tokens()[1].span(), span(5, 6));
+}
+
+#[test]
+fn test_cst_errors_are_kept() {
+  let source: &str = "say & hi.\n\nhowto %?\n\nsay 0b12 bye.\n";
+  let tree = SyntaxTree::parse(source);
+
+  assert_eq!(tree.text(), source);
+  assert_eq!(tree.errors().len(), 3);
+  assert!(tree.to_program().nodes.is_empty());
+
+  let errors: Vec<String> = tree.root().tokens().iter()
+    .filter(|token| token.kind() == SyntaxKind::Error)
+    .map(|token| token.text().to_string())
+    .collect();
+
+  assert_eq!(errors, vec!["&".to_string(), "0b12".to_string()]);
+}
+
+#[test]
+fn test_cst_green_sharing() {
+  let first = SyntaxTree::parse("say hi.");
+  let second = SyntaxTree::parse("say hi.");
+
+  assert_eq!(first.root().green(), second.root().green());
+  assert_eq!(first.root().green().width, 7);
+}
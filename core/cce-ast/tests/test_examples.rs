@@ -20,6 +20,11 @@ Circe. If not, see <https://www.gnu.org/licenses/>.
 
 
 use cce_ast::*;
+use cce_stream::{Span, FileId};
+
+fn span(start: usize, end: usize) -> Span {
+  Span::new(FileId(0), start, end)
+}
 
 #[test]
 fn test_parser_example_helloworld() {
@@ -28,61 +33,364 @@ fn test_parser_example_helloworld() {
   let expected_output = vec![
     ParseNode::Command(Command {
       components: vec![
-        CommandComponent::Keyword("print".to_string()),
-        CommandComponent::Literal("Hello, world!".to_string()),
-        CommandComponent::Keyword("to".to_string()),
-        CommandComponent::Keyword("the".to_string()),
-        CommandComponent::Keyword("console".to_string())
+        CommandComponent::Keyword("print".to_string(), span(0, 5)),
+        CommandComponent::Literal("Hello, world!".to_string(), span(6, 21)),
+        CommandComponent::Keyword("to".to_string(), span(22, 24)),
+        CommandComponent::Keyword("the".to_string(), span(25, 28)),
+        CommandComponent::Keyword("console".to_string(), span(29, 36))
       ],
-      modifiers: vec![]
+      modifiers: vec![],
+      children: vec![],
+      span: span(0, 36)
     }),
     ParseNode::HowToStatement(HowToStatement {
+      visibility: Visibility::Public,
       signature: vec![
-        CommandComponent::Keyword("print".to_string()),
-        CommandComponent::Keyword("a".to_string()),
-        CommandComponent::Keyword("string".to_string()),
-        CommandComponent::Keyword("to".to_string()),
-        CommandComponent::Keyword("the".to_string()),
-        CommandComponent::Keyword("console".to_string())
+        CommandComponent::Keyword("print".to_string(), span(45, 50)),
+        CommandComponent::Keyword("a".to_string(), span(51, 52)),
+        CommandComponent::Keyword("string".to_string(), span(53, 59)),
+        CommandComponent::Keyword("to".to_string(), span(60, 62)),
+        CommandComponent::Keyword("the".to_string(), span(63, 66)),
+        CommandComponent::Keyword("console".to_string(), span(67, 74))
       ],
       body: vec![
         HowToCommand::HighLevel(Command {
           components: vec![
-            CommandComponent::Keyword("write".to_string()),
-            CommandComponent::Keyword("the".to_string()),
-            CommandComponent::Keyword("string".to_string()),
-            CommandComponent::Keyword("to".to_string()),
-            CommandComponent::Keyword("stdout".to_string())
+            CommandComponent::Keyword("write".to_string(), span(78, 83)),
+            CommandComponent::Keyword("the".to_string(), span(84, 87)),
+            CommandComponent::Keyword("string".to_string(), span(88, 94)),
+            CommandComponent::Keyword("to".to_string(), span(95, 97)),
+            CommandComponent::Keyword("stdout".to_string(), span(98, 104))
           ],
           modifiers: vec![vec![
-              CommandComponent::Keyword("add".to_string()),
-              CommandComponent::Keyword("a".to_string()),
-              CommandComponent::Keyword("newline".to_string())
-          ]]
+              CommandComponent::Keyword("add".to_string(), span(107, 110)),
+              CommandComponent::Keyword("a".to_string(), span(111, 112)),
+              CommandComponent::Keyword("newline".to_string(), span(113, 120))
+          ]],
+          children: vec![],
+          span: span(78, 120)
         })
-      ]
+      ],
+      span: span(39, 120)
+    }),
+    ParseNode::WhatIsStatement(WhatIsStatement {
+      visibility: Visibility::Public,
+      signature: vec![
+        CommandComponent::Literal("stdout".to_string(), span(129, 137))
+      ],
+      body: vec![
+        Command {
+          components: vec![
+            CommandComponent::Keyword("a".to_string(), span(141, 142)),
+            CommandComponent::Keyword("file".to_string(), span(143, 147)),
+            CommandComponent::Keyword("stream".to_string(), span(148, 154))
+          ],
+          modifiers: vec![],
+          children: vec![],
+          span: span(141, 154)
+        }
+      ],
+      span: span(122, 154)
+    })
+  ];
+
+  let output: Program = parser.parse_all().unwrap();
+
+  assert_eq!(output.nodes, expected_output);
+}
+
+#[test]
+fn test_parser_example_full_helloworld() {
+  let mut parser = Parser::from(include_str!("./examples/full_hello.cce"));
+
+  let expected_output = vec![
+    ParseNode::Command(Command {
+      components: vec![
+        CommandComponent::Keyword("print".to_string(), span(0, 5)),
+        CommandComponent::Literal("Hello, world!".to_string(), span(6, 21)),
+        CommandComponent::Keyword("to".to_string(), span(22, 24)),
+        CommandComponent::Keyword("the".to_string(), span(25, 28)),
+        CommandComponent::Keyword("console".to_string(), span(29, 36))
+      ],
+      modifiers: vec![],
+      children: vec![],
+      span: span(0, 36)
+    }),
+    ParseNode::HowToStatement(HowToStatement {
+      visibility: Visibility::Public,
+      signature: vec![
+        CommandComponent::Keyword("print".to_string(), span(45, 50)),
+        CommandComponent::Keyword("a".to_string(), span(51, 52)),
+        CommandComponent::Keyword("string".to_string(), span(53, 59)),
+        CommandComponent::Keyword("to".to_string(), span(60, 62)),
+        CommandComponent::Keyword("the".to_string(), span(63, 66)),
+        CommandComponent::Keyword("console".to_string(), span(67, 74))
+      ],
+      body: vec![
+        HowToCommand::HighLevel(Command {
+          components: vec![
+            CommandComponent::Keyword("write".to_string(), span(78, 83)),
+            CommandComponent::Keyword("the".to_string(), span(84, 87)),
+            CommandComponent::Keyword("string".to_string(), span(88, 94)),
+            CommandComponent::Keyword("to".to_string(), span(95, 97)),
+            CommandComponent::Keyword("stdout".to_string(), span(98, 104))
+          ],
+          modifiers: vec![],
+          children: vec![],
+          span: span(78, 104)
+        }),
+        HowToCommand::HighLevel(Command {
+          components: vec![
+            CommandComponent::Keyword("add".to_string(), span(107, 110)),
+            CommandComponent::Keyword("a".to_string(), span(111, 112)),
+            CommandComponent::Keyword("newline".to_string(), span(113, 120))
+          ],
+          modifiers: vec![],
+          children: vec![],
+          span: span(107, 120)
+        })
+      ],
+      span: span(39, 120)
+    }),
+    ParseNode::WhatIsStatement(WhatIsStatement {
+      visibility: Visibility::Public,
+      signature: vec![
+        CommandComponent::Literal("stdout".to_string(), span(129, 137))
+      ],
+      body: vec![
+        Command {
+          components: vec![
+            CommandComponent::Keyword("the".to_string(), span(141, 144)),
+            CommandComponent::Keyword("standard".to_string(), span(145, 153)),
+            CommandComponent::Keyword("output".to_string(), span(154, 160)),
+            CommandComponent::Keyword("stream".to_string(), span(161, 167))
+          ],
+          modifiers: vec![],
+          children: vec![],
+          span: span(141, 167)
+        },
+        Command {
+          components: vec![
+            CommandComponent::Keyword("file".to_string(), span(170, 174)),
+            CommandComponent::Keyword("descriptor".to_string(), span(175, 185)),
+            CommandComponent::Number(1, span(186, 187))
+          ],
+          modifiers: vec![],
+          children: vec![],
+          span: span(170, 187)
+        }
+      ],
+      span: span(122, 187)
+    }),
+    ParseNode::WhatIsStatement(WhatIsStatement {
+      visibility: Visibility::Public,
+      signature: vec![
+        CommandComponent::Keyword("a".to_string(), span(196, 197)),
+        CommandComponent::Literal("file descriptor".to_string(), span(198, 215))
+      ],
+      body: vec![
+        Command {
+          components: vec![
+            CommandComponent::Keyword("a".to_string(), span(219, 220)),
+            CommandComponent::Keyword("number".to_string(), span(221, 227)),
+            CommandComponent::Keyword("that".to_string(), span(228, 232)),
+            CommandComponent::Keyword("refers".to_string(), span(233, 239)),
+            CommandComponent::Keyword("to".to_string(), span(240, 242)),
+            CommandComponent::Keyword("a".to_string(), span(243, 244)),
+            CommandComponent::Keyword("file".to_string(), span(245, 249))
+          ],
+          modifiers: vec![],
+          children: vec![],
+          span: span(219, 249)
+        }
+      ],
+      span: span(189, 249)
     }),
     ParseNode::WhatIsStatement(WhatIsStatement {
+      visibility: Visibility::Public,
       signature: vec![
-        CommandComponent::Literal("stdout".to_string())
+        CommandComponent::Keyword("a".to_string(), span(258, 259)),
+        CommandComponent::Literal("newline".to_string(), span(260, 269))
       ],
       body: vec![
         Command {
           components: vec![
-            CommandComponent::Keyword("a".to_string()),
-            CommandComponent::Keyword("file".to_string()),
-            CommandComponent::Keyword("stream".to_string())
+            CommandComponent::Keyword("a".to_string(), span(273, 274)),
+            CommandComponent::Keyword("character".to_string(), span(275, 284)),
+            CommandComponent::Keyword("that".to_string(), span(285, 289)),
+            CommandComponent::Keyword("indicates".to_string(), span(290, 299)),
+            CommandComponent::Keyword("the".to_string(), span(300, 303)),
+            CommandComponent::Keyword("end".to_string(), span(304, 307)),
+            CommandComponent::Keyword("of".to_string(), span(308, 310)),
+            CommandComponent::Keyword("a".to_string(), span(311, 312)),
+            CommandComponent::Keyword("line".to_string(), span(313, 317))
           ],
-          modifiers: vec![]
+          modifiers: vec![],
+          children: vec![],
+          span: span(273, 317)
+        },
+        Command {
+          components: vec![
+            CommandComponent::Keyword("byte".to_string(), span(320, 324)),
+            CommandComponent::Number(10, span(325, 329))
+          ],
+          modifiers: vec![],
+          children: vec![],
+          span: span(320, 329)
         }
-      ]
+      ],
+      span: span(251, 329)
+    }),
+    ParseNode::HowToStatement(HowToStatement {
+      visibility: Visibility::Public,
+      signature: vec![
+        CommandComponent::Keyword("write".to_string(), span(338, 343)),
+        CommandComponent::Keyword("a".to_string(), span(344, 345)),
+        CommandComponent::Keyword("string".to_string(), span(346, 352)),
+        CommandComponent::Keyword("to".to_string(), span(353, 355)),
+        CommandComponent::Keyword("a".to_string(), span(356, 357)),
+        CommandComponent::Keyword("file".to_string(), span(358, 362)),
+        CommandComponent::Keyword("descriptor".to_string(), span(363, 373))
+      ],
+      body: vec![
+        HowToCommand::Primitive(vec![
+          Command {
+            components: vec![
+              CommandComponent::Keyword("run".to_string(), span(381, 384)),
+              CommandComponent::Keyword("the".to_string(), span(385, 388)),
+              CommandComponent::Literal("write".to_string(), span(389, 396)),
+              CommandComponent::Keyword("system".to_string(), span(397, 403)),
+              CommandComponent::Keyword("call".to_string(), span(404, 408))
+            ],
+            modifiers: vec![
+              vec![
+                CommandComponent::Keyword("the".to_string(), span(411, 414)),
+                CommandComponent::Keyword("first".to_string(), span(415, 420)),
+                CommandComponent::Keyword("argument".to_string(), span(421, 429)),
+                CommandComponent::Keyword("is".to_string(), span(430, 432)),
+                CommandComponent::Keyword("the".to_string(), span(433, 436)),
+                CommandComponent::Keyword("file".to_string(), span(437, 441)),
+                CommandComponent::Keyword("descriptor".to_string(), span(442, 452))
+              ],
+              vec![
+                CommandComponent::Keyword("the".to_string(), span(455, 458)),
+                CommandComponent::Keyword("second".to_string(), span(459, 465)),
+                CommandComponent::Keyword("argument".to_string(), span(466, 474)),
+                CommandComponent::Keyword("is".to_string(), span(475, 477)),
+                CommandComponent::Keyword("a".to_string(), span(478, 479)),
+                CommandComponent::Keyword("pointer".to_string(), span(480, 487)),
+                CommandComponent::Keyword("to".to_string(), span(488, 490)),
+                CommandComponent::Keyword("the".to_string(), span(491, 494)),
+                CommandComponent::Keyword("string".to_string(), span(495, 501)),
+                CommandComponent::Keyword("data".to_string(), span(502, 506))
+              ],
+              vec![
+                CommandComponent::Keyword("the".to_string(), span(509, 512)),
+                CommandComponent::Keyword("third".to_string(), span(513, 518)),
+                CommandComponent::Keyword("argument".to_string(), span(519, 527)),
+                CommandComponent::Keyword("is".to_string(), span(528, 530)),
+                CommandComponent::Keyword("the".to_string(), span(531, 534)),
+                CommandComponent::Keyword("length".to_string(), span(535, 541)),
+                CommandComponent::Keyword("of".to_string(), span(542, 544)),
+                CommandComponent::Keyword("the".to_string(), span(545, 548)),
+                CommandComponent::Keyword("string".to_string(), span(549, 555))
+              ]
+            ],
+            children: vec![],
+            span: span(381, 555)
+          }
+        ], span(375, 561))
+      ],
+      span: span(332, 561)
+    })
+  ];
+
+  let output: Program = parser.parse_all().unwrap();
+
+  assert_eq!(output.nodes, expected_output);
+}
+
+#[test]
+fn test_parser_example_lowlevel() {
+  let mut parser = Parser::from(include_str!("./examples/lowlevel.cce"));
+
+  let expected_output = vec![
+    ParseNode::Command(Command {
+      components: vec![
+        CommandComponent::Keyword("write".to_string(), span(0, 5)),
+        CommandComponent::Literal("Hello, world!".to_string(), span(6, 21)),
+        CommandComponent::Keyword("to".to_string(), span(22, 24)),
+        CommandComponent::Keyword("stdout".to_string(), span(25, 31))
+      ],
+      modifiers: vec![],
+      children: vec![],
+      span: span(0, 31)
+    }),
+    ParseNode::HowToStatement(HowToStatement {
+      visibility: Visibility::Public,
+      signature: vec![
+        CommandComponent::Keyword("write".to_string(), span(40, 45)),
+        CommandComponent::Keyword("a".to_string(), span(46, 47)),
+        CommandComponent::Keyword("string".to_string(), span(48, 54)),
+        CommandComponent::Keyword("to".to_string(), span(55, 57)),
+        CommandComponent::Keyword("a".to_string(), span(58, 59)),
+        CommandComponent::Keyword("file".to_string(), span(60, 64)),
+        CommandComponent::Keyword("descriptor".to_string(), span(65, 75))
+      ],
+      body: vec![
+        HowToCommand::Primitive(vec![
+          Command {
+            components: vec![
+              CommandComponent::Keyword("run".to_string(), span(83, 86)),
+              CommandComponent::Keyword("the".to_string(), span(87, 90)),
+              CommandComponent::Literal("write".to_string(), span(91, 98)),
+              CommandComponent::Keyword("system".to_string(), span(99, 105)),
+              CommandComponent::Keyword("call".to_string(), span(106, 110))
+            ],
+            modifiers: vec![
+              vec![
+                CommandComponent::Keyword("the".to_string(), span(113, 116)),
+                CommandComponent::Keyword("first".to_string(), span(117, 122)),
+                CommandComponent::Keyword("argument".to_string(), span(123, 131)),
+                CommandComponent::Keyword("is".to_string(), span(132, 134)),
+                CommandComponent::Keyword("the".to_string(), span(135, 138)),
+                CommandComponent::Keyword("file".to_string(), span(139, 143)),
+                CommandComponent::Keyword("descriptor".to_string(), span(144, 154))
+              ],
+              vec![
+                CommandComponent::Keyword("the".to_string(), span(157, 160)),
+                CommandComponent::Keyword("second".to_string(), span(161, 167)),
+                CommandComponent::Keyword("argument".to_string(), span(168, 176)),
+                CommandComponent::Keyword("is".to_string(), span(177, 179)),
+                CommandComponent::Keyword("a".to_string(), span(180, 181)),
+                CommandComponent::Keyword("pointer".to_string(), span(182, 189)),
+                CommandComponent::Keyword("to".to_string(), span(190, 192)),
+                CommandComponent::Keyword("the".to_string(), span(193, 196)),
+                CommandComponent::Keyword("string".to_string(), span(197, 203)),
+                CommandComponent::Keyword("data".to_string(), span(204, 208))
+              ],
+              vec![
+                CommandComponent::Keyword("the".to_string(), span(211, 214)),
+                CommandComponent::Keyword("third".to_string(), span(215, 220)),
+                CommandComponent::Keyword("argument".to_string(), span(221, 229)),
+                CommandComponent::Keyword("is".to_string(), span(230, 232)),
+                CommandComponent::Keyword("the".to_string(), span(233, 236)),
+                CommandComponent::Keyword("length".to_string(), span(237, 243)),
+                CommandComponent::Keyword("of".to_string(), span(244, 246)),
+                CommandComponent::Keyword("the".to_string(), span(247, 250)),
+                CommandComponent::Keyword("string".to_string(), span(251, 257))
+              ]
+            ],
+            children: vec![],
+            span: span(83, 257)
+          }
+        ], span(77, 263))
+      ],
+      span: span(34, 263)
     })
   ];
 
-  let mut output: Vec<ParseNode> = Vec::new();
-  while let Some(node) = parser.next().unwrap() {
-    output.push(node)
-  }
+  let output: Program = parser.parse_all().unwrap();
 
-  assert_eq!(output, expected_output);
-}
\ No newline at end of file
+  assert_eq!(output.nodes, expected_output);
+}
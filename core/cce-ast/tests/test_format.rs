@@ -0,0 +1,361 @@
+/*
+
+Copyright (C) 2023 Carlos Kieliszewski
+
+This file is part of the Circe Project.
+
+Circe is free software: you can redistribute it and/or modify it under
+the terms of the GNU General Public License as published by the Free
+Software Foundation, either version 3 of the License, or (at your option)
+any later version.
+
+Circe is distributed in the hope that it will be useful, but WITHOUT ANY
+WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+FOR A PARTICULAR PURPOSE. See the GNU General Public License for more details.
+
+You should have received a copy of the GNU General Public License along with
+Circe. If not, see <https://www.gnu.org/licenses/>. 
+
+*/
+
+
+use cce_ast::{format, Parser, Program};
+use circelang_hash::CirceHash;
+
+/// Formats `source` and checks that the result means the same thing and
+/// formats to itself.
+fn assert_round_trip(source: &str) -> String {
+  let formatted: String = format(source).unwrap();
+
+  let before: Program = Parser::from(source).parse_all().unwrap();
+  let after: Program = Parser::from(formatted.as_str()).parse_all().unwrap();
+
+  // Spans don't contribute to the hash, so this compares everything else.
+  assert_eq!(before.hash(), after.hash(), "formatting changed the meaning of:\n{}", formatted);
+  assert_eq!(format(&formatted).unwrap(), formatted);
+
+  formatted
+}
+
+#[test]
+fn test_format_examples() {
+  let expected: &str = "\
+print 'Hello, world!' to the console.
+
+howto print a string to the console?
+- write the string to stdout
+| add a newline
+
+whatis 'stdout'?
+- a file stream
+";
+
+  assert_eq!(assert_round_trip(include_str!("./examples/hello.cce")), expected);
+
+  assert_round_trip(include_str!("./examples/full_hello.cce"));
+  assert_round_trip(include_str!("./examples/lowlevel.cce"));
+}
+
+#[test]
+fn test_format_layout() {
+  let source: &str = "say   hi |loud.  say bye.\nhowto   greet %name ?\n- say hi | to %name -say bye\n\n\n\nwhatis a 'greeting' ?\n- a word| spoken.";
+
+  let expected: &str = "\
+say hi
+| loud.
+say bye.
+
+howto greet %name?
+- say hi
+| to %name
+- say bye
+
+whatis a 'greeting'?
+- a word
+| spoken
+";
+
+  assert_eq!(assert_round_trip(source), expected);
+}
+
+#[test]
+fn test_format_nested() {
+  let source: &str = "howto greet?\n- say hi\n\t- wave |slowly\n\t\t// twice\n\t\t- again\n\t- smile\n- leave.";
+
+  let expected: &str = "\
+howto greet?
+- say hi
+  - wave
+  | slowly
+    // twice
+    - again
+  - smile
+- leave
+";
+
+  assert_eq!(assert_round_trip(source), expected);
+}
+
+#[test]
+fn test_format_typed_slots() {
+  let source: &str = "howto print %text :a  string to %out:'file stream'?\n- write %text.";
+  let expected: &str = "howto print %text: a string to %out: 'file stream'?\n- write %text\n";
+
+  assert_eq!(assert_round_trip(source), expected);
+}
+
+#[test]
+fn test_format_conditionals() {
+  let source: &str = "\
+if it rains,stay.  otherwise   if it snows , ski.otherwise,go out.
+howto greet?
+- if it is late
+  - say bye | quietly
+    - wave
+- otherwise, if it is early
+    - yawn
+    -*
+    < stretch
+    >
+    -*
+- otherwise,say hi.";
+
+  let expected: &str = "\
+if it rains, stay.
+otherwise if it snows, ski.
+otherwise, go out.
+
+howto greet?
+- if it is late
+  - say bye
+  | quietly
+    - wave
+- otherwise if it is early
+  - yawn
+  -*
+  < stretch
+  >
+  -*
+- otherwise, say hi
+";
+
+  assert_eq!(assert_round_trip(source), expected);
+}
+
+#[test]
+fn test_format_loops() {
+  let source: &str = "\
+for each   %x in the list,print %x.
+repeat 2 times,while it rains , wait.
+howto print the file %f?
+- for each %line: a line in %f
+    - print %line
+    - repeat 2 times, if it is late
+      - leave
+- while the door is open, knock.";
+
+  let expected: &str = "\
+for each %x in the list, print %x.
+repeat 2 times, while it rains, wait.
+
+howto print the file %f?
+- for each %line: a line in %f
+  - print %line
+  - repeat 2 times
+    - if it is late, leave
+- while the door is open, knock
+";
+
+  assert_eq!(assert_round_trip(source), expected);
+}
+
+#[test]
+fn test_format_bindings() {
+  let source: &str = "\
+read a line|from the console   and call it name.
+print %name.
+howto greet?
+- ask for a name and call it who
+      - politely
+- if %who is empty,ask again and call it who
+- say %who.";
+
+  let expected: &str = "\
+read a line
+| from the console and call it name.
+print %name.
+
+howto greet?
+- ask for a name and call it who
+  - politely
+- if %who is empty, ask again and call it who
+- say %who
+";
+
+  assert_eq!(assert_round_trip(source), expected);
+}
+
+#[test]
+fn test_format_imports() {
+  let source: &str = "use 'console'.include   './greet'.\nprint 'hi'.\nuse '''it's'''.";
+
+  let expected: &str = "\
+use 'console'.
+include './greet'.
+
+print 'hi'.
+
+use '''it's'''.
+";
+
+  assert_eq!(assert_round_trip(source), expected);
+}
+
+#[test]
+fn test_format_modules() {
+  let source: &str = "module   console.private howto print %s?\n- within io,write %s\n- if %s is empty , within io, flush.\nwithin console,print 'hi'.";
+
+  let expected: &str = "\
+module console.
+
+private howto print %s?
+- within io, write %s
+- if %s is empty, within io, flush
+
+within console, print 'hi'.
+";
+
+  assert_eq!(assert_round_trip(source), expected);
+}
+
+#[test]
+fn test_format_queries() {
+  let source: &str = "?whatis stdout.\n\n\n?  howto print   %n: a number?\nprint 42.";
+
+  let expected: &str = "\
+? whatis stdout
+? howto print %n: a number
+
+print 42.
+";
+
+  assert_eq!(assert_round_trip(source), expected);
+}
+
+#[test]
+fn test_format_primitive() {
+  let source: &str = "howto exit?\n-*\n\n< run the 'exit' system call |with code 0\n>\n\n-*.";
+
+  let expected: &str = "\
+howto exit?
+-*
+< run the 'exit' system call
+| with code 0
+>
+-*
+";
+
+  assert_eq!(assert_round_trip(source), expected);
+}
+
+#[test]
+fn test_format_low_level() {
+  let source: &str = "howto add?\n-$$ fn add(a: u32, b: u32) -> u32 { a + b } $$\n- return it.";
+
+  let expected: &str = "\
+howto add?
+- $$ fn add(a: u32, b: u32) -> u32 { a + b } $$
+- return it
+";
+
+  assert_eq!(assert_round_trip(source), expected);
+}
+
+#[test]
+fn test_format_comments() {
+  let source: &str = "// header\nsay hi. // greet\n\n\nwhatis a word?\n- text\n  // more\n- letters\n\nhowto go?\n- if late, run\n// c\n- otherwise if tired, leave\n  // d\n- otherwise // e\n  - stay\n\nif late, run.\n// f\notherwise, walk.\n// end";
+
+  let expected: &str = "\
+// header
+say hi. // greet
+
+whatis a word?
+- text
+  // more
+- letters
+
+howto go?
+- if late, run
+// c
+- otherwise if tired, leave
+  // d
+- otherwise // e
+  - stay
+
+if late, run.
+// f
+otherwise, walk.
+
+// end
+";
+
+  assert_eq!(assert_round_trip(source), expected);
+}
+
+#[test]
+fn test_format_trailing_comments() {
+  let source: &str = "say hi |loud. /* loudly */ // greet\nhowto greet?   // greeting\n- say hi // first\n  | to them\n- if late, go /* late */\n-*\n< say bye // step\n>\n-*";
+
+  let expected: &str = "\
+say hi
+| loud. /* loudly */ // greet
+
+howto greet? // greeting
+- say hi // first
+| to them
+- if late, go /* late */
+-*
+< say bye // step
+>
+-*
+";
+
+  assert_eq!(assert_round_trip(source), expected);
+}
+
+#[test]
+fn test_format_nested_comments() {
+  let source: &str = "howto greet?\n// first\n- say hi\n    // under hi\n    - wave\n      // under wave\n      - smile\n    // after wave\n// last\n- if late\n    // in branch\n  - run";
+
+  let expected: &str = "\
+howto greet?
+// first
+- say hi
+  // under hi
+  - wave
+    // under wave
+    - smile
+  // after wave
+// last
+- if late
+  // in branch
+  - run
+";
+
+  assert_eq!(assert_round_trip(source), expected);
+}
+
+#[test]
+fn test_format_literals() {
+  let source: &str = "say 'it\\'s' '\\x41\\tb' 0x2a b'a' '''\nline one\nline two\n'''.";
+
+  let expected: &str = "say 'it\\'s' '\\x41\\tb' 0x2a b'a' '''\nline one\nline two\n'''.\n";
+
+  assert_eq!(assert_round_trip(source), expected);
+}
+
+#[test]
+fn test_format_errors() {
+  let errors = format("say & hi.\n\nhowto %?").unwrap_err();
+  assert_eq!(errors.len(), 2);
+}
@@ -19,19 +19,20 @@ Circe. If not, see <https://www.gnu.org/licenses/>.
 */
 
 
-use cce_ast::{Lexer, Token};
+use cce_ast::{Lexer, Token, SpannedToken, Trivia, LexerError};
+use cce_stream::{Span, FileId, InputStream};
 
 #[test]
 fn test_lexer_basic() {
   let mut lexer = Lexer::from("howto hello world");
 
-  let next_token = lexer.next().unwrap().unwrap();
+  let next_token = lexer.next().unwrap().unwrap().token;
   assert_eq!(next_token, Token::Keyword("howto".to_string()));
 
-  let next_token = lexer.next().unwrap().unwrap();
+  let next_token = lexer.next().unwrap().unwrap().token;
   assert_eq!(next_token, Token::Identifier("hello".to_string()));
 
-  let next_token = lexer.next().unwrap().unwrap();
+  let next_token = lexer.next().unwrap().unwrap().token;
   assert_eq!(next_token, Token::Identifier("world".to_string()));
 }
 
@@ -39,10 +40,10 @@ fn test_lexer_basic() {
 fn test_lexer_string() {
   let mut lexer = Lexer::from("howto 'hello world'");
 
-  let next_token = lexer.next().unwrap().unwrap();
+  let next_token = lexer.next().unwrap().unwrap().token;
   assert_eq!(next_token, Token::Keyword("howto".to_string()));
 
-  let next_token = lexer.next().unwrap().unwrap();
+  let next_token = lexer.next().unwrap().unwrap().token;
   assert_eq!(next_token, Token::Literal("hello world".to_string()));
 }
 
@@ -50,25 +51,25 @@ fn test_lexer_string() {
 fn test_lexer_punct() {
   let mut lexer = Lexer::from("howto hello world\n- do it");
 
-  let next_token = lexer.next().unwrap().unwrap();
+  let next_token = lexer.next().unwrap().unwrap().token;
   assert_eq!(next_token, Token::Keyword("howto".to_string()));
 
-  let next_token = lexer.next().unwrap().unwrap();
+  let next_token = lexer.next().unwrap().unwrap().token;
   assert_eq!(next_token, Token::Identifier("hello".to_string()));
 
-  let next_token = lexer.next().unwrap().unwrap();
+  let next_token = lexer.next().unwrap().unwrap().token;
   assert_eq!(next_token, Token::Identifier("world".to_string()));
 
-  let next_token = lexer.next().unwrap().unwrap();
+  let next_token = lexer.next().unwrap().unwrap().token;
   assert_eq!(next_token, Token::Newline);
 
-  let next_token = lexer.next().unwrap().unwrap();
+  let next_token = lexer.next().unwrap().unwrap().token;
   assert_eq!(next_token, Token::Punctuation('-'));
 
-  let next_token = lexer.next().unwrap().unwrap();
+  let next_token = lexer.next().unwrap().unwrap().token;
   assert_eq!(next_token, Token::Identifier("do".to_string()));
 
-  let next_token = lexer.next().unwrap().unwrap();
+  let next_token = lexer.next().unwrap().unwrap().token;
   assert_eq!(next_token, Token::Identifier("it".to_string()));
 }
 
@@ -76,19 +77,19 @@ fn test_lexer_punct() {
 fn test_lexer_peek() {
   let mut lexer = Lexer::from("howto hello world");
 
-  let next_token = lexer.peek().unwrap().unwrap();
+  let next_token = lexer.peek().unwrap().unwrap().token;
   assert_eq!(next_token, Token::Keyword("howto".to_string()));
 
-  let next_token = lexer.next().unwrap().unwrap();
+  let next_token = lexer.next().unwrap().unwrap().token;
   assert_eq!(next_token, Token::Keyword("howto".to_string()));
 
-  let next_token = lexer.peek().unwrap().unwrap();
+  let next_token = lexer.peek().unwrap().unwrap().token;
   assert_eq!(next_token, Token::Identifier("hello".to_string()));
 
-  let next_token = lexer.next().unwrap().unwrap();
+  let next_token = lexer.next().unwrap().unwrap().token;
   assert_eq!(next_token, Token::Identifier("hello".to_string()));
 
-  let next_token = lexer.next().unwrap().unwrap();
+  let next_token = lexer.next().unwrap().unwrap().token;
   assert_eq!(next_token, Token::Identifier("world".to_string()));
 }
 
@@ -96,13 +97,13 @@ fn test_lexer_peek() {
 fn test_lexer_lowlevel() {
   let mut lexer = Lexer::from("hello -$$ struct Foo { bar: u32 } $$.");
 
-  let next_token = lexer.next().unwrap().unwrap();
+  let next_token = lexer.next().unwrap().unwrap().token;
   assert_eq!(next_token, Token::Identifier("hello".to_string()));
 
-  let next_token = lexer.next().unwrap().unwrap();
+  let next_token = lexer.next().unwrap().unwrap().token;
   assert_eq!(next_token, Token::Punctuation('-'));
 
-  let next_token = lexer.next().unwrap().unwrap();
+  let next_token = lexer.next().unwrap().unwrap().token;
   assert_eq!(next_token, Token::LowLevelSequence(" struct Foo { bar: u32 } ".to_string()));
 }
 
@@ -111,7 +112,7 @@ fn test_lexer_lowlevel() {
 fn test_lexer_open_string() {
   let mut lexer = Lexer::from("howto 'hello world");
 
-  let next_token = lexer.next().unwrap().unwrap();
+  let next_token = lexer.next().unwrap().unwrap().token;
   assert_eq!(next_token, Token::Keyword("howto".to_string()));
   
   lexer.next().unwrap().unwrap();
@@ -121,9 +122,429 @@ fn test_lexer_open_string() {
 fn test_lexer_slot() {
   let mut lexer = Lexer::from("%hello");
 
-  let next_token = lexer.next().unwrap().unwrap();
+  let next_token = lexer.next().unwrap().unwrap().token;
   assert_eq!(next_token, Token::Percent);
 
-  let next_token = lexer.next().unwrap().unwrap();
+  let next_token = lexer.next().unwrap().unwrap().token;
   assert_eq!(next_token, Token::Identifier("hello".to_string()));
 }
+
+#[test]
+fn test_lexer_spans() {
+  let mut lexer = Lexer::from("howto  'hi' %x\n- it.");
+
+  let expected = vec![
+    (Token::Keyword("howto".to_string()), 0, 5, Some("")),
+    (Token::Literal("hi".to_string()), 7, 11, None),
+    (Token::Percent, 12, 13, None),
+    (Token::Identifier("x".to_string()), 13, 14, None),
+    (Token::Newline, 14, 15, None),
+    (Token::Punctuation('-'), 15, 16, Some("")),
+    (Token::Identifier("it".to_string()), 17, 19, None),
+    (Token::Dot, 19, 20, None)
+  ];
+
+  for (token, start, end, indent) in expected {
+    let next_token = lexer.next().unwrap().unwrap();
+    assert_eq!(next_token, SpannedToken {
+      token,
+      span: Span::new(FileId(0), start, end),
+      trivia: vec![],
+      indent: indent.map(str::to_string)
+    });
+  }
+
+  assert!(lexer.next().is_none());
+}
+
+#[test]
+fn test_lexer_unexpected_character() {
+  let mut lexer = Lexer::from("say & hi");
+
+  lexer.next().unwrap().unwrap();
+
+  match lexer.next_token() {
+    Err(LexerError::UnexpectedCharacter(c, span)) => {
+      assert_eq!(c, '&');
+      assert_eq!(span, Span::new(FileId(0), 4, 5));
+    },
+    other => panic!("Expected an unexpected character error, got {:?}", other)
+  }
+}
+
+#[test]
+fn test_lexer_numbers() {
+  let mut lexer = Lexer::from("42 1_000 0x1F 0o17 0b1010 b'a'");
+
+  let expected = vec![
+    (Token::Number(42), 0, 2),
+    (Token::Number(1000), 3, 8),
+    (Token::Number(31), 9, 13),
+    (Token::Number(15), 14, 18),
+    (Token::Number(10), 19, 25),
+    (Token::Number(97), 26, 30)
+  ];
+
+  for (i, (token, start, end)) in expected.into_iter().enumerate() {
+    let next_token = lexer.next().unwrap().unwrap();
+    assert_eq!(next_token, SpannedToken {
+      token,
+      span: Span::new(FileId(0), start, end),
+      trivia: vec![],
+      indent: if i == 0 { Some(String::new()) } else { None }
+    });
+  }
+
+  assert!(lexer.next().is_none());
+}
+
+#[test]
+fn test_lexer_number_invalid_digit() {
+  let mut lexer = Lexer::from("0b102 next");
+
+  match lexer.next_token() {
+    Err(LexerError::InvalidDigit(c, radix, span)) => {
+      assert_eq!(c, '2');
+      assert_eq!(radix, 2);
+      assert_eq!(span, Span::new(FileId(0), 4, 5));
+    },
+    other => panic!("Expected an invalid digit error, got {:?}", other)
+  }
+
+  let next_token = lexer.next().unwrap().unwrap().token;
+  assert_eq!(next_token, Token::Identifier("next".to_string()));
+}
+
+#[test]
+fn test_lexer_number_missing_digits() {
+  let mut lexer = Lexer::from("0x_");
+
+  match lexer.next_token() {
+    Err(LexerError::MissingDigits(span)) => {
+      assert_eq!(span, Span::new(FileId(0), 0, 3));
+    },
+    other => panic!("Expected a missing digits error, got {:?}", other)
+  }
+}
+
+#[test]
+fn test_lexer_number_overflow() {
+  let mut lexer = Lexer::from("18446744073709551615 18446744073709551616");
+
+  let next_token = lexer.next().unwrap().unwrap().token;
+  assert_eq!(next_token, Token::Number(u64::MAX));
+
+  match lexer.next_token() {
+    Err(LexerError::NumberOverflow(span)) => {
+      assert_eq!(span, Span::new(FileId(0), 21, 41));
+    },
+    other => panic!("Expected a number overflow error, got {:?}", other)
+  }
+}
+
+#[test]
+fn test_lexer_invalid_byte_literal() {
+  let mut lexer = Lexer::from("b'ab'");
+
+  match lexer.next_token() {
+    Err(LexerError::InvalidByteLiteral(span)) => {
+      assert_eq!(span, Span::new(FileId(0), 0, 5));
+    },
+    other => panic!("Expected an invalid byte literal error, got {:?}", other)
+  }
+}
+
+#[test]
+fn test_lexer_string_escapes() {
+  let mut lexer = Lexer::from(r"'it\'s \\ a\tb\n \x41 \u{1F600}'");
+
+  let next_token = lexer.next().unwrap().unwrap().token;
+  assert_eq!(next_token, Token::Literal("it's \\ a\tb\n A \u{1F600}".to_string()));
+
+  assert!(lexer.next().is_none());
+}
+
+#[test]
+fn test_lexer_string_invalid_escape() {
+  let mut lexer = Lexer::from(r"'a\qb' next");
+
+  match lexer.next_token() {
+    Err(LexerError::InvalidEscape(c, span)) => {
+      assert_eq!(c, 'q');
+      assert_eq!(span, Span::new(FileId(0), 2, 4));
+    },
+    other => panic!("Expected an invalid escape error, got {:?}", other)
+  }
+
+  let next_token = lexer.next().unwrap().unwrap().token;
+  assert_eq!(next_token, Token::Identifier("next".to_string()));
+}
+
+#[test]
+fn test_lexer_string_invalid_hex_escape() {
+  let mut lexer = Lexer::from(r"'\x8f' '\xg'");
+
+  match lexer.next_token() {
+    Err(LexerError::InvalidHexEscape(span)) => {
+      assert_eq!(span, Span::new(FileId(0), 1, 5));
+    },
+    other => panic!("Expected an invalid hex escape error, got {:?}", other)
+  }
+
+  match lexer.next_token() {
+    Err(LexerError::InvalidHexEscape(span)) => {
+      assert_eq!(span, Span::new(FileId(0), 8, 10));
+    },
+    other => panic!("Expected an invalid hex escape error, got {:?}", other)
+  }
+}
+
+#[test]
+fn test_lexer_string_invalid_unicode_escape() {
+  let mut lexer = Lexer::from(r"'\u{110000}' '\u41' '\u{}'");
+
+  let expected = vec![(1, 11), (14, 16), (21, 25)];
+
+  for (start, end) in expected {
+    match lexer.next_token() {
+      Err(LexerError::InvalidUnicodeEscape(span)) => {
+        assert_eq!(span, Span::new(FileId(0), start, end));
+      },
+      other => panic!("Expected an invalid unicode escape error, got {:?}", other)
+    }
+  }
+
+  assert!(lexer.next().is_none());
+}
+
+#[test]
+fn test_lexer_raw_string() {
+  let mut lexer = Lexer::from("print '''\nline one\nit's \\n raw\n''' now");
+
+  lexer.next().unwrap().unwrap();
+
+  let next_token = lexer.next().unwrap().unwrap();
+  assert_eq!(next_token, SpannedToken {
+    token: Token::Literal("line one\nit's \\n raw\n".to_string()),
+    span: Span::new(FileId(0), 6, 34),
+    trivia: vec![],
+    indent: None
+  });
+
+  let next_token = lexer.next().unwrap().unwrap().token;
+  assert_eq!(next_token, Token::Identifier("now".to_string()));
+}
+
+#[test]
+fn test_lexer_raw_string_unterminated() {
+  let mut lexer = Lexer::from("'''never closed''");
+
+  match lexer.next_token() {
+    Err(LexerError::UnexpectedEndOfStream(span)) => {
+      assert_eq!(span, Span::new(FileId(0), 0, 17));
+    },
+    other => panic!("Expected an unexpected end of stream error, got {:?}", other)
+  }
+}
+
+#[test]
+fn test_lexer_comments() {
+  let mut lexer = Lexer::from("// header\nsay hi // greet\n/* a\nblock */ bye");
+
+  let next_token = lexer.next().unwrap().unwrap();
+  assert_eq!(next_token, SpannedToken {
+    token: Token::Identifier("say".to_string()),
+    span: Span::new(FileId(0), 10, 13),
+    trivia: vec![
+      Trivia::LineComment("// header".to_string(), Span::new(FileId(0), 0, 9))
+    ],
+    indent: Some(String::new())
+  });
+
+  lexer.next().unwrap().unwrap();
+
+  let next_token = lexer.next().unwrap().unwrap();
+  assert_eq!(next_token, SpannedToken {
+    token: Token::Newline,
+    span: Span::new(FileId(0), 25, 26),
+    trivia: vec![
+      Trivia::LineComment("// greet".to_string(), Span::new(FileId(0), 17, 25))
+    ],
+    indent: None
+  });
+
+  let next_token = lexer.next().unwrap().unwrap();
+  assert_eq!(next_token, SpannedToken {
+    token: Token::Identifier("bye".to_string()),
+    span: Span::new(FileId(0), 40, 43),
+    trivia: vec![
+      Trivia::BlockComment("/* a\nblock */".to_string(), Span::new(FileId(0), 26, 39))
+    ],
+    indent: None
+  });
+
+  assert!(lexer.next().is_none());
+}
+
+#[test]
+fn test_lexer_comment_line_keeps_blank_line() {
+  let mut lexer = Lexer::from("a\n// note\n\nb // end");
+
+  let expected = vec![
+    Token::Identifier("a".to_string()),
+    Token::Newline,
+    Token::Newline,
+    Token::Identifier("b".to_string())
+  ];
+
+  for token in expected {
+    assert_eq!(lexer.next().unwrap().unwrap().token, token);
+  }
+
+  assert!(lexer.next().is_none());
+  assert_eq!(lexer.trailing_trivia(), &[
+    Trivia::LineComment("// end".to_string(), Span::new(FileId(0), 13, 19))
+  ]);
+}
+
+#[test]
+fn test_lexer_unterminated_block_comment() {
+  let mut lexer = Lexer::from("say /*/ hi");
+
+  lexer.next().unwrap().unwrap();
+
+  match lexer.next_token() {
+    Err(LexerError::UnexpectedEndOfStream(span)) => {
+      assert_eq!(span, Span::new(FileId(0), 4, 10));
+    },
+    other => panic!("Expected an unexpected end of stream error, got {:?}", other)
+  }
+}
+
+#[test]
+fn test_lexer_reader() {
+  let reader = std::io::BufReader::with_capacity(3, "say 'héllo'.".as_bytes());
+  let mut lexer = Lexer::new(InputStream::from_reader(reader));
+
+  let expected = vec![
+    Token::Identifier("say".to_string()),
+    Token::Literal("héllo".to_string()),
+    Token::Dot
+  ];
+
+  for token in expected {
+    assert_eq!(lexer.next().unwrap().unwrap().token, token);
+  }
+
+  assert!(lexer.next().is_none());
+}
+
+#[test]
+fn test_lexer_stream_error() {
+  let mut lexer = Lexer::new(InputStream::from_reader(&b"say 'hi\xff there'"[..]));
+
+  let next_token = lexer.next().unwrap().unwrap().token;
+  assert_eq!(next_token, Token::Identifier("say".to_string()));
+
+  match lexer.next_token() {
+    Err(LexerError::InputStreamError(_)) => {},
+    other => panic!("Expected an input stream error, got {:?}", other)
+  }
+
+  assert!(lexer.next().is_none());
+}
+
+#[test]
+fn test_lexer_statement_words() {
+  let lexer = Lexer::from("repeat 3 times, say it if it fails
+- otherwise
+private whatis a name");
+
+  let words: Vec<Token> = lexer.map(|token| token.unwrap().token)
+    .filter(|token| matches!(token, Token::Identifier(_) | Token::Keyword(_)))
+    .collect();
+
+  let identifier = |word: &str| Token::Identifier(word.to_string());
+
+  assert_eq!(words, vec![
+    identifier("repeat"),
+    identifier("times"),
+    identifier("say"),
+    identifier("it"),
+    identifier("if"),
+    identifier("it"),
+    identifier("fails"),
+    identifier("otherwise"),
+    identifier("private"),
+    Token::Keyword("whatis".to_string()),
+    identifier("a"),
+    identifier("name")
+  ]);
+}
+
+#[test]
+fn test_lexer_byte_spans() {
+  let mut lexer = Lexer::from("say 'héllo' ✓ now");
+
+  lexer.next().unwrap().unwrap();
+
+  let next_token = lexer.next().unwrap().unwrap();
+  assert_eq!(next_token.span, Span::new(FileId(0), 4, 12));
+
+  match lexer.next_token() {
+    Err(LexerError::UnexpectedCharacter(c, span)) => {
+      assert_eq!(c, '✓');
+      assert_eq!(span, Span::new(FileId(0), 13, 16));
+    },
+    other => panic!("Expected an unexpected character error, got {:?}", other)
+  }
+
+  let next_token = lexer.next().unwrap().unwrap();
+  assert_eq!(next_token.span, Span::new(FileId(0), 17, 20));
+}
+
+#[test]
+fn test_lexer_iterator() {
+  let tokens: Vec<Token> = Lexer::from("say 'hi' to 3 people.")
+    .map(|token| token.unwrap().token)
+    .collect();
+
+  assert_eq!(tokens, vec![
+    Token::Identifier("say".to_string()),
+    Token::Literal("hi".to_string()),
+    Token::Identifier("to".to_string()),
+    Token::Number(3),
+    Token::Identifier("people".to_string()),
+    Token::Dot
+  ]);
+}
+
+#[test]
+fn test_lexer_iterator_fused() {
+  let mut lexer = Lexer::from("say & hi");
+
+  assert!(matches!(lexer.next(), Some(Ok(_))));
+  assert!(matches!(lexer.next(), Some(Err(LexerError::UnexpectedCharacter('&', _)))));
+  assert!(lexer.next().is_none());
+  assert!(lexer.next().is_none());
+}
+
+#[test]
+fn test_lexer_indent() {
+  let mut lexer = Lexer::from("- a\n  - b\n\t- c\n  // note\n    - d");
+
+  let mut indents: Vec<(Token, String)> = Vec::new();
+  for tok in lexer.by_ref() {
+    let tok = tok.unwrap();
+    if let Some(indent) = tok.indent {
+      indents.push((tok.token, indent));
+    }
+  }
+
+  assert_eq!(indents, vec![
+    (Token::Punctuation('-'), "".to_string()),
+    (Token::Punctuation('-'), "  ".to_string()),
+    (Token::Punctuation('-'), "\t".to_string()),
+    (Token::Punctuation('-'), "    ".to_string())
+  ]);
+}
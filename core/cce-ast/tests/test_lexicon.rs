@@ -0,0 +1,118 @@
+/*
+
+Copyright (C) 2023 Carlos Kieliszewski
+
+This file is part of the Circe Project.
+
+Circe is free software: you can redistribute it and/or modify it under
+the terms of the GNU General Public License as published by the Free
+Software Foundation, either version 3 of the License, or (at your option)
+any later version.
+
+Circe is distributed in the hope that it will be useful, but WITHOUT ANY
+WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+FOR A PARTICULAR PURPOSE. See the GNU General Public License for more details.
+
+You should have received a copy of the GNU General Public License along with
+Circe. If not, see <https://www.gnu.org/licenses/>. 
+
+*/
+
+
+use cce_ast::*;
+use cce_stream::{Span, FileId};
+
+fn span(start: usize, end: usize) -> Span {
+  Span::new(FileId(0), start, end)
+}
+
+#[test]
+fn test_lexicon_default() {
+  let lexicon: Lexicon = Lexicon::default();
+
+  assert_eq!(lexicon.classify("a"), WordClass::Article);
+  assert_eq!(lexicon.classify("The"), WordClass::Article);
+  assert_eq!(lexicon.classify("to"), WordClass::Preposition);
+  assert_eq!(lexicon.classify("it"), WordClass::Pronoun);
+  assert_eq!(lexicon.classify("string"), WordClass::Content);
+
+  let mut articles: Vec<&str> = lexicon.words(WordClass::Article).collect();
+  articles.sort();
+  assert_eq!(articles, vec!["a", "an", "the"]);
+}
+
+#[test]
+fn test_lexicon_configure() {
+  let mut lexicon: Lexicon = Lexicon::new();
+  assert_eq!(lexicon.classify("the"), WordClass::Content);
+
+  lexicon.insert("Le", WordClass::Article);
+  lexicon.insert("un", WordClass::Article);
+  lexicon.insert("vers", WordClass::Preposition);
+  assert_eq!(lexicon.classify("le"), WordClass::Article);
+  assert_eq!(lexicon.classify("vers"), WordClass::Preposition);
+
+  lexicon.insert("vers", WordClass::Pronoun);
+  assert_eq!(lexicon.classify("vers"), WordClass::Pronoun);
+
+  lexicon.remove("UN");
+  assert_eq!(lexicon.classify("un"), WordClass::Content);
+}
+
+#[test]
+fn test_lexicon_word_class() {
+  let lexicon: Lexicon = Lexicon::default();
+
+  let components: Vec<CommandComponent> = vec![
+    CommandComponent::Keyword("print".to_string(), span(0, 5)),
+    CommandComponent::Keyword("the".to_string(), span(6, 9)),
+    CommandComponent::Literal("the".to_string(), span(10, 15)),
+    CommandComponent::Keyword("to".to_string(), span(16, 18)),
+    CommandComponent::Keyword("it".to_string(), span(19, 21)),
+    CommandComponent::Number(1, span(22, 23))
+  ];
+
+  let classes: Vec<Option<WordClass>> = components.iter().map(|component| component.word_class(&lexicon)).collect();
+  assert_eq!(classes, vec![
+    Some(WordClass::Content),
+    Some(WordClass::Article),
+    None,
+    Some(WordClass::Preposition),
+    Some(WordClass::Pronoun),
+    None
+  ]);
+}
+
+#[test]
+fn test_lexicon_slot_kind_articles() {
+  let mut lexicon: Lexicon = Lexicon::new();
+  lexicon.insert("un", WordClass::Article);
+
+  let mut parser = Parser::with_lexicon(Lexer::from("montre %n: un nombre."), lexicon);
+
+  let command: Command = match parser.next().unwrap().unwrap() {
+    ParseNode::Command(command) => command,
+    other => panic!("Expected a command, got {:?}", other)
+  };
+
+  assert_eq!(command.components[1], CommandComponent::Slot(Slot {
+    name: "n".to_string(),
+    kind: Some(vec![
+      CommandComponent::Keyword("un".to_string(), span(11, 13)),
+      CommandComponent::Keyword("nombre".to_string(), span(14, 20))
+    ])
+  }, span(7, 20)));
+
+  // Without `a` on the list, it is taken as the kind itself.
+  let mut parser = Parser::with_lexicon(Lexer::from("show %n: a number."), Lexicon::new());
+
+  let command: Command = match parser.next().unwrap().unwrap() {
+    ParseNode::Command(command) => command,
+    other => panic!("Expected a command, got {:?}", other)
+  };
+
+  assert_eq!(command.components[1], CommandComponent::Slot(Slot {
+    name: "n".to_string(),
+    kind: Some(vec![CommandComponent::Keyword("a".to_string(), span(9, 10))])
+  }, span(5, 10)));
+}
@@ -0,0 +1,200 @@
+/*
+
+Copyright (C) 2023 Carlos Kieliszewski
+
+This file is part of the Circe Project.
+
+Circe is free software: you can redistribute it and/or modify it under
+the terms of the GNU General Public License as published by the Free
+Software Foundation, either version 3 of the License, or (at your option)
+any later version.
+
+Circe is distributed in the hope that it will be useful, but WITHOUT ANY
+WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+FOR A PARTICULAR PURPOSE. See the GNU General Public License for more details.
+
+You should have received a copy of the GNU General Public License along with
+Circe. If not, see <https://www.gnu.org/licenses/>. 
+
+*/
+
+
+use cce_ast::*;
+use cce_stream::{FileId, SourceMap, Span};
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Writes `files` to a fresh directory for one test.
+fn setup(test: &str, files: &[(&str, &str)]) -> PathBuf {
+  let root: PathBuf = std::env::temp_dir().join(format!("cce-loader-{}-{}", test, std::process::id()));
+  let _ = fs::remove_dir_all(&root);
+
+  for (path, source) in files.iter() {
+    let path: PathBuf = root.join(path);
+    fs::create_dir_all(path.parent().unwrap()).unwrap();
+    fs::write(path, source).unwrap();
+  }
+
+  root
+}
+
+fn load(loader: &Loader, main: &Path) -> (SourceMap, LoadedProgram, Vec<LoadError>) {
+  let mut sources = SourceMap::new();
+  let main: FileId = sources.add_file(main).unwrap();
+
+  let (program, errors) = loader.load(&mut sources, main);
+  (sources, program, errors)
+}
+
+/// The first word of each node, to tell them apart.
+fn first_words(program: &LoadedProgram) -> Vec<String> {
+  program.nodes.iter().map(|node| {
+    let components: &[CommandComponent] = match node {
+      ParseNode::Command(command) => &command.components,
+      ParseNode::HowToStatement(howto) => &howto.signature,
+      ParseNode::WhatIsStatement(whatis) => &whatis.signature,
+      other => panic!("Unexpected node {:?}", other)
+    };
+
+    match &components[0] {
+      CommandComponent::Keyword(word, _) => word.clone(),
+      other => panic!("Unexpected component {:?}", other)
+    }
+  }).collect()
+}
+
+#[test]
+fn test_loader_use_and_include() {
+  let root: PathBuf = setup("merge", &[
+    ("app/main.cce", "use 'console'.\ninclude './greet'.\nprint 'hi'.\n"),
+    ("app/greet.cce", "howto greet?\n- say hello.\n\nsay loaded.\n"),
+    ("lib/console.cce", "howto print %s?\n- write %s.\n\nwhatis stdout?\n- the output.\n\nflush the console.\n")
+  ]);
+
+  let loader: Loader = Loader::new().search(root.join("lib"));
+  let (sources, program, errors) = load(&loader, &root.join("app/main.cce"));
+
+  assert!(errors.is_empty(), "{:?}", errors);
+
+  // `use` leaves out the commands of the file, and `include` keeps them.
+  assert_eq!(first_words(&program), vec!["print", "stdout", "greet", "say", "print"]);
+
+  let files: Vec<&str> = program.files.iter().map(|file| sources.name(file.id).unwrap()).collect();
+  assert_eq!(files, vec![
+    root.join("app/main.cce").display().to_string(),
+    root.join("lib/console.cce").display().to_string(),
+    root.join("app/greet.cce").display().to_string()
+  ]);
+
+  assert_eq!(program.nodes[0].span().file, program.files[1].id);
+  assert_eq!(program.import_chain(program.files[2].id), vec![Span::new(program.files[0].id, 15, 32)]);
+  assert!(program.import_chain(program.files[0].id).is_empty());
+}
+
+#[test]
+fn test_loader_use_keeps_module() {
+  let root: PathBuf = setup("module", &[
+    ("main.cce", "use 'io'.\nwithin io, print 'hi' to the console.\n"),
+    ("io.cce", "module io.\n\nhowto print %s to the console?\n- write %s.\n\nsay loaded.\n")
+  ]);
+
+  let (_, program, errors) = load(&Loader::new(), &root.join("main.cce"));
+
+  assert!(errors.is_empty(), "{:?}", errors);
+  assert_eq!(program.nodes.len(), 3);
+  assert!(matches!(&program.nodes[0], ParseNode::Module(module) if module.name == "io" && module.span.file == program.files[1].id));
+  assert!(matches!(&program.nodes[1], ParseNode::HowToStatement(_)));
+  assert!(matches!(&program.nodes[2], ParseNode::Qualified(qualified) if qualified.module == "io"));
+}
+
+#[test]
+fn test_loader_use_then_include() {
+  let root: PathBuf = setup("use-include", &[
+    ("main.cce", "use 'shared'.\nsay main.\ninclude 'shared'.\nuse 'shared'.\ninclude 'shared'.\n"),
+    ("shared.cce", "whatis shared?\n- something.\n\nsay shared.\n")
+  ]);
+
+  let (_, program, errors) = load(&Loader::new(), &root.join("main.cce"));
+
+  // The include brings in what the use left out, and only once.
+  assert!(errors.is_empty(), "{:?}", errors);
+  assert_eq!(first_words(&program), vec!["shared", "say", "say"]);
+  assert_eq!(program.nodes[2].span().file, program.files[1].id);
+  assert_eq!(program.files.len(), 2);
+}
+
+#[test]
+fn test_loader_loads_once() {
+  let root: PathBuf = setup("once", &[
+    ("main.cce", "include 'a'.\ninclude 'b'.\n"),
+    ("a.cce", "use 'shared'.\nsay a.\n"),
+    ("b.cce", "use 'shared'.\nsay b.\n"),
+    ("shared.cce", "whatis shared?\n- something.\n")
+  ]);
+
+  let (_, program, errors) = load(&Loader::new(), &root.join("main.cce"));
+
+  assert!(errors.is_empty(), "{:?}", errors);
+  assert_eq!(first_words(&program), vec!["shared", "say", "say"]);
+  assert_eq!(program.files.len(), 4);
+}
+
+#[test]
+fn test_loader_error_not_found() {
+  let root: PathBuf = setup("missing", &[
+    ("main.cce", "use 'nowhere'.\nuse './nearby.cce'.\nsay hi.\n")
+  ]);
+
+  let loader: Loader = Loader::new().search(root.join("lib"));
+  let (_, program, errors) = load(&loader, &root.join("main.cce"));
+
+  assert_eq!(first_words(&program), vec!["say"]);
+
+  match errors.as_slice() {
+    [LoadError::NotFound { path: first, searched: first_searched, .. }, LoadError::NotFound { path: second, searched: second_searched, .. }] => {
+      assert_eq!(first, "nowhere");
+      assert_eq!(first_searched, &vec![root.join("nowhere.cce"), root.join("lib/nowhere.cce")]);
+      assert_eq!(second, "./nearby.cce");
+      assert_eq!(second_searched, &vec![root.join("nearby.cce")]);
+    },
+    other => panic!("Expected two missing files, got {:?}", other)
+  }
+}
+
+#[test]
+fn test_loader_error_cycle() {
+  let root: PathBuf = setup("cycle", &[
+    ("main.cce", "use 'a'.\n"),
+    ("a.cce", "use 'b'.\nwhatis a?\n- first.\n"),
+    ("b.cce", "use './a.cce'.\nwhatis b?\n- second.\n")
+  ]);
+
+  let (_, program, errors) = load(&Loader::new(), &root.join("main.cce"));
+
+  assert_eq!(first_words(&program), vec!["b", "a"]);
+
+  match errors.as_slice() {
+    [LoadError::Cycle { path, span, chain }] => {
+      assert_eq!(path, "./a.cce");
+      assert_eq!(span.start, 0);
+      assert_eq!(chain.iter().map(|(path, _)| path.as_str()).collect::<Vec<_>>(), vec!["b"]);
+    },
+    other => panic!("Expected a cycle, got {:?}", other)
+  }
+}
+
+#[test]
+fn test_loader_parse_errors() {
+  let root: PathBuf = setup("errors", &[
+    ("main.cce", "include 'broken'.\nsay hi.\n"),
+    ("broken.cce", "howto?\n\nsay bye.\n")
+  ]);
+
+  let (_, program, errors) = load(&Loader::new(), &root.join("main.cce"));
+
+  // The error is reported in the file it is in, and the rest of the
+  // program is still loaded.
+  assert!(matches!(errors.as_slice(), [err @ LoadError::Parse(_)] if err.span().unwrap().file == program.files[1].id));
+  assert_eq!(program.nodes.last().unwrap().span().file, program.files[0].id);
+}
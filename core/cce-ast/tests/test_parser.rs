@@ -20,6 +20,21 @@ Circe. If not, see <https://www.gnu.org/licenses/>.
 
 
 use cce_ast::*;
+use cce_stream::{Span, FileId};
+
+fn span(start: usize, end: usize) -> Span {
+  Span::new(FileId(0), start, end)
+}
+
+/// The words of a command made of nothing but words.
+fn words(components: &[CommandComponent]) -> String {
+  let words: Vec<&str> = components.iter().map(|component| match component {
+    CommandComponent::Keyword(word, _) => word.as_str(),
+    component => panic!("expected a word, got {:?}", component)
+  }).collect();
+
+  words.join(" ")
+}
 
 #[test]
 fn test_parser_basic() {
@@ -28,11 +43,13 @@ fn test_parser_basic() {
   let next_node: ParseNode = parser.next().unwrap().unwrap();
   let expected_node: ParseNode = ParseNode::Command(Command {
     components: vec![
-      CommandComponent::Keyword("say".to_string()),
-      CommandComponent::Keyword("hello".to_string()),
-      CommandComponent::Keyword("world".to_string())
+      CommandComponent::Keyword("say".to_string(), span(0, 3)),
+      CommandComponent::Keyword("hello".to_string(), span(4, 9)),
+      CommandComponent::Keyword("world".to_string(), span(10, 15))
     ],
-    modifiers: vec![]
+    modifiers: vec![],
+    children: vec![],
+    span: span(0, 15)
   });
 
   assert_eq!(next_node, expected_node);
@@ -45,10 +62,12 @@ fn test_parser_literal() {
   let next_node: ParseNode = parser.next().unwrap().unwrap();
   let expected_node: ParseNode = ParseNode::Command(Command {
     components: vec![
-      CommandComponent::Keyword("say".to_string()),
-      CommandComponent::Literal("hello world".to_string())
+      CommandComponent::Keyword("say".to_string(), span(0, 3)),
+      CommandComponent::Literal("hello world".to_string(), span(4, 17))
     ],
-    modifiers: vec![]
+    modifiers: vec![],
+    children: vec![],
+    span: span(0, 17)
   });
 
   assert_eq!(next_node, expected_node);
@@ -61,17 +80,19 @@ fn test_parser_modifier() {
   let next_node: ParseNode = parser.next().unwrap().unwrap();
   let expected_node: ParseNode = ParseNode::Command(Command {
     components: vec![
-      CommandComponent::Keyword("say".to_string()),
-      CommandComponent::Keyword("hello".to_string()),
-      CommandComponent::Keyword("world".to_string())
+      CommandComponent::Keyword("say".to_string(), span(0, 3)),
+      CommandComponent::Keyword("hello".to_string(), span(4, 9)),
+      CommandComponent::Keyword("world".to_string(), span(10, 15))
     ],
     modifiers: vec![
       vec![
-        CommandComponent::Keyword("say".to_string()),
-        CommandComponent::Keyword("hello".to_string()),
-        CommandComponent::Keyword("world".to_string())
+        CommandComponent::Keyword("say".to_string(), span(18, 21)),
+        CommandComponent::Keyword("hello".to_string(), span(22, 27)),
+        CommandComponent::Keyword("world".to_string(), span(28, 33))
       ]
-    ]
+    ],
+    children: vec![],
+    span: span(0, 33)
   });
 
   assert_eq!(next_node, expected_node);
@@ -84,22 +105,24 @@ fn test_parser_modifier_multiple() {
   let next_node: ParseNode = parser.next().unwrap().unwrap();
   let expected_node: ParseNode = ParseNode::Command(Command {
     components: vec![
-      CommandComponent::Keyword("say".to_string()),
-      CommandComponent::Keyword("hello".to_string()),
-      CommandComponent::Keyword("world".to_string())
+      CommandComponent::Keyword("say".to_string(), span(0, 3)),
+      CommandComponent::Keyword("hello".to_string(), span(4, 9)),
+      CommandComponent::Keyword("world".to_string(), span(10, 15))
     ],
     modifiers: vec![
       vec![
-        CommandComponent::Keyword("say".to_string()),
-        CommandComponent::Keyword("hello".to_string()),
-        CommandComponent::Keyword("world".to_string())
+        CommandComponent::Keyword("say".to_string(), span(18, 21)),
+        CommandComponent::Keyword("hello".to_string(), span(22, 27)),
+        CommandComponent::Keyword("world".to_string(), span(28, 33))
       ],
       vec![
-        CommandComponent::Keyword("say".to_string()),
-        CommandComponent::Keyword("hello".to_string()),
-        CommandComponent::Keyword("world".to_string())
+        CommandComponent::Keyword("say".to_string(), span(36, 39)),
+        CommandComponent::Keyword("hello".to_string(), span(40, 45)),
+        CommandComponent::Keyword("world".to_string(), span(46, 51))
       ]
-    ]
+    ],
+    children: vec![],
+    span: span(0, 51)
   });
 
   assert_eq!(next_node, expected_node);
@@ -111,28 +134,32 @@ fn test_parser_howto() {
 
   let next_node: ParseNode = parser.next().unwrap().unwrap();
   let expected_node: ParseNode = ParseNode::HowToStatement(HowToStatement {
+    visibility: Visibility::Public,
     signature: vec![
-      CommandComponent::Keyword("say".to_string()),
-      CommandComponent::Keyword("hello".to_string()),
-      CommandComponent::Keyword("world".to_string())
+      CommandComponent::Keyword("say".to_string(), span(6, 9)),
+      CommandComponent::Keyword("hello".to_string(), span(10, 15)),
+      CommandComponent::Keyword("world".to_string(), span(16, 21))
     ],
     body: vec![
       HowToCommand::HighLevel(Command {
         components: vec![
-          CommandComponent::Keyword("say".to_string()),
-          CommandComponent::Keyword("hello".to_string()),
-          CommandComponent::Keyword("world".to_string())
+          CommandComponent::Keyword("say".to_string(), span(25, 28)),
+          CommandComponent::Keyword("hello".to_string(), span(29, 34)),
+          CommandComponent::Keyword("world".to_string(), span(35, 40))
         ],
         modifiers: vec![
           vec![
-            CommandComponent::Keyword("do".to_string()),
-            CommandComponent::Keyword("not".to_string()),
-            CommandComponent::Keyword("say".to_string()),
-            CommandComponent::Keyword("goodbye".to_string())
+            CommandComponent::Keyword("do".to_string(), span(43, 45)),
+            CommandComponent::Keyword("not".to_string(), span(46, 49)),
+            CommandComponent::Keyword("say".to_string(), span(50, 53)),
+            CommandComponent::Keyword("goodbye".to_string(), span(54, 61))
           ]
-        ]
+        ],
+        children: vec![],
+        span: span(25, 61)
       })
-    ]
+    ],
+    span: span(0, 61)
   });
 
   assert_eq!(next_node, expected_node);
@@ -144,37 +171,43 @@ fn test_parser_howto_multiple() {
 
   let next_node: ParseNode = parser.next().unwrap().unwrap();
   let expected_node: ParseNode = ParseNode::HowToStatement(HowToStatement {
+    visibility: Visibility::Public,
     signature: vec![
-      CommandComponent::Keyword("say".to_string()),
-      CommandComponent::Keyword("hello".to_string()),
-      CommandComponent::Keyword("world".to_string())
+      CommandComponent::Keyword("say".to_string(), span(6, 9)),
+      CommandComponent::Keyword("hello".to_string(), span(10, 15)),
+      CommandComponent::Keyword("world".to_string(), span(16, 21))
     ],
     body: vec![
       HowToCommand::HighLevel(Command {
         components: vec![
-          CommandComponent::Keyword("say".to_string()),
-          CommandComponent::Keyword("hello".to_string()),
-          CommandComponent::Keyword("world".to_string())
+          CommandComponent::Keyword("say".to_string(), span(25, 28)),
+          CommandComponent::Keyword("hello".to_string(), span(29, 34)),
+          CommandComponent::Keyword("world".to_string(), span(35, 40))
         ],
         modifiers: vec![
           vec![
-            CommandComponent::Keyword("do".to_string()),
-            CommandComponent::Keyword("not".to_string()),
-            CommandComponent::Keyword("say".to_string()),
-            CommandComponent::Keyword("goodbye".to_string())
+            CommandComponent::Keyword("do".to_string(), span(43, 45)),
+            CommandComponent::Keyword("not".to_string(), span(46, 49)),
+            CommandComponent::Keyword("say".to_string(), span(50, 53)),
+            CommandComponent::Keyword("goodbye".to_string(), span(54, 61))
           ]
-        ]
+        ],
+        children: vec![],
+        span: span(25, 61)
       }),
       HowToCommand::HighLevel(Command {
         components: vec![
-          CommandComponent::Keyword("say".to_string()),
-          CommandComponent::Keyword("hello".to_string()),
-          CommandComponent::Keyword("world".to_string()),
-          CommandComponent::Keyword("again".to_string())
+          CommandComponent::Keyword("say".to_string(), span(64, 67)),
+          CommandComponent::Keyword("hello".to_string(), span(68, 73)),
+          CommandComponent::Keyword("world".to_string(), span(74, 79)),
+          CommandComponent::Keyword("again".to_string(), span(80, 85))
         ],
-        modifiers: vec![]
+        modifiers: vec![],
+        children: vec![],
+        span: span(64, 85)
       })
-    ]
+    ],
+    span: span(0, 85)
   });
 
   assert_eq!(next_node, expected_node);
@@ -186,25 +219,29 @@ fn test_parser_whatis() {
 
   let next_node: ParseNode = parser.next().unwrap().unwrap();
   let expected_node: ParseNode = ParseNode::WhatIsStatement(WhatIsStatement {
+    visibility: Visibility::Public,
     signature: vec![
-      CommandComponent::Keyword("the".to_string()),
-      CommandComponent::Keyword("world".to_string())
+      CommandComponent::Keyword("the".to_string(), span(7, 10)),
+      CommandComponent::Keyword("world".to_string(), span(11, 16))
     ],
     body: vec![
       Command {
         components: vec![
-          CommandComponent::Keyword("a".to_string()),
-          CommandComponent::Keyword("planet".to_string())
+          CommandComponent::Keyword("a".to_string(), span(20, 21)),
+          CommandComponent::Keyword("planet".to_string(), span(22, 28))
         ],
         modifiers: vec![
           vec![
-            CommandComponent::Keyword("in".to_string()),
-            CommandComponent::Keyword("the".to_string()),
-            CommandComponent::Keyword("universe".to_string())
+            CommandComponent::Keyword("in".to_string(), span(31, 33)),
+            CommandComponent::Keyword("the".to_string(), span(34, 37)),
+            CommandComponent::Keyword("universe".to_string(), span(38, 46))
           ]
-        ]
+        ],
+        children: vec![],
+        span: span(20, 46)
       }
-    ]
+    ],
+    span: span(0, 46)
   });
 
   assert_eq!(next_node, expected_node);
@@ -216,36 +253,42 @@ fn test_parser_whatis_multiple() {
 
   let next_node: ParseNode = parser.next().unwrap().unwrap();
   let expected_node: ParseNode = ParseNode::WhatIsStatement(WhatIsStatement {
+    visibility: Visibility::Public,
     signature: vec![
-      CommandComponent::Keyword("the".to_string()),
-      CommandComponent::Keyword("world".to_string())
+      CommandComponent::Keyword("the".to_string(), span(7, 10)),
+      CommandComponent::Keyword("world".to_string(), span(11, 16))
     ],
     body: vec![
       Command {
         components: vec![
-          CommandComponent::Keyword("a".to_string()),
-          CommandComponent::Keyword("planet".to_string())
+          CommandComponent::Keyword("a".to_string(), span(20, 21)),
+          CommandComponent::Keyword("planet".to_string(), span(22, 28))
         ],
         modifiers: vec![
           vec![
-            CommandComponent::Keyword("in".to_string()),
-            CommandComponent::Keyword("the".to_string()),
-            CommandComponent::Keyword("universe".to_string())
+            CommandComponent::Keyword("in".to_string(), span(31, 33)),
+            CommandComponent::Keyword("the".to_string(), span(34, 37)),
+            CommandComponent::Keyword("universe".to_string(), span(38, 46))
           ]
-        ]
+        ],
+        children: vec![],
+        span: span(20, 46)
       },
       Command {
         components: vec![
-          CommandComponent::Keyword("a".to_string()),
-          CommandComponent::Keyword("planet".to_string()),
-          CommandComponent::Keyword("in".to_string()),
-          CommandComponent::Keyword("the".to_string()),
-          CommandComponent::Keyword("solar".to_string()),
-          CommandComponent::Keyword("system".to_string())
+          CommandComponent::Keyword("a".to_string(), span(49, 50)),
+          CommandComponent::Keyword("planet".to_string(), span(51, 57)),
+          CommandComponent::Keyword("in".to_string(), span(58, 60)),
+          CommandComponent::Keyword("the".to_string(), span(61, 64)),
+          CommandComponent::Keyword("solar".to_string(), span(65, 70)),
+          CommandComponent::Keyword("system".to_string(), span(71, 77))
         ],
-        modifiers: vec![]
+        modifiers: vec![],
+        children: vec![],
+        span: span(49, 77)
       }
-    ]
+    ],
+    span: span(0, 77)
   });
 
   assert_eq!(next_node, expected_node);
@@ -257,30 +300,36 @@ fn test_parser_howto_multiple_nomod() {
 
   let next_node: ParseNode = parser.next().unwrap().unwrap();
   let expected_node: ParseNode = ParseNode::HowToStatement(HowToStatement {
+    visibility: Visibility::Public,
     signature: vec![
-      CommandComponent::Keyword("say".to_string()),
-      CommandComponent::Keyword("hello".to_string()),
-      CommandComponent::Keyword("world".to_string())
+      CommandComponent::Keyword("say".to_string(), span(6, 9)),
+      CommandComponent::Keyword("hello".to_string(), span(10, 15)),
+      CommandComponent::Keyword("world".to_string(), span(16, 21))
     ],
     body: vec![
       HowToCommand::HighLevel(Command {
         components: vec![
-          CommandComponent::Keyword("say".to_string()),
-          CommandComponent::Keyword("hello".to_string()),
-          CommandComponent::Keyword("world".to_string())
+          CommandComponent::Keyword("say".to_string(), span(25, 28)),
+          CommandComponent::Keyword("hello".to_string(), span(29, 34)),
+          CommandComponent::Keyword("world".to_string(), span(35, 40))
         ],
-        modifiers: vec![]
+        modifiers: vec![],
+        children: vec![],
+        span: span(25, 40)
       }),
       HowToCommand::HighLevel(Command {
         components: vec![
-          CommandComponent::Keyword("say".to_string()),
-          CommandComponent::Keyword("hello".to_string()),
-          CommandComponent::Keyword("world".to_string()),
-          CommandComponent::Keyword("again".to_string())
+          CommandComponent::Keyword("say".to_string(), span(43, 46)),
+          CommandComponent::Keyword("hello".to_string(), span(47, 52)),
+          CommandComponent::Keyword("world".to_string(), span(53, 58)),
+          CommandComponent::Keyword("again".to_string(), span(59, 64))
         ],
-        modifiers: vec![]
+        modifiers: vec![],
+        children: vec![],
+        span: span(43, 64)
       })
-    ]
+    ],
+    span: span(0, 64)
   });
 
   assert_eq!(next_node, expected_node);
@@ -293,10 +342,12 @@ fn test_parser_slot() {
   let next_node: ParseNode = parser.next().unwrap().unwrap();
   let expected_node: ParseNode = ParseNode::Command(Command {
     components: vec![
-      CommandComponent::Keyword("read".to_string()),
-      CommandComponent::Slot("hello".to_string())
+      CommandComponent::Keyword("read".to_string(), span(0, 4)),
+      CommandComponent::Slot(Slot { name: "hello".to_string(), kind: None }, span(5, 11))
     ],
-    modifiers: vec![]
+    modifiers: vec![],
+    children: vec![],
+    span: span(0, 11)
   });
 
   assert_eq!(next_node, expected_node);
@@ -309,3 +360,876 @@ fn test_parser_openslot() {
 
   parser.next().unwrap().unwrap();
 }
+
+#[test]
+fn test_parser_error_span() {
+  let mut parser = Parser::from("howto say hello world\n- say hello");
+
+  let err = parser.next().unwrap().unwrap_err();
+  assert_eq!(err.span(), Some(span(21, 22)));
+  assert!(matches!(err, ParserError::Expected {
+    expected, found: Some(Token::Newline), ..
+  } if expected == vec![TokenKind::Question]));
+}
+
+#[test]
+fn test_parser_error_expected_slot_name() {
+  let mut parser = Parser::from("read %.");
+
+  match parser.next().unwrap().unwrap_err() {
+    ParserError::Expected { expected, found, span: err_span } => {
+      assert_eq!(expected, vec![TokenKind::Identifier]);
+      assert_eq!(found, Some(Token::Dot));
+      assert_eq!(err_span, span(6, 7));
+    },
+    err => panic!("Expected an Expected error, got {:?}", err)
+  }
+}
+
+#[test]
+fn test_parser_error_empty_howto() {
+  let mut parser = Parser::from("howto say hello?\n\nsay hello");
+
+  match parser.next().unwrap().unwrap_err() {
+    ParserError::EmptyHowToBody { span: err_span } => {
+      assert_eq!(err_span, span(0, 16));
+    },
+    err => panic!("Expected an EmptyHowToBody error, got {:?}", err)
+  }
+}
+
+#[test]
+fn test_parser_error_empty_whatis() {
+  let mut parser = Parser::from("whatis the world?");
+
+  assert!(matches!(parser.next().unwrap().unwrap_err(), ParserError::EmptyWhatIsBody { .. }));
+}
+
+#[test]
+fn test_parser_error_unexpected_token() {
+  let mut parser = Parser::from("- say hello");
+
+  match parser.next().unwrap().unwrap_err() {
+    ParserError::UnexpectedToken { found, span: err_span } => {
+      assert_eq!(found, Token::Punctuation('-'));
+      assert_eq!(err_span, span(0, 1));
+    },
+    err => panic!("Expected an UnexpectedToken error, got {:?}", err)
+  }
+}
+
+#[test]
+fn test_parser_error_lowlevel_in_command() {
+  let mut parser = Parser::from("say $$ fn foo() {} $$");
+
+  assert!(matches!(parser.next().unwrap().unwrap_err(), ParserError::LowLevelNotAllowed { span: err_span } if err_span == span(4, 21)));
+}
+
+#[test]
+fn test_parser_error_message() {
+  let mut parser = Parser::from("whatis the world\n- a planet");
+
+  let err = parser.next().unwrap().unwrap_err();
+  assert_eq!(err.to_string(), "Expected '?', found newline");
+}
+
+#[test]
+fn test_parser_recovery() {
+  let mut parser = Parser::from(
+    "howto say hello\n- say hello\n\nhowto say goodbye?\n- say goodbye\n\nwhatis the world?\n- a & planet\n- round\n\nwhatis the sun?\n- a star"
+  );
+
+  let (nodes, errors) = parser.parse_with_recovery();
+
+  assert_eq!(errors.len(), 2);
+  assert_eq!(errors[0].span(), Some(span(15, 16)));
+  assert_eq!(errors[1].span(), Some(span(85, 86)));
+
+  assert_eq!(nodes.len(), 2);
+  assert!(matches!(&nodes[0], ParseNode::HowToStatement(howto) if howto.span == span(29, 61)));
+  assert!(matches!(&nodes[1], ParseNode::WhatIsStatement(whatis) if whatis.span == span(103, 127)));
+}
+
+#[test]
+fn test_parser_recovery_keyword() {
+  let mut parser = Parser::from("howto say hello\nhowto say goodbye?\n- say goodbye");
+
+  let (nodes, errors) = parser.parse_with_recovery();
+
+  assert_eq!(errors.len(), 1);
+  assert_eq!(nodes.len(), 1);
+}
+
+#[test]
+fn test_parser_error_lowlevel() {
+  let mut parser = Parser::from("howto add?\n-$$\nfn add() -> {}\n$$.");
+
+  match parser.next().unwrap().unwrap_err() {
+    ParserError::LowLevel { span: err_span, block, .. } => {
+      assert_eq!(err_span, span(27, 28));
+      assert_eq!(block, span(12, 32));
+    },
+    err => panic!("Expected a LowLevel error, got {:?}", err)
+  }
+}
+
+#[test]
+fn test_parser_error_lowlevel_unsupported() {
+  let mut parser = Parser::from("howto x?\n-$$ enum Foo { Bar } $$.");
+
+  match parser.next().unwrap().unwrap_err() {
+    ParserError::LowLevel { error, span: err_span, .. } => {
+      assert!(matches!(error, cce_llast::LLError::UnsupportedItem { kind: "enum", .. }));
+      assert_eq!(err_span, span(13, 29));
+    },
+    err => panic!("Expected a LowLevel error, got {:?}", err)
+  }
+}
+
+#[test]
+fn test_parser_primitive_unterminated() {
+  let mut parser = Parser::from("howto x?\n-*\n< do it\n>\n");
+
+  match parser.next().unwrap().unwrap_err() {
+    ParserError::Expected { expected, found, .. } => {
+      assert_eq!(expected, vec![TokenKind::Punctuation('<'), TokenKind::Punctuation('-')]);
+      assert_eq!(found, None);
+    },
+    err => panic!("Expected an Expected error, got {:?}", err)
+  }
+}
+
+#[test]
+fn test_parser_primitive_unclosed_step() {
+  let mut parser = Parser::from("howto x?\n-*\n< do it\n-*.");
+
+  assert!(matches!(parser.next().unwrap().unwrap_err(), ParserError::Expected {
+    found: Some(Token::Punctuation('-')), ..
+  }));
+}
+
+#[test]
+fn test_parser_number() {
+  let mut parser = Parser::from("exit with code 0x2a.");
+
+  let next_node: ParseNode = parser.next().unwrap().unwrap();
+  let expected_node: ParseNode = ParseNode::Command(Command {
+    components: vec![
+      CommandComponent::Keyword("exit".to_string(), span(0, 4)),
+      CommandComponent::Keyword("with".to_string(), span(5, 9)),
+      CommandComponent::Keyword("code".to_string(), span(10, 14)),
+      CommandComponent::Number(42, span(15, 19))
+    ],
+    modifiers: vec![],
+    children: vec![],
+    span: span(0, 19)
+  });
+
+  assert_eq!(next_node, expected_node);
+}
+
+#[test]
+fn test_parser_comments_between_bullets() {
+  let mut parser = Parser::from("// strings\nwhatis a string?\n- text // short\n// more\n- words\n\nsay it.");
+
+  let next_node: ParseNode = parser.next().unwrap().unwrap();
+  let expected_node: ParseNode = ParseNode::WhatIsStatement(WhatIsStatement {
+    visibility: Visibility::Public,
+    signature: vec![
+      CommandComponent::Keyword("a".to_string(), span(18, 19)),
+      CommandComponent::Keyword("string".to_string(), span(20, 26))
+    ],
+    body: vec![
+      Command {
+        components: vec![
+          CommandComponent::Keyword("text".to_string(), span(30, 34))
+        ],
+        modifiers: vec![],
+        children: vec![],
+        span: span(30, 34)
+      },
+      Command {
+        components: vec![
+          CommandComponent::Keyword("words".to_string(), span(54, 59))
+        ],
+        modifiers: vec![],
+        children: vec![],
+        span: span(54, 59)
+      }
+    ],
+    span: span(11, 59)
+  });
+
+  assert_eq!(next_node, expected_node);
+
+  let next_node: ParseNode = parser.next().unwrap().unwrap();
+  assert_eq!(next_node.span(), span(61, 67));
+  assert!(parser.next().is_none());
+}
+
+#[test]
+fn test_parser_iterator_fused() {
+  let mut parser = Parser::from("say hi.\n%\n\nsay bye.");
+
+  assert!(matches!(parser.next(), Some(Ok(ParseNode::Command(_)))));
+  assert!(matches!(parser.next(), Some(Err(ParserError::UnexpectedToken { .. }))));
+  assert!(parser.next().is_none());
+}
+
+#[test]
+fn test_parser_parse_all() {
+  let program: Program = Parser::from("say hi.\nsay bye.").parse_all().unwrap();
+
+  assert_eq!(program.nodes.len(), 2);
+  assert_eq!(program.nodes[1].span(), span(8, 15));
+
+  let err = Parser::from("say hi.\nhowto %?").parse_all().unwrap_err();
+  assert!(matches!(err, ParserError::Expected { .. }));
+}
+
+#[test]
+fn test_parser_nested_howto() {
+  let mut parser = Parser::from("howto greet?\n- say hi\n  - wave\n  - smile\n- leave.\n");
+
+  let next_node: ParseNode = parser.next().unwrap().unwrap();
+  let expected_node: ParseNode = ParseNode::HowToStatement(HowToStatement {
+    visibility: Visibility::Public,
+    signature: vec![
+      CommandComponent::Keyword("greet".to_string(), span(6, 11))
+    ],
+    body: vec![
+      HowToCommand::HighLevel(Command {
+        components: vec![
+          CommandComponent::Keyword("say".to_string(), span(15, 18)),
+          CommandComponent::Keyword("hi".to_string(), span(19, 21))
+        ],
+        modifiers: vec![],
+        children: vec![
+          Command {
+            components: vec![
+              CommandComponent::Keyword("wave".to_string(), span(26, 30))
+            ],
+            modifiers: vec![],
+            children: vec![],
+            span: span(26, 30)
+          },
+          Command {
+            components: vec![
+              CommandComponent::Keyword("smile".to_string(), span(35, 40))
+            ],
+            modifiers: vec![],
+            children: vec![],
+            span: span(35, 40)
+          }
+        ],
+        span: span(15, 21)
+      }),
+      HowToCommand::HighLevel(Command {
+        components: vec![
+          CommandComponent::Keyword("leave".to_string(), span(43, 48))
+        ],
+        modifiers: vec![],
+        children: vec![],
+        span: span(43, 48)
+      })
+    ],
+    span: span(0, 48)
+  });
+
+  assert_eq!(next_node, expected_node);
+  assert!(parser.next().is_none());
+}
+
+#[test]
+fn test_parser_nested_whatis() {
+  let mut parser = Parser::from("whatis a byte?\n- a number\n\t- between 0\n\t\t- and 255\n\t- whole\n- small");
+
+  let whatis: WhatIsStatement = match parser.next().unwrap().unwrap() {
+    ParseNode::WhatIsStatement(whatis) => whatis,
+    other => panic!("Expected a whatis statement, got {:?}", other)
+  };
+
+  let shape: Vec<(usize, usize)> = whatis.body.iter()
+    .map(|command| (command.children.len(), command.children.iter().map(|child| child.children.len()).sum()))
+    .collect();
+  assert_eq!(shape, vec![(2, 1), (0, 0)]);
+
+  let grandchild: &Command = &whatis.body[0].children[0].children[0];
+  assert_eq!(grandchild.components, vec![
+    CommandComponent::Keyword("and".to_string(), span(43, 46)),
+    CommandComponent::Number(255, span(47, 50))
+  ]);
+  assert_eq!(whatis.span, span(0, 67));
+}
+
+#[test]
+fn test_parser_error_inconsistent_indentation() {
+  let mut parser = Parser::from("howto greet?\n- say hi\n    - wave\n  - smile.");
+
+  match parser.next().unwrap().unwrap_err() {
+    ParserError::InconsistentIndentation { span: err_span } => {
+      assert_eq!(err_span, span(35, 36));
+    },
+    other => panic!("Expected an inconsistent indentation error, got {:?}", other)
+  }
+}
+
+#[test]
+fn test_parser_error_mixed_indentation() {
+  let mut parser = Parser::from("howto greet?\n\t- say hi\n  - wave.");
+
+  assert!(matches!(parser.next().unwrap().unwrap_err(), ParserError::InconsistentIndentation { span: err_span } if err_span == span(25, 26)));
+}
+
+#[test]
+fn test_parser_error_nested_block() {
+  let mut parser = Parser::from("howto add?\n- add them\n  -$$ fn f() {} $$\n- done.");
+
+  assert!(matches!(parser.next().unwrap().unwrap_err(), ParserError::NestedBlock { span: err_span } if err_span == span(25, 40)));
+
+  let mut parser = Parser::from("howto add?\n-$$ fn f() {} $$\n  - add them.");
+
+  match parser.next().unwrap().unwrap_err() {
+    ParserError::SubStepOfBlock { span: err_span, block } => {
+      assert_eq!(err_span, span(32, 40));
+      assert_eq!(block, span(12, 27));
+    },
+    other => panic!("Expected a sub-step of block error, got {:?}", other)
+  }
+}
+
+#[test]
+fn test_parser_typed_slot() {
+  let mut parser = Parser::from("howto print %text: a string to it?\n- write %n: 'file stream'.");
+
+  let howto: HowToStatement = match parser.next().unwrap().unwrap() {
+    ParseNode::HowToStatement(howto) => howto,
+    other => panic!("Expected a howto statement, got {:?}", other)
+  };
+
+  assert_eq!(howto.signature, vec![
+    CommandComponent::Keyword("print".to_string(), span(6, 11)),
+    CommandComponent::Slot(Slot {
+      name: "text".to_string(),
+      kind: Some(vec![
+        CommandComponent::Keyword("a".to_string(), span(19, 20)),
+        CommandComponent::Keyword("string".to_string(), span(21, 27))
+      ])
+    }, span(12, 27)),
+    CommandComponent::Keyword("to".to_string(), span(28, 30)),
+    CommandComponent::Keyword("it".to_string(), span(31, 33))
+  ]);
+
+  assert_eq!(howto.body, vec![
+    HowToCommand::HighLevel(Command {
+      components: vec![
+        CommandComponent::Keyword("write".to_string(), span(37, 42)),
+        CommandComponent::Slot(Slot {
+          name: "n".to_string(),
+          kind: Some(vec![
+            CommandComponent::Literal("file stream".to_string(), span(47, 60))
+          ])
+        }, span(43, 60))
+      ],
+      modifiers: vec![],
+      children: vec![],
+      span: span(37, 60)
+    })
+  ]);
+}
+
+#[test]
+fn test_parser_error_slot_kind() {
+  let mut parser = Parser::from("howto print %n: a?\n- it.");
+
+  match parser.next().unwrap().unwrap_err() {
+    ParserError::Expected { expected, found, span: err_span } => {
+      assert_eq!(expected, vec![TokenKind::Identifier, TokenKind::Literal]);
+      assert_eq!(found, Some(Token::Question));
+      assert_eq!(err_span, span(17, 18));
+    },
+    other => panic!("Expected an expected token error, got {:?}", other)
+  }
+}
+
+#[test]
+fn test_parser_conditional() {
+  let mut parser = Parser::from("howto greet?\n- if it is late, say bye\n- otherwise, say hi.");
+
+  let next_node: ParseNode = parser.next().unwrap().unwrap();
+  let expected_node: ParseNode = ParseNode::HowToStatement(HowToStatement {
+    visibility: Visibility::Public,
+    signature: vec![
+      CommandComponent::Keyword("greet".to_string(), span(6, 11))
+    ],
+    body: vec![
+      HowToCommand::Conditional(Conditional {
+        condition: vec![
+          CommandComponent::Keyword("it".to_string(), span(18, 20)),
+          CommandComponent::Keyword("is".to_string(), span(21, 23)),
+          CommandComponent::Keyword("late".to_string(), span(24, 28))
+        ],
+        then: vec![
+          HowToCommand::HighLevel(Command {
+            components: vec![
+              CommandComponent::Keyword("say".to_string(), span(30, 33)),
+              CommandComponent::Keyword("bye".to_string(), span(34, 37))
+            ],
+            modifiers: vec![],
+            children: vec![],
+            span: span(30, 37)
+          })
+        ],
+        otherwise: vec![
+          HowToCommand::HighLevel(Command {
+            components: vec![
+              CommandComponent::Keyword("say".to_string(), span(51, 54)),
+              CommandComponent::Keyword("hi".to_string(), span(55, 57))
+            ],
+            modifiers: vec![],
+            children: vec![],
+            span: span(51, 57)
+          })
+        ],
+        span: span(15, 37)
+      })
+    ],
+    span: span(0, 57)
+  });
+
+  assert_eq!(next_node, expected_node);
+  assert!(parser.next().is_none());
+}
+
+#[test]
+fn test_parser_conditional_bullets() {
+  let source: &str = "\
+howto greet?
+- if it is late
+  - say bye
+  - leave
+- otherwise if it is early, yawn
+- otherwise
+  - say hi
+- smile.";
+  let mut parser = Parser::from(source);
+
+  let howto: HowToStatement = match parser.next().unwrap().unwrap() {
+    ParseNode::HowToStatement(howto) => howto,
+    other => panic!("Expected a howto statement, got {:?}", other)
+  };
+
+  assert_eq!(howto.body.len(), 2);
+
+  let conditional: &Conditional = match &howto.body[0] {
+    HowToCommand::Conditional(conditional) => conditional,
+    other => panic!("Expected a conditional, got {:?}", other)
+  };
+
+  assert_eq!(conditional.span, span(15, 28));
+  assert_eq!(conditional.then.len(), 2);
+
+  let early: &Conditional = match conditional.otherwise.as_slice() {
+    [HowToCommand::Conditional(early)] => early,
+    other => panic!("Expected an otherwise if, got {:?}", other)
+  };
+
+  assert_eq!(early.condition.len(), 3);
+  assert_eq!(early.then.len(), 1);
+  assert_eq!(early.otherwise.len(), 1);
+  assert!(matches!(&early.otherwise[0], HowToCommand::HighLevel(command) if command.span == span(100, 106)));
+}
+
+#[test]
+fn test_parser_conditional_top_level() {
+  let mut parser = Parser::from("if the file exists, delete it.\notherwise if it is open, close it.\notherwise, ignore it.\nsay done.");
+
+  let conditional: Conditional = match parser.next().unwrap().unwrap() {
+    ParseNode::Conditional(conditional) => conditional,
+    other => panic!("Expected a conditional, got {:?}", other)
+  };
+
+  assert_eq!(conditional.span, span(0, 29));
+  assert_eq!(conditional.then.len(), 1);
+
+  match conditional.otherwise.as_slice() {
+    [HowToCommand::Conditional(inner)] => {
+      assert_eq!(inner.span, span(41, 64));
+      assert!(matches!(inner.otherwise.as_slice(), [HowToCommand::HighLevel(command)] if command.span == span(77, 86)));
+    },
+    other => panic!("Expected an otherwise if, got {:?}", other)
+  }
+
+  assert!(matches!(parser.next().unwrap().unwrap(), ParseNode::Command(_)));
+  assert!(parser.next().is_none());
+}
+
+#[test]
+fn test_parser_error_otherwise_without_if() {
+  let mut parser = Parser::from("howto greet?\n- say hi\n- otherwise, say bye.");
+  assert!(matches!(parser.next().unwrap().unwrap_err(), ParserError::OtherwiseWithoutIf { span: err_span } if err_span == span(24, 42)));
+
+  let mut parser = Parser::from("howto greet?\n- if late, say bye\n- otherwise, say hi\n- otherwise, wave.");
+  assert!(matches!(parser.next().unwrap().unwrap_err(), ParserError::OtherwiseWithoutIf { .. }));
+
+  let mut parser = Parser::from("otherwise, say hi.");
+  assert!(matches!(parser.next().unwrap().unwrap_err(), ParserError::OtherwiseWithoutIf { span: err_span } if err_span == span(0, 9)));
+}
+
+#[test]
+fn test_parser_error_empty_branch() {
+  let mut parser = Parser::from("howto greet?\n- if it is late\n- say hi.");
+  assert!(matches!(parser.next().unwrap().unwrap_err(), ParserError::EmptyBranch { span: err_span } if err_span == span(15, 28)));
+
+  let mut parser = Parser::from("howto greet?\n- if it is late, say bye\n- otherwise\n- say hi.");
+  assert!(matches!(parser.next().unwrap().unwrap_err(), ParserError::EmptyBranch { span: err_span } if err_span == span(40, 49)));
+
+  let mut parser = Parser::from("if it is late\nsay hi.");
+  assert!(matches!(parser.next().unwrap().unwrap_err(), ParserError::EmptyBranch { .. }));
+}
+
+#[test]
+fn test_parser_error_nested_conditional_under_command() {
+  let mut parser = Parser::from("howto greet?\n- say hi\n  - if it is late, wave.");
+  assert!(matches!(parser.next().unwrap().unwrap_err(), ParserError::NestedBlock { span: err_span } if err_span == span(26, 45)));
+}
+
+#[test]
+fn test_parser_loop() {
+  let mut parser = Parser::from("howto greet?\n- repeat 3 times, say hi.");
+
+  let next_node: ParseNode = parser.next().unwrap().unwrap();
+  let expected_node: ParseNode = ParseNode::HowToStatement(HowToStatement {
+    visibility: Visibility::Public,
+    signature: vec![
+      CommandComponent::Keyword("greet".to_string(), span(6, 11))
+    ],
+    body: vec![
+      HowToCommand::Loop(Loop {
+        kind: LoopKind::Repeat {
+          count: vec![
+            CommandComponent::Number(3, span(22, 23))
+          ]
+        },
+        body: vec![
+          HowToCommand::HighLevel(Command {
+            components: vec![
+              CommandComponent::Keyword("say".to_string(), span(31, 34)),
+              CommandComponent::Keyword("hi".to_string(), span(35, 37))
+            ],
+            modifiers: vec![],
+            children: vec![],
+            span: span(31, 37)
+          })
+        ],
+        span: span(15, 37)
+      })
+    ],
+    span: span(0, 37)
+  });
+
+  assert_eq!(next_node, expected_node);
+  assert!(parser.next().is_none());
+}
+
+#[test]
+fn test_parser_loop_bullets() {
+  let source: &str = "\
+howto wait?
+- while the door is open
+  - if it is late, leave
+  - knock
+- say bye.";
+  let mut parser = Parser::from(source);
+
+  let howto: HowToStatement = match parser.next().unwrap().unwrap() {
+    ParseNode::HowToStatement(howto) => howto,
+    other => panic!("Expected a howto statement, got {:?}", other)
+  };
+
+  assert_eq!(howto.body.len(), 2);
+
+  let repeat: &Loop = match &howto.body[0] {
+    HowToCommand::Loop(repeat) => repeat,
+    other => panic!("Expected a loop, got {:?}", other)
+  };
+
+  assert_eq!(repeat.span, span(14, 36));
+  assert!(matches!(&repeat.kind, LoopKind::While { condition } if condition.len() == 4));
+  assert!(matches!(repeat.body.as_slice(), [HowToCommand::Conditional(_), HowToCommand::HighLevel(command)] if command.span == span(66, 71)));
+}
+
+#[test]
+fn test_parser_loop_top_level() {
+  let mut parser = Parser::from("for each %line: a line in the file, print %line.\nsay done.");
+
+  let next_node: ParseNode = parser.next().unwrap().unwrap();
+  let expected_node: ParseNode = ParseNode::Loop(Loop {
+    kind: LoopKind::ForEach {
+      item: Slot {
+        name: "line".to_string(),
+        kind: Some(vec![
+          CommandComponent::Keyword("a".to_string(), span(16, 17)),
+          CommandComponent::Keyword("line".to_string(), span(18, 22))
+        ])
+      },
+      item_span: span(9, 22),
+      collection: vec![
+        CommandComponent::Keyword("the".to_string(), span(26, 29)),
+        CommandComponent::Keyword("file".to_string(), span(30, 34))
+      ]
+    },
+    body: vec![
+      HowToCommand::HighLevel(Command {
+        components: vec![
+          CommandComponent::Keyword("print".to_string(), span(36, 41)),
+          CommandComponent::Slot(Slot { name: "line".to_string(), kind: None }, span(42, 47))
+        ],
+        modifiers: vec![],
+        children: vec![],
+        span: span(36, 47)
+      })
+    ],
+    span: span(0, 47)
+  });
+
+  assert_eq!(next_node, expected_node);
+  assert!(matches!(parser.next().unwrap().unwrap(), ParseNode::Command(_)));
+  assert!(parser.next().is_none());
+}
+
+#[test]
+fn test_parser_statement_words_in_commands() {
+  let source: &str = "repeat the song.\nwhile away the hours.\nfor good measure.\nmodule the house.\nwithin the hour call back.\nif.\n\nhowto rest?\n- repeat the chorus\n- otherwise engaged\n- for now";
+  let nodes: Program = Parser::from(source).parse_all().unwrap();
+
+  let commands: Vec<String> = nodes.nodes.iter().map(|node| match node {
+    ParseNode::Command(command) => words(&command.components),
+    node => panic!("expected a command, got {:?}", node)
+  }).take(6).collect();
+
+  assert_eq!(commands, vec![
+    "repeat the song", "while away the hours", "for good measure", "module the house", "within the hour call back", "if"
+  ]);
+
+  match &nodes.nodes[6] {
+    ParseNode::HowToStatement(howto) => {
+      let steps: Vec<String> = howto.body.iter().map(|step| match step {
+        HowToCommand::HighLevel(command) => words(&command.components),
+        step => panic!("expected a command, got {:?}", step)
+      }).collect();
+
+      assert_eq!(steps, vec!["repeat the chorus", "otherwise engaged", "for now"]);
+    },
+    node => panic!("expected a howto, got {:?}", node)
+  }
+}
+
+#[test]
+fn test_parser_statement_words_after_conditional() {
+  let source: &str = "print it if it fails.
+if late, run.
+otherwise engaged.
+if late, run.
+otherwise, walk.";
+  let nodes: Program = Parser::from(source).parse_all().unwrap();
+
+  match nodes.nodes.as_slice() {
+    [ParseNode::Command(print), ParseNode::Conditional(first), ParseNode::Command(engaged), ParseNode::Conditional(second)] => {
+      assert_eq!(words(&print.components), "print it if it fails");
+      assert!(first.otherwise.is_empty());
+      assert_eq!(words(&engaged.components), "otherwise engaged");
+      assert_eq!(engaged.span, span(36, 53));
+      assert_eq!(second.otherwise.len(), 1);
+    },
+    nodes => panic!("expected a command, two conditionals and a command between, got {:?}", nodes)
+  }
+}
+
+#[test]
+fn test_parser_error_empty_loop() {
+  let mut parser = Parser::from("howto wait?\n- while the door is open\n  - if it is late, leave\n  - knock\n- for each %x in the list\n- print it.");
+  assert!(matches!(parser.next().unwrap().unwrap_err(), ParserError::EmptyLoop { span: err_span } if err_span == span(74, 97)));
+
+  let mut parser = Parser::from("repeat 2 times.");
+  assert!(matches!(parser.next().unwrap().unwrap_err(), ParserError::EmptyLoop { span: err_span } if err_span == span(0, 14)));
+}
+
+#[test]
+fn test_parser_error_loop_expected_word() {
+  let mut parser = Parser::from("howto wait?\n- repeat it, knock.");
+  assert!(matches!(parser.next().unwrap().unwrap_err(), ParserError::ExpectedWord { word, found: Some(Token::Punctuation(',')), span: err_span } if word == "times" && err_span == span(23, 24)));
+
+  let mut parser = Parser::from("for %x in the list, print %x.");
+  assert!(matches!(parser.next().unwrap().unwrap_err(), ParserError::ExpectedWord { word, found: Some(Token::Percent), .. } if word == "each"));
+
+  let mut parser = Parser::from("for each %x of the list, print %x.");
+  assert!(matches!(parser.next().unwrap().unwrap_err(), ParserError::ExpectedWord { word, span: err_span, .. } if word == "in" && err_span == span(12, 14)));
+}
+
+#[test]
+fn test_parser_binding() {
+  let mut parser = Parser::from("read a line | from the console and call it name.\nprint %name.");
+
+  let next_node: ParseNode = parser.next().unwrap().unwrap();
+  let expected_node: ParseNode = ParseNode::Binding(Binding {
+    command: Command {
+      components: vec![
+        CommandComponent::Keyword("read".to_string(), span(0, 4)),
+        CommandComponent::Keyword("a".to_string(), span(5, 6)),
+        CommandComponent::Keyword("line".to_string(), span(7, 11))
+      ],
+      modifiers: vec![
+        vec![
+          CommandComponent::Keyword("from".to_string(), span(14, 18)),
+          CommandComponent::Keyword("the".to_string(), span(19, 22)),
+          CommandComponent::Keyword("console".to_string(), span(23, 30))
+        ]
+      ],
+      children: vec![],
+      span: span(0, 30)
+    },
+    name: "name".to_string(),
+    name_span: span(43, 47),
+    span: span(0, 47)
+  });
+
+  assert_eq!(next_node, expected_node);
+  assert!(matches!(parser.next().unwrap().unwrap(), ParseNode::Command(_)));
+  assert!(parser.next().is_none());
+}
+
+#[test]
+fn test_parser_binding_bullets() {
+  let mut parser = Parser::from("howto greet?\n- ask for a name and call it who\n  - politely\n- say %who.");
+
+  let howto: HowToStatement = match parser.next().unwrap().unwrap() {
+    ParseNode::HowToStatement(howto) => howto,
+    other => panic!("Expected a howto statement, got {:?}", other)
+  };
+
+  let binding: &Binding = match howto.body.as_slice() {
+    [HowToCommand::Binding(binding), HowToCommand::HighLevel(_)] => binding,
+    other => panic!("Expected a binding and a command, got {:?}", other)
+  };
+
+  assert_eq!(binding.name, "who");
+  assert_eq!(binding.name_span, span(42, 45));
+  assert_eq!(binding.span, span(15, 45));
+  assert_eq!(binding.command.span, span(15, 29));
+  assert!(matches!(binding.command.children.as_slice(), [child] if child.span == span(50, 58)));
+}
+
+#[test]
+fn test_parser_binding_needs_command() {
+  let mut parser = Parser::from("and call it x.\nwhatis a name?\n- what you call it when introduced.");
+
+  assert!(matches!(parser.next().unwrap().unwrap(), ParseNode::Command(command) if command.components.len() == 4));
+  assert!(matches!(parser.next().unwrap().unwrap(), ParseNode::WhatIsStatement(_)));
+
+  let mut parser = Parser::from("howto greet?\n- say hi\n  - ask a name and call it who.");
+  assert!(matches!(parser.next().unwrap().unwrap_err(), ParserError::NestedBlock { .. }));
+}
+
+#[test]
+fn test_parser_import() {
+  let mut parser = Parser::from("use 'console'.\ninclude './greet'.\nuse the hammer.");
+
+  assert_eq!(parser.next().unwrap().unwrap(), ParseNode::Import(Import {
+    kind: ImportKind::Use,
+    path: "console".to_string(),
+    path_span: span(4, 13),
+    span: span(0, 13)
+  }));
+
+  assert!(matches!(parser.next().unwrap().unwrap(), ParseNode::Import(Import { kind: ImportKind::Include, path, .. }) if path == "./greet"));
+  assert!(matches!(parser.next().unwrap().unwrap(), ParseNode::Command(_)));
+}
+
+#[test]
+fn test_parser_module() {
+  let mut parser = Parser::from("module console.\nprivate howto print %s?\n- within io, write %s.\nwithin console, print 'hi'.");
+
+  assert_eq!(parser.next().unwrap().unwrap(), ParseNode::Module(Module {
+    name: "console".to_string(),
+    name_span: span(7, 14),
+    span: span(0, 14)
+  }));
+
+  assert_eq!(parser.next().unwrap().unwrap(), ParseNode::HowToStatement(HowToStatement {
+    visibility: Visibility::Private,
+    signature: vec![
+      CommandComponent::Keyword("print".to_string(), span(30, 35)),
+      CommandComponent::Slot(Slot { name: "s".to_string(), kind: None }, span(36, 38))
+    ],
+    body: vec![
+      HowToCommand::Qualified(Qualified {
+        module: "io".to_string(),
+        module_span: span(49, 51),
+        command: Command {
+          components: vec![
+            CommandComponent::Keyword("write".to_string(), span(53, 58)),
+            CommandComponent::Slot(Slot { name: "s".to_string(), kind: None }, span(59, 61))
+          ],
+          modifiers: vec![],
+          children: vec![],
+          span: span(53, 61)
+        },
+        span: span(42, 61)
+      })
+    ],
+    span: span(16, 61)
+  }));
+
+  assert_eq!(parser.next().unwrap().unwrap(), ParseNode::Qualified(Qualified {
+    module: "console".to_string(),
+    module_span: span(70, 77),
+    command: Command {
+      components: vec![
+        CommandComponent::Keyword("print".to_string(), span(79, 84)),
+        CommandComponent::Literal("hi".to_string(), span(85, 89))
+      ],
+      modifiers: vec![],
+      children: vec![],
+      span: span(79, 89)
+    },
+    span: span(63, 89)
+  }));
+
+  assert!(parser.next().is_none());
+}
+
+#[test]
+fn test_parser_private_without_description() {
+  let mut parser = Parser::from("private print 'hi'.");
+
+  assert!(matches!(parser.next().unwrap().unwrap(), ParseNode::Command(_)));
+}
+
+#[test]
+fn test_parser_query() {
+  let mut parser = Parser::from("? whatis stdout\n? howto print %n: a number.\n? print it");
+
+  assert_eq!(parser.next().unwrap().unwrap(), ParseNode::Query(Query {
+    kind: QueryKind::WhatIs,
+    subject: vec![CommandComponent::Keyword("stdout".to_string(), span(9, 15))],
+    span: span(0, 15)
+  }));
+
+  assert_eq!(parser.next().unwrap().unwrap(), ParseNode::Query(Query {
+    kind: QueryKind::HowTo,
+    subject: vec![
+      CommandComponent::Keyword("print".to_string(), span(24, 29)),
+      CommandComponent::Slot(Slot {
+        name: "n".to_string(),
+        kind: Some(vec![
+          CommandComponent::Keyword("a".to_string(), span(34, 35)),
+          CommandComponent::Keyword("number".to_string(), span(36, 42))
+        ])
+      }, span(30, 42))
+    ],
+    span: span(16, 42)
+  }));
+
+  assert!(parser.next().unwrap().is_err());
+}
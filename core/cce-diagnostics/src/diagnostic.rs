@@ -0,0 +1,133 @@
+/*
+
+Copyright (C) 2023 Carlos Kieliszewski
+
+This file is part of the Circe Project.
+
+Circe is free software: you can redistribute it and/or modify it under
+the terms of the GNU General Public License as published by the Free
+Software Foundation, either version 3 of the License, or (at your option)
+any later version.
+
+Circe is distributed in the hope that it will be useful, but WITHOUT ANY
+WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+FOR A PARTICULAR PURPOSE. See the GNU General Public License for more details.
+
+You should have received a copy of the GNU General Public License along with
+Circe. If not, see <https://www.gnu.org/licenses/>. 
+
+*/
+
+
+use cce_stream::Span;
+
+use std::fmt;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+  Error,
+  Warning,
+  Note,
+  Help
+}
+
+impl fmt::Display for Severity {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      Severity::Error => write!(f, "error"),
+      Severity::Warning => write!(f, "warning"),
+      Severity::Note => write!(f, "note"),
+      Severity::Help => write!(f, "help")
+    }
+  }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LabelStyle {
+  /// Points at the source of the problem, drawn with `^`.
+  Primary,
+  /// Points at related context, drawn with `-`.
+  Secondary
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Label {
+  pub style: LabelStyle,
+  pub span: Span,
+  pub message: String
+}
+
+impl Label {
+  pub fn primary(span: Span, message: impl Into<String>) -> Self {
+    Label { style: LabelStyle::Primary, span, message: message.into() }
+  }
+
+  pub fn secondary(span: Span, message: impl Into<String>) -> Self {
+    Label { style: LabelStyle::Secondary, span, message: message.into() }
+  }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+  pub severity: Severity,
+  pub code: Option<String>,
+  pub message: String,
+  pub labels: Vec<Label>,
+  pub notes: Vec<String>,
+  pub help: Vec<String>
+}
+
+impl Diagnostic {
+  pub fn new(severity: Severity, message: impl Into<String>) -> Self {
+    Diagnostic {
+      severity,
+      code: None,
+      message: message.into(),
+      labels: Vec::new(),
+      notes: Vec::new(),
+      help: Vec::new()
+    }
+  }
+
+  pub fn error(message: impl Into<String>) -> Self {
+    Diagnostic::new(Severity::Error, message)
+  }
+
+  pub fn warning(message: impl Into<String>) -> Self {
+    Diagnostic::new(Severity::Warning, message)
+  }
+
+  pub fn with_code(mut self, code: impl Into<String>) -> Self {
+    self.code = Some(code.into());
+    self
+  }
+
+  pub fn with_label(mut self, label: Label) -> Self {
+    self.labels.push(label);
+    self
+  }
+
+  pub fn with_note(mut self, note: impl Into<String>) -> Self {
+    self.notes.push(note.into());
+    self
+  }
+
+  pub fn with_help(mut self, help: impl Into<String>) -> Self {
+    self.help.push(help.into());
+    self
+  }
+
+  /// The label the diagnostic is reported at: the first primary label,
+  /// or the first label of any kind if there is no primary one.
+  pub fn primary_label(&self) -> Option<&Label> {
+    self.labels.iter()
+      .find(|label| label.style == LabelStyle::Primary)
+      .or_else(|| self.labels.first())
+  }
+}
+
+/// Implemented by the error types of every compiler stage so that
+/// `ccec` can report them all the same way.
+pub trait ToDiagnostic {
+  fn to_diagnostic(&self) -> Diagnostic;
+}
@@ -0,0 +1,284 @@
+/*
+
+Copyright (C) 2023 Carlos Kieliszewski
+
+This file is part of the Circe Project.
+
+Circe is free software: you can redistribute it and/or modify it under
+the terms of the GNU General Public License as published by the Free
+Software Foundation, either version 3 of the License, or (at your option)
+any later version.
+
+Circe is distributed in the hope that it will be useful, but WITHOUT ANY
+WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+FOR A PARTICULAR PURPOSE. See the GNU General Public License for more details.
+
+You should have received a copy of the GNU General Public License along with
+Circe. If not, see <https://www.gnu.org/licenses/>. 
+
+*/
+
+
+use crate::diagnostic::{Diagnostic, Label, LabelStyle, Severity};
+use cce_stream::{FileId, Location, SourceFile, SourceMap};
+
+use std::fmt::Write;
+
+/// Gives the renderer access to the names and contents of source files.
+pub trait Files {
+  fn name(&self, file: FileId) -> Option<&str>;
+  fn source(&self, file: FileId) -> Option<&str>;
+  /// Resolves a byte offset within a file to a line and column.
+  fn location(&self, file: FileId, offset: usize) -> Option<Location>;
+  /// The text of a 1-based line of a file, without its line ending.
+  fn line(&self, file: FileId, line: usize) -> Option<&str>;
+}
+
+/// A single named source, used for every `FileId`.
+pub struct SimpleFile {
+  file: SourceFile
+}
+
+impl SimpleFile {
+  pub fn new(name: &str, source: &str) -> Self {
+    SimpleFile { file: SourceFile::new_virtual(FileId::default(), name, source) }
+  }
+}
+
+impl Files for SimpleFile {
+  fn name(&self, _file: FileId) -> Option<&str> {
+    Some(self.file.display_name())
+  }
+
+  fn source(&self, _file: FileId) -> Option<&str> {
+    self.file.source()
+  }
+
+  fn location(&self, _file: FileId, offset: usize) -> Option<Location> {
+    self.file.location(offset)
+  }
+
+  fn line(&self, _file: FileId, line: usize) -> Option<&str> {
+    self.file.line(line)
+  }
+}
+
+impl Files for SourceMap {
+  fn name(&self, file: FileId) -> Option<&str> {
+    SourceMap::name(self, file)
+  }
+
+  fn source(&self, file: FileId) -> Option<&str> {
+    SourceMap::source(self, file)
+  }
+
+  fn location(&self, file: FileId, offset: usize) -> Option<Location> {
+    SourceMap::location(self, file, offset)
+  }
+
+  fn line(&self, file: FileId, line: usize) -> Option<&str> {
+    self.file(file)?.line(line)
+  }
+}
+
+const TAB_WIDTH: usize = 4;
+
+const STYLE_BOLD: &str = "1";
+const STYLE_GUTTER: &str = "1;34";
+
+fn display_width<I: Iterator<Item = char>>(chars: I) -> usize {
+  chars.map(|c| if c == '\t' { TAB_WIDTH } else { 1 }).sum()
+}
+
+/// The labels of a diagnostic that fall in one file.
+struct Snippet<'d> {
+  file: FileId,
+  name: &'d str,
+  /// Where the `-->` header points.
+  at: Location,
+  labels: Vec<(Location, Location, &'d Label)>
+}
+
+/// Turns a [`Diagnostic`] into text, showing every labelled line of
+/// source with the labelled range underlined.
+pub struct Renderer {
+  pub color: bool
+}
+
+impl Renderer {
+  pub fn new(color: bool) -> Self {
+    Renderer { color }
+  }
+
+  fn paint(&self, text: &str, style: &str) -> String {
+    if self.color {
+      format!("\x1b[{}m{}\x1b[0m", style, text)
+    } else {
+      text.to_string()
+    }
+  }
+
+  fn severity_style(severity: Severity) -> &'static str {
+    match severity {
+      Severity::Error => "1;31",
+      Severity::Warning => "1;33",
+      Severity::Note => "1;32",
+      Severity::Help => "1;36"
+    }
+  }
+
+  pub fn render(&self, diagnostic: &Diagnostic, files: &dyn Files) -> String {
+    let mut out: String = String::new();
+    let severity_style: &str = Renderer::severity_style(diagnostic.severity);
+
+    let title: String = match &diagnostic.code {
+      Some(code) => format!("{}[{}]", diagnostic.severity, code),
+      None => diagnostic.severity.to_string()
+    };
+
+    let _ = writeln!(out, "{}{}",
+      self.paint(&title, severity_style),
+      self.paint(&format!(": {}", diagnostic.message), STYLE_BOLD)
+    );
+
+    let primary: &Label = match diagnostic.primary_label() {
+      Some(primary) if files.source(primary.span.file).is_some() => primary,
+      _ => {
+        self.render_footer(&mut out, diagnostic, 0);
+        return out;
+      }
+    };
+
+    // One snippet per file, the primary label's first and then the others
+    // in the order their labels were added. Files whose source can't be
+    // read are left out.
+    let mut snippets: Vec<Snippet> = Vec::new();
+
+    for label in std::iter::once(primary).chain(diagnostic.labels.iter()) {
+      if snippets.iter().any(|snippet| snippet.file == label.span.file) {
+        continue;
+      }
+
+      let file: FileId = label.span.file;
+      let mut labels: Vec<(Location, Location, &Label)> = diagnostic.labels.iter()
+        .filter(|other| other.span.file == file)
+        .filter_map(|other| Some((
+          files.location(file, other.span.start)?,
+          files.location(file, other.span.end)?,
+          other
+        )))
+        .collect();
+
+      labels.sort_by_key(|(start, _, _)| (start.line, start.column));
+
+      // The primary snippet points at the primary label, and the others at
+      // their first label.
+      let at: Option<Location> = if file == primary.span.file {
+        files.location(file, primary.span.start)
+      } else {
+        labels.first().map(|(start, _, _)| *start)
+      };
+
+      let at: Location = match at {
+        Some(at) => at,
+        None => continue
+      };
+
+      snippets.push(Snippet {
+        file,
+        name: files.name(file).unwrap_or("<unknown>"),
+        at,
+        labels
+      });
+    }
+
+    let last_line: usize = snippets.iter()
+      .flat_map(|snippet| snippet.labels.iter())
+      .map(|(start, _, _)| start.line)
+      .max()
+      .unwrap_or(1);
+    let width: usize = last_line.to_string().len();
+
+    for (i, snippet) in snippets.iter().enumerate() {
+      if i > 0 {
+        let _ = writeln!(out, "{} {}", " ".repeat(width), self.paint("|", STYLE_GUTTER));
+      }
+
+      self.render_snippet(&mut out, snippet, width, severity_style, files);
+    }
+
+    self.render_footer(&mut out, diagnostic, width);
+
+    out
+  }
+
+  /// Writes a file's `-->` header and its labelled lines.
+  fn render_snippet(&self, out: &mut String, snippet: &Snippet, width: usize, severity_style: &str, files: &dyn Files) {
+    let pad: String = " ".repeat(width);
+    let gutter: String = self.paint("|", STYLE_GUTTER);
+
+    let _ = writeln!(out, "{}{} {}:{}:{}",
+      pad, self.paint("-->", STYLE_GUTTER), snippet.name, snippet.at.line, snippet.at.column
+    );
+    let _ = writeln!(out, "{} {}", pad, gutter);
+
+    let mut previous_line: Option<usize> = None;
+
+    for (start, end, label) in snippet.labels.iter() {
+      if previous_line != Some(start.line) {
+        if previous_line.is_some_and(|line| start.line > line + 1) {
+          let _ = writeln!(out, "{}", self.paint("...", STYLE_GUTTER));
+        }
+
+        let text: String = files.line(snippet.file, start.line).unwrap_or("").replace('\t', &" ".repeat(TAB_WIDTH));
+        let number: String = format!("{:>width$}", start.line, width = width);
+        let _ = writeln!(out, "{} {} {}", self.paint(&number, STYLE_GUTTER), gutter, text);
+        previous_line = Some(start.line);
+      }
+
+      // Columns are 1-based, so the label starts after `start.column - 1`
+      // characters of its line.
+      let line: &str = files.line(snippet.file, start.line).unwrap_or("");
+      let offset: usize = display_width(line.chars().take(start.column - 1));
+      let length: usize = if end.line == start.line {
+        display_width(line.chars().skip(start.column - 1).take(end.column.saturating_sub(start.column)))
+      } else {
+        display_width(line.chars().skip(start.column - 1))
+      };
+
+      let (mark, style) = match label.style {
+        LabelStyle::Primary => ("^", severity_style),
+        LabelStyle::Secondary => ("-", STYLE_GUTTER)
+      };
+
+      let underline: String = mark.repeat(length.max(1));
+      let annotation: String = if label.message.is_empty() {
+        underline
+      } else {
+        format!("{} {}", underline, label.message)
+      };
+
+      let _ = writeln!(out, "{} {} {}{}", pad, gutter, " ".repeat(offset), self.paint(&annotation, style));
+    }
+  }
+
+  fn render_footer(&self, out: &mut String, diagnostic: &Diagnostic, width: usize) {
+    if diagnostic.notes.is_empty() && diagnostic.help.is_empty() {
+      return;
+    }
+
+    let pad: String = " ".repeat(width);
+
+    if width > 0 {
+      let _ = writeln!(out, "{} {}", pad, self.paint("|", STYLE_GUTTER));
+    }
+
+    for note in diagnostic.notes.iter() {
+      let _ = writeln!(out, "{} {} {}", pad, self.paint("= note:", STYLE_BOLD), note);
+    }
+
+    for help in diagnostic.help.iter() {
+      let _ = writeln!(out, "{} {} {}", pad, self.paint("= help:", STYLE_BOLD), help);
+    }
+  }
+}
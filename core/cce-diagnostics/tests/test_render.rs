@@ -0,0 +1,162 @@
+/*
+
+Copyright (C) 2023 Carlos Kieliszewski
+
+This file is part of the Circe Project.
+
+Circe is free software: you can redistribute it and/or modify it under
+the terms of the GNU General Public License as published by the Free
+Software Foundation, either version 3 of the License, or (at your option)
+any later version.
+
+Circe is distributed in the hope that it will be useful, but WITHOUT ANY
+WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+FOR A PARTICULAR PURPOSE. See the GNU General Public License for more details.
+
+You should have received a copy of the GNU General Public License along with
+Circe. If not, see <https://www.gnu.org/licenses/>. 
+
+*/
+
+
+use cce_diagnostics::*;
+use cce_stream::{Span, FileId, SourceMap};
+
+fn span(start: usize, end: usize) -> Span {
+  Span::new(FileId(0), start, end)
+}
+
+const SOURCE: &str = "howto print a string?\n- write the string\n| add a newline\n";
+
+#[test]
+fn test_render_primary() {
+  let file = SimpleFile::new("hello.cce", SOURCE);
+  let diagnostic = Diagnostic::error("unknown description")
+    .with_code("E0000")
+    .with_label(Label::primary(span(24, 29), "nothing describes how to write"));
+
+  let expected = "\
+error[E0000]: unknown description
+ --> hello.cce:2:3
+  |
+2 | - write the string
+  |   ^^^^^ nothing describes how to write
+";
+
+  assert_eq!(Renderer::new(false).render(&diagnostic, &file), expected);
+}
+
+#[test]
+fn test_render_secondary_notes() {
+  let file = SimpleFile::new("hello.cce", SOURCE);
+  let diagnostic = Diagnostic::warning("modifier is ignored")
+    .with_label(Label::primary(span(43, 56), "this modifier"))
+    .with_label(Label::secondary(span(6, 11), "applies to this description"))
+    .with_note("modifiers only apply to the command above them")
+    .with_help("move the modifier below a command");
+
+  let expected = "\
+warning: modifier is ignored
+ --> hello.cce:3:3
+  |
+1 | howto print a string?
+  |       ----- applies to this description
+...
+3 | | add a newline
+  |   ^^^^^^^^^^^^^ this modifier
+  |
+  = note: modifiers only apply to the command above them
+  = help: move the modifier below a command
+";
+
+  assert_eq!(Renderer::new(false).render(&diagnostic, &file), expected);
+}
+
+#[test]
+fn test_render_no_labels() {
+  let file = SimpleFile::new("hello.cce", SOURCE);
+  let diagnostic = Diagnostic::error("no input files")
+    .with_note("pass a .cce file to compile");
+
+  let expected = "\
+error: no input files
+ = note: pass a .cce file to compile
+";
+
+  assert_eq!(Renderer::new(false).render(&diagnostic, &file), expected);
+}
+
+#[test]
+fn test_render_color() {
+  let file = SimpleFile::new("hello.cce", SOURCE);
+  let diagnostic = Diagnostic::error("bad")
+    .with_label(Label::primary(span(0, 5), ""));
+
+  let rendered = Renderer::new(true).render(&diagnostic, &file);
+
+  assert!(rendered.starts_with("\x1b[1;31merror\x1b[0m"));
+  assert!(rendered.contains("\x1b[1;31m^^^^^\x1b[0m"));
+}
+
+#[test]
+fn test_render_multibyte() {
+  let file = SimpleFile::new("café.cce", "say 'café' to olé.\n");
+  let diagnostic = Diagnostic::error("unknown word")
+    .with_label(Label::primary(span(15, 19), "what is this?"));
+
+  let expected = "\
+error: unknown word
+ --> café.cce:1:15
+  |
+1 | say 'café' to olé.
+  |               ^^^ what is this?
+";
+
+  assert_eq!(Renderer::new(false).render(&diagnostic, &file), expected);
+}
+
+#[test]
+fn test_render_source_map() {
+  let mut sources = SourceMap::new();
+  sources.add_virtual("<prelude>", "whatis a byte?\n- a number\n");
+  let main = sources.add_virtual("main.cce", "write byte 10.\n");
+
+  let diagnostic = Diagnostic::error("unknown description")
+    .with_label(Label::primary(Span::new(main, 0, 5), "nothing describes how to write"));
+
+  let expected = "\
+error: unknown description
+ --> main.cce:1:1
+  |
+1 | write byte 10.
+  | ^^^^^ nothing describes how to write
+";
+
+  assert_eq!(Renderer::new(false).render(&diagnostic, &sources), expected);
+}
+
+#[test]
+fn test_render_several_files() {
+  let mut sources = SourceMap::new();
+  let library = sources.add_virtual("library.cce", "howto print a string?\n- write it\n");
+  let main = sources.add_virtual("main.cce", "say hi.\nhowto print the string?\n- say it\n");
+
+  let diagnostic = Diagnostic::error("'howto print the string?' is defined more than once")
+    .with_label(Label::primary(Span::new(main, 14, 30), "defined again here"))
+    .with_label(Label::secondary(Span::new(library, 6, 20), "first defined here"));
+
+  let expected = "\
+error: 'howto print the string?' is defined more than once
+ --> main.cce:2:7
+  |
+2 | howto print the string?
+  |       ^^^^^^^^^^^^^^^^ defined again here
+  |
+ --> library.cce:1:7
+  |
+1 | howto print a string?
+  |       -------------- first defined here
+";
+
+  assert_eq!(Renderer::new(false).render(&diagnostic, &sources), expected);
+}
@@ -19,37 +19,260 @@ Circe. If not, see <https://www.gnu.org/licenses/>.
 */
 
 
+mod source_map;
+
+pub use source_map::{SourceMap, SourceFile, FileName, Location};
+
+use circelang_hash::CirceHash;
+use std::collections::VecDeque;
+use std::io::BufRead;
 use thiserror::Error;
 
+/// Identifies the source file a [`Span`] points into.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, PartialOrd, Ord)]
+pub struct FileId(pub usize);
+
+/// A half-open `start..end` range of byte offsets in a single source
+/// file, so it can be used to slice the source text directly.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct Span {
+  pub file: FileId,
+  pub start: usize,
+  pub end: usize
+}
+
+impl Span {
+  pub fn new(file: FileId, start: usize, end: usize) -> Self {
+    Span { file, start, end }
+  }
+
+  /// Returns the smallest span covering both `self` and `other`.
+  pub fn to(self, other: Span) -> Span {
+    Span {
+      file: self.file,
+      start: self.start.min(other.start),
+      end: self.end.max(other.end)
+    }
+  }
+
+  pub fn len(&self) -> usize {
+    self.end - self.start
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.start == self.end
+  }
+}
+
+// Where a node came from is not part of what it means, so spans
+// never contribute to the hash of the node that carries them.
+impl CirceHash for Span {
+  fn hash(&self) -> u64 {
+    0
+  }
+}
+
+/// A stream of characters decoded from any buffered reader.
+///
+/// Input is read and decoded a chunk at a time, so only the lookahead
+/// that has been asked for is ever held in memory, along with anything
+/// consumed since the oldest outstanding [`Mark`]. If reading fails, the
+/// stream ends at that point and the error can be collected with
+/// [`InputStream::take_error`].
 pub struct InputStream<'s> {
-  pub(crate) data: &'s str,
+  pub(crate) reader: Box<dyn BufRead + 's>,
+  pub(crate) buffer: VecDeque<char>,
+  pub(crate) cursor: usize,
+  pub(crate) base: usize,
+  pub(crate) marks: Vec<usize>,
+  pub(crate) partial: Vec<u8>,
+  pub(crate) error: Option<InputStreamError>,
+  pub(crate) eof: bool,
+  pub file: FileId,
+  /// The 1-based line of the next character.
   pub line: usize,
+  /// The 1-based column of the next character, counted in characters.
   pub column: usize,
+  /// The 1-based column of the next character, counted in UTF-16 code
+  /// units, as editors using the Language Server Protocol expect.
+  pub utf16_column: usize,
+  /// The byte offset of the next character.
   pub pos: usize
 }
 
+/// A saved position in an [`InputStream`] that it can be
+/// [reset](InputStream::reset) to, for lexers that need to backtrack.
+///
+/// Every mark must be given back with either [`InputStream::reset`] or
+/// [`InputStream::release`], since the stream keeps everything read
+/// after it until then.
+#[derive(Debug, PartialEq, Eq)]
+pub struct Mark {
+  index: usize,
+  line: usize,
+  column: usize,
+  utf16_column: usize,
+  pos: usize
+}
+
 #[derive(Error, Debug)]
 pub enum InputStreamError {
   #[error("Failed to read from stream")]
-  ReadError(#[from] std::io::Error)
+  ReadError(#[from] std::io::Error),
+  #[error("Stream is not valid UTF-8")]
+  InvalidUtf8
 }
 
 impl<'s> InputStream<'s> {
   pub fn new(data: &'s str) -> Self {
+    InputStream::with_file(data, FileId::default())
+  }
+
+  pub fn with_file(data: &'s str, file: FileId) -> Self {
+    InputStream::from_reader_with_file(data.as_bytes(), file)
+  }
+
+  /// Creates a stream over a file, stdin, a pipe or anything else that
+  /// can be read from.
+  pub fn from_reader(reader: impl BufRead + 's) -> Self {
+    InputStream::from_reader_with_file(reader, FileId::default())
+  }
+
+  pub fn from_reader_with_file(reader: impl BufRead + 's, file: FileId) -> Self {
     InputStream {
-      data,
+      reader: Box::new(reader),
+      buffer: VecDeque::new(),
+      cursor: 0,
+      base: 0,
+      marks: Vec::new(),
+      partial: Vec::new(),
+      error: None,
+      eof: false,
+      file,
       line: 1,
       column: 1,
+      utf16_column: 1,
       pos: 0
     }
   }
 
-  pub fn peek(&self) -> Option<char> {
-    self.data.chars().next()
+  pub fn peek(&mut self) -> Option<char> {
+    self.peek_n(0)
+  }
+
+  pub fn peek_n(&mut self, n: usize) -> Option<char> {
+    while self.buffer.len() <= self.cursor + n && !self.eof {
+      self.fill();
+    }
+
+    self.buffer.get(self.cursor + n).copied()
+  }
+
+  /// Returns the error that ended the stream, once every character read
+  /// before it has been consumed.
+  pub fn take_error(&mut self) -> Option<InputStreamError> {
+    if self.cursor >= self.buffer.len() {
+      self.error.take()
+    } else {
+      None
+    }
+  }
+
+  /// Saves the current position.
+  pub fn mark(&mut self) -> Mark {
+    let index: usize = self.base + self.cursor;
+    self.marks.push(index);
+
+    Mark {
+      index,
+      line: self.line,
+      column: self.column,
+      utf16_column: self.utf16_column,
+      pos: self.pos
+    }
+  }
+
+  /// Goes back to a saved position, so everything read since will be
+  /// read again.
+  pub fn reset(&mut self, mark: Mark) {
+    self.cursor = mark.index - self.base;
+    self.line = mark.line;
+    self.column = mark.column;
+    self.utf16_column = mark.utf16_column;
+    self.pos = mark.pos;
+
+    self.release(mark);
+  }
+
+  /// Gives up a saved position without going back to it.
+  pub fn release(&mut self, mark: Mark) {
+    if let Some(i) = self.marks.iter().position(|index| *index == mark.index) {
+      self.marks.swap_remove(i);
+    }
+
+    self.trim();
+  }
+
+  /// Drops consumed characters that no mark can go back to.
+  fn trim(&mut self) {
+    let keep: usize = self.marks.iter().min().map_or(self.cursor, |index| index - self.base);
+
+    self.buffer.drain(..keep);
+    self.base += keep;
+    self.cursor -= keep;
   }
 
-  pub fn peek_n(&self, n: usize) -> Option<char> {
-    self.data.chars().nth(n)
+  /// Decodes the next chunk of input into the lookahead.
+  fn fill(&mut self) {
+    let chunk: &[u8] = match self.reader.fill_buf() {
+      Ok(chunk) => chunk,
+      Err(err) if err.kind() == std::io::ErrorKind::Interrupted => {
+        return;
+      },
+      Err(err) => {
+        self.fail(err.into());
+        return;
+      }
+    };
+
+    if chunk.is_empty() {
+      if !self.partial.is_empty() {
+        self.fail(InputStreamError::InvalidUtf8);
+      }
+
+      self.eof = true;
+      return;
+    }
+
+    // A character may be split across two chunks, in which case its
+    // first bytes wait in `partial` for the rest of it.
+    let mut bytes: Vec<u8> = std::mem::take(&mut self.partial);
+    bytes.extend_from_slice(chunk);
+
+    let length: usize = chunk.len();
+    self.reader.consume(length);
+
+    match std::str::from_utf8(&bytes) {
+      Ok(text) => {
+        self.buffer.extend(text.chars());
+      },
+      Err(err) => {
+        let valid: usize = err.valid_up_to();
+        let text: &str = std::str::from_utf8(&bytes[..valid]).unwrap_or_default();
+        self.buffer.extend(text.chars());
+
+        match err.error_len() {
+          None => self.partial = bytes[valid..].to_vec(),
+          Some(_) => self.fail(InputStreamError::InvalidUtf8)
+        }
+      }
+    }
+  }
+
+  fn fail(&mut self, error: InputStreamError) {
+    self.error = Some(error);
+    self.partial.clear();
+    self.eof = true;
   }
 }
 
@@ -59,16 +282,23 @@ impl<'s> Iterator for InputStream<'s> {
   fn next(&mut self) -> Option<Self::Item> {
     let c = self.peek()?;
 
-    self.pos += 1;
+    if self.marks.is_empty() {
+      self.buffer.pop_front();
+      self.base += 1;
+    } else {
+      self.cursor += 1;
+    }
+
+    self.pos += c.len_utf8();
     self.column += 1;
+    self.utf16_column += c.len_utf16();
 
     if c == '\n' {
       self.line += 1;
       self.column = 1;
+      self.utf16_column = 1;
     }
 
-    self.data = &self.data[c.len_utf8()..];
-
     Some(c)
   }
 }
@@ -0,0 +1,254 @@
+/*
+
+Copyright (C) 2023 Carlos Kieliszewski
+
+This file is part of the Circe Project.
+
+Circe is free software: you can redistribute it and/or modify it under
+the terms of the GNU General Public License as published by the Free
+Software Foundation, either version 3 of the License, or (at your option)
+any later version.
+
+Circe is distributed in the hope that it will be useful, but WITHOUT ANY
+WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+FOR A PARTICULAR PURPOSE. See the GNU General Public License for more details.
+
+You should have received a copy of the GNU General Public License along with
+Circe. If not, see <https://www.gnu.org/licenses/>. 
+
+*/
+
+
+use std::cell::OnceCell;
+use std::fs::{self, File};
+use std::io::{self, BufReader};
+use std::path::{Path, PathBuf};
+
+use crate::{FileId, InputStream, Span};
+
+/// Where the text of a [`SourceFile`] comes from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FileName {
+  /// A file on disk, which is streamed when it is lexed and only read
+  /// whole if something needs to show its lines.
+  Real(PathBuf),
+  /// Text that doesn't live in a file, like stdin, REPL input or a
+  /// generated prelude.
+  Virtual(String)
+}
+
+/// A file known to a [`SourceMap`], placed at `start..start + len` in
+/// the map's global offsets.
+#[derive(Debug)]
+pub struct SourceFile {
+  pub id: FileId,
+  pub name: FileName,
+  pub start: usize,
+  pub len: usize,
+  pub(crate) display: String,
+  pub(crate) source: OnceCell<Option<String>>,
+  pub(crate) line_starts: OnceCell<Vec<usize>>
+}
+
+/// A resolved position: 1-based line, and 1-based column counted in
+/// characters, the same as [`InputStream::line`] and [`InputStream::column`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Location {
+  pub file: FileId,
+  pub line: usize,
+  pub column: usize
+}
+
+impl SourceFile {
+  /// A file of text already in memory, outside of any [`SourceMap`].
+  pub fn new_virtual(id: FileId, name: impl Into<String>, source: impl Into<String>) -> Self {
+    let name: String = name.into();
+    let source: String = source.into();
+
+    SourceFile {
+      id,
+      name: FileName::Virtual(name.clone()),
+      start: 0,
+      len: source.len(),
+      display: name,
+      source: OnceCell::from(Some(source)),
+      line_starts: OnceCell::new()
+    }
+  }
+
+  /// The name to show for this file in messages.
+  pub fn display_name(&self) -> &str {
+    &self.display
+  }
+
+  /// The whole text of the file, if it can be read.
+  pub fn source(&self) -> Option<&str> {
+    self.source.get_or_init(|| match &self.name {
+      FileName::Real(path) => fs::read_to_string(path).ok(),
+      FileName::Virtual(_) => None
+    }).as_deref()
+  }
+
+  /// Resolves a byte offset within this file.
+  pub fn location(&self, offset: usize) -> Option<Location> {
+    let source: &str = self.source()?;
+    let offset: usize = offset.min(source.len());
+    let line_starts: &[usize] = self.line_starts(source);
+
+    let line: usize = line_starts.partition_point(|start| *start <= offset) - 1;
+    let text: &str = source.get(line_starts[line]..offset)?;
+
+    Some(Location {
+      file: self.id,
+      line: line + 1,
+      column: text.chars().count() + 1
+    })
+  }
+
+  /// The text of a 1-based line, without its line ending.
+  pub fn line(&self, line: usize) -> Option<&str> {
+    let source: &str = self.source()?;
+    let line_starts: &[usize] = self.line_starts(source);
+
+    let start: usize = *line_starts.get(line.checked_sub(1)?)?;
+    let end: usize = line_starts.get(line).map_or(source.len(), |next| next - 1);
+    let text: &str = &source[start..end];
+
+    Some(text.strip_suffix('\r').unwrap_or(text))
+  }
+
+  fn line_starts(&self, source: &str) -> &[usize] {
+    self.line_starts.get_or_init(|| {
+      std::iter::once(0)
+        .chain(source.match_indices('\n').map(|(i, _)| i + 1))
+        .collect()
+    })
+  }
+}
+
+/// Every source file taking part in a compilation.
+///
+/// Each file is given a [`FileId`], which is what a [`Span`] uses to
+/// point into it, and a range of global offsets that no other file
+/// shares, so a single `usize` can also name any position in any file.
+#[derive(Debug, Default)]
+pub struct SourceMap {
+  files: Vec<SourceFile>
+}
+
+impl SourceMap {
+  pub fn new() -> Self {
+    SourceMap::default()
+  }
+
+  /// Adds a file on disk. Only its size is looked at until it is read.
+  pub fn add_file(&mut self, path: impl AsRef<Path>) -> io::Result<FileId> {
+    let path: &Path = path.as_ref();
+    let len: usize = fs::metadata(path)?.len() as usize;
+
+    Ok(self.push(
+      FileName::Real(path.to_path_buf()),
+      path.display().to_string(),
+      len,
+      OnceCell::new()
+    ))
+  }
+
+  /// Adds text that isn't backed by a file, such as stdin.
+  pub fn add_virtual(&mut self, name: impl Into<String>, source: impl Into<String>) -> FileId {
+    let name: String = name.into();
+    let source: String = source.into();
+
+    self.push(
+      FileName::Virtual(name.clone()),
+      name,
+      source.len(),
+      OnceCell::from(Some(source))
+    )
+  }
+
+  fn push(&mut self, name: FileName, display: String, len: usize, source: OnceCell<Option<String>>) -> FileId {
+    let id: FileId = FileId(self.files.len());
+
+    // Leave a gap of one between files, so that the offset just past
+    // the end of one file is not also the start of the next.
+    let start: usize = self.files.last().map_or(0, |file| file.start + file.len + 1);
+
+    self.files.push(SourceFile {
+      id,
+      name,
+      start,
+      len,
+      display,
+      source,
+      line_starts: OnceCell::new()
+    });
+
+    id
+  }
+
+  pub fn file(&self, id: FileId) -> Option<&SourceFile> {
+    self.files.get(id.0)
+  }
+
+  pub fn files(&self) -> impl Iterator<Item = &SourceFile> {
+    self.files.iter()
+  }
+
+  /// Opens a file for lexing. Files on disk are streamed rather than
+  /// read whole.
+  pub fn stream(&self, id: FileId) -> io::Result<InputStream<'_>> {
+    let file: &SourceFile = self.file(id)
+      .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "unknown file"))?;
+
+    match (&file.name, file.source.get()) {
+      (_, Some(Some(source))) => Ok(InputStream::with_file(source, id)),
+      (FileName::Real(path), _) => {
+        let reader = BufReader::new(File::open(path)?);
+        Ok(InputStream::from_reader_with_file(reader, id))
+      },
+      (FileName::Virtual(_), _) => Ok(InputStream::with_file("", id))
+    }
+  }
+
+  /// Turns a position in a file into a global offset.
+  pub fn global_offset(&self, file: FileId, offset: usize) -> Option<usize> {
+    self.file(file).map(|file| file.start + offset.min(file.len))
+  }
+
+  /// Finds the file and byte offset within it of a global offset.
+  pub fn split_offset(&self, global: usize) -> Option<(FileId, usize)> {
+    let index: usize = self.files.partition_point(|file| file.start <= global).checked_sub(1)?;
+    let file: &SourceFile = &self.files[index];
+
+    if global > file.start + file.len {
+      return None;
+    }
+
+    Some((file.id, global - file.start))
+  }
+
+  /// Resolves a global offset to a file, line and column.
+  pub fn lookup(&self, global: usize) -> Option<Location> {
+    let (file, offset) = self.split_offset(global)?;
+    self.location(file, offset)
+  }
+
+  /// Resolves a byte offset within a file to a line and column.
+  pub fn location(&self, file: FileId, offset: usize) -> Option<Location> {
+    self.file(file)?.location(offset)
+  }
+
+  /// The text a span covers.
+  pub fn span_text(&self, span: Span) -> Option<&str> {
+    self.file(span.file)?.source()?.get(span.start..span.end)
+  }
+
+  pub fn name(&self, file: FileId) -> Option<&str> {
+    self.file(file).map(SourceFile::display_name)
+  }
+
+  pub fn source(&self, file: FileId) -> Option<&str> {
+    self.file(file)?.source()
+  }
+}
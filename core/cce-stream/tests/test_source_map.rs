@@ -0,0 +1,107 @@
+/*
+
+Copyright (C) 2023 Carlos Kieliszewski
+
+This file is part of the Circe Project.
+
+Circe is free software: you can redistribute it and/or modify it under
+the terms of the GNU General Public License as published by the Free
+Software Foundation, either version 3 of the License, or (at your option)
+any later version.
+
+Circe is distributed in the hope that it will be useful, but WITHOUT ANY
+WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+FOR A PARTICULAR PURPOSE. See the GNU General Public License for more details.
+
+You should have received a copy of the GNU General Public License along with
+Circe. If not, see <https://www.gnu.org/licenses/>. 
+
+*/
+
+
+use cce_stream::{SourceMap, SourceFile, FileName, FileId, Location, Span};
+
+#[test]
+fn test_source_map_virtual() {
+  let mut sources = SourceMap::new();
+
+  let prelude: FileId = sources.add_virtual("<prelude>", "whatis a byte?\n- a number\n");
+  let repl: FileId = sources.add_virtual("<repl>", "say hi.");
+
+  assert_eq!(prelude, FileId(0));
+  assert_eq!(repl, FileId(1));
+
+  assert_eq!(sources.name(repl), Some("<repl>"));
+  assert_eq!(sources.source(prelude), Some("whatis a byte?\n- a number\n"));
+  assert_eq!(sources.file(repl).unwrap().name, FileName::Virtual("<repl>".to_string()));
+  assert_eq!(sources.span_text(Span::new(prelude, 17, 25)), Some("a number"));
+}
+
+#[test]
+fn test_source_file_lines() {
+  let file = SourceFile::new_virtual(FileId(3), "café.cce", "say 'café'\r\nto olé.\n");
+
+  assert_eq!(file.display_name(), "café.cce");
+  assert_eq!(file.line(1), Some("say 'café'"));
+  assert_eq!(file.line(2), Some("to olé."));
+  assert_eq!(file.line(3), Some(""));
+  assert_eq!(file.line(0), None);
+  assert_eq!(file.line(4), None);
+
+  // Columns count characters, not bytes.
+  assert_eq!(file.location(20), Some(Location { file: FileId(3), line: 2, column: 7 }));
+}
+
+#[test]
+fn test_source_map_global_offsets() {
+  let mut sources = SourceMap::new();
+
+  let first: FileId = sources.add_virtual("first", "ab\ncd");
+  let second: FileId = sources.add_virtual("second", "héllo\nworld");
+
+  assert_eq!(sources.global_offset(first, 4), Some(4));
+  assert_eq!(sources.global_offset(second, 0), Some(6));
+
+  assert_eq!(sources.split_offset(5), Some((first, 5)));
+  assert_eq!(sources.split_offset(6), Some((second, 0)));
+  assert_eq!(sources.split_offset(100), None);
+
+  assert_eq!(sources.lookup(4), Some(Location { file: first, line: 2, column: 2 }));
+  assert_eq!(sources.lookup(6 + 3), Some(Location { file: second, line: 1, column: 3 }));
+  assert_eq!(sources.lookup(6 + 8), Some(Location { file: second, line: 2, column: 2 }));
+}
+
+#[test]
+fn test_source_map_stream() {
+  let mut sources = SourceMap::new();
+
+  sources.add_virtual("<stdin>", "first");
+  let repl: FileId = sources.add_virtual("<repl>", "second");
+
+  let mut stream = sources.stream(repl).unwrap();
+  assert_eq!(stream.file, repl);
+  assert_eq!(stream.next(), Some('s'));
+
+  assert!(sources.stream(FileId(7)).is_err());
+}
+
+#[test]
+fn test_source_map_real_file() {
+  let path = std::env::temp_dir().join(format!("cce-source-map-{}.cce", std::process::id()));
+  std::fs::write(&path, "say hi.\nsay bye.\n").unwrap();
+
+  let mut sources = SourceMap::new();
+  sources.add_virtual("<prelude>", "");
+  let file: FileId = sources.add_file(&path).unwrap();
+
+  let text: String = sources.stream(file).unwrap().collect();
+  assert_eq!(text, "say hi.\nsay bye.\n");
+
+  assert_eq!(sources.global_offset(file, 0), Some(1));
+  assert_eq!(sources.lookup(1 + 12), Some(Location { file, line: 2, column: 5 }));
+  assert_eq!(sources.name(file), Some(path.display().to_string().as_str()));
+
+  std::fs::remove_file(&path).unwrap();
+
+  assert!(sources.add_file(&path).is_err());
+}
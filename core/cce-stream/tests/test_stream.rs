@@ -19,7 +19,8 @@ Circe. If not, see <https://www.gnu.org/licenses/>.
 */
 
 
-use cce_stream::InputStream;
+use cce_stream::{InputStream, InputStreamError, Span, FileId};
+use std::io::{self, BufReader, Read};
 
 #[test]
 fn test_input_stream() {
@@ -50,4 +51,132 @@ fn test_input_stream_none() {
 
   let next_char: Option<char> = stream.next();
   assert_eq!(next_char, None);
-}
\ No newline at end of file
+}
+
+#[test]
+fn test_span_to() {
+  let first = Span::new(FileId(0), 4, 9);
+  let second = Span::new(FileId(0), 12, 15);
+
+  assert_eq!(first.to(second), Span::new(FileId(0), 4, 15));
+  assert_eq!(second.to(first), Span::new(FileId(0), 4, 15));
+  assert_eq!(first.to(second).len(), 11);
+}
+
+/// Hands out its data, then fails every read after that.
+struct FailingReader<'d> {
+  data: &'d [u8]
+}
+
+impl<'d> Read for FailingReader<'d> {
+  fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+    if self.data.is_empty() {
+      return Err(io::Error::other("disk on fire"));
+    }
+
+    let length: usize = self.data.len().min(buf.len());
+    buf[..length].copy_from_slice(&self.data[..length]);
+    self.data = &self.data[length..];
+
+    Ok(length)
+  }
+}
+
+#[test]
+fn test_input_stream_reader() {
+  // A one byte buffer splits every multi-byte character across reads.
+  let reader = BufReader::with_capacity(1, "héllo 🌍!".as_bytes());
+  let mut stream = InputStream::from_reader(reader);
+
+  assert_eq!(stream.peek_n(6), Some('🌍'));
+  assert_eq!(stream.peek_n(8), None);
+
+  let text: String = stream.by_ref().collect();
+  assert_eq!(text, "héllo 🌍!");
+  assert_eq!(stream.pos, 12);
+  assert_eq!(stream.column, 9);
+  assert!(stream.take_error().is_none());
+}
+
+#[test]
+fn test_input_stream_read_error() {
+  let reader = BufReader::with_capacity(2, FailingReader { data: b"abc" });
+  let mut stream = InputStream::from_reader(reader);
+
+  assert_eq!(stream.peek_n(2), Some('c'));
+  assert!(stream.take_error().is_none());
+
+  let text: String = stream.by_ref().collect();
+  assert_eq!(text, "abc");
+
+  match stream.take_error() {
+    Some(InputStreamError::ReadError(err)) => assert_eq!(err.to_string(), "disk on fire"),
+    other => panic!("Expected a read error, got {:?}", other)
+  }
+}
+
+#[test]
+fn test_input_stream_invalid_utf8() {
+  let mut stream = InputStream::from_reader(&b"ok\xffno"[..]);
+
+  assert_eq!(stream.next(), Some('o'));
+  assert_eq!(stream.next(), Some('k'));
+  assert_eq!(stream.next(), None);
+
+  assert!(matches!(stream.take_error(), Some(InputStreamError::InvalidUtf8)));
+}
+
+#[test]
+fn test_input_stream_truncated_utf8() {
+  let mut stream = InputStream::from_reader(&"é".as_bytes()[..1]);
+
+  assert_eq!(stream.next(), None);
+  assert!(matches!(stream.take_error(), Some(InputStreamError::InvalidUtf8)));
+}
+
+#[test]
+fn test_input_stream_positions() {
+  let mut stream = InputStream::new("é🌍x\ny");
+
+  assert_eq!((stream.pos, stream.column, stream.utf16_column), (0, 1, 1));
+
+  stream.next();
+  assert_eq!((stream.pos, stream.column, stream.utf16_column), (2, 2, 2));
+
+  stream.next();
+  assert_eq!((stream.pos, stream.column, stream.utf16_column), (6, 3, 4));
+
+  stream.next();
+  stream.next();
+  assert_eq!((stream.pos, stream.line, stream.column, stream.utf16_column), (8, 2, 1, 1));
+}
+
+#[test]
+fn test_input_stream_mark_reset() {
+  let reader = BufReader::with_capacity(1, "abcdef".as_bytes());
+  let mut stream = InputStream::from_reader(reader);
+
+  stream.next();
+  let outer = stream.mark();
+
+  stream.next();
+  let inner = stream.mark();
+
+  assert_eq!(stream.next(), Some('c'));
+  assert_eq!(stream.next(), Some('d'));
+
+  stream.reset(inner);
+  assert_eq!(stream.pos, 2);
+  assert_eq!(stream.next(), Some('c'));
+
+  stream.reset(outer);
+  assert_eq!((stream.pos, stream.column), (1, 2));
+  assert_eq!(stream.peek_n(4), Some('f'));
+
+  let mark = stream.mark();
+  assert_eq!(stream.next(), Some('b'));
+  stream.release(mark);
+
+  let text: String = stream.collect();
+  assert_eq!(text, "cdef");
+}
@@ -22,46 +22,147 @@ Circe. If not, see <https://www.gnu.org/licenses/>.
 use cce_ast as ast;
 use crate::nodes::*;
 
+/// Imports are left out, as they should already have been replaced by
+/// what they import, by a `cce_ast::Loader`.
 pub fn convert(program: Vec<ast::ParseNode>) -> Vec<ProgramNode> {
-  program.into_iter().map(|node| match node {
-    ast::ParseNode::Command(command) => ProgramNode::Command(convert_command(command)),
-    ast::ParseNode::HowToStatement(howto) => ProgramNode::HowTo(convert_howto(howto)),
-    ast::ParseNode::WhatIsStatement(whatis) => ProgramNode::WhatIs(convert_whatis(whatis))
+  program.into_iter().filter_map(|node| match node {
+    ast::ParseNode::Command(command) => Some(ProgramNode::Command(convert_command(command))),
+    ast::ParseNode::Conditional(conditional) => Some(ProgramNode::Conditional(convert_conditional(conditional))),
+    ast::ParseNode::Loop(repeat) => Some(ProgramNode::Loop(convert_loop(repeat))),
+    ast::ParseNode::Binding(binding) => Some(ProgramNode::Binding(convert_binding(binding))),
+    ast::ParseNode::Module(module) => Some(ProgramNode::Module(convert_module(module))),
+    ast::ParseNode::Qualified(qualified) => Some(ProgramNode::Qualified(convert_qualified(qualified))),
+    ast::ParseNode::Query(query) => Some(ProgramNode::Query(convert_query(query))),
+    ast::ParseNode::Import(_) => None,
+    ast::ParseNode::HowToStatement(howto) => Some(ProgramNode::HowTo(convert_howto(howto))),
+    ast::ParseNode::WhatIsStatement(whatis) => Some(ProgramNode::WhatIs(convert_whatis(whatis)))
   }).collect()
 }
 
 fn convert_command(command: ast::Command) -> CommandNode {
   CommandNode {
     command: command.components.into_iter().map(convert_command_component).collect(),
-    modifiers: command.modifiers.into_iter().map(|modifier| modifier.into_iter().map(convert_command_component).collect()).collect()
+    modifiers: command.modifiers.into_iter().map(|modifier| modifier.into_iter().map(convert_command_component).collect()).collect(),
+    children: command.children.into_iter().map(convert_command).collect(),
+    span: command.span
   }
 }
 
 fn convert_command_component(component: ast::CommandComponent) -> CommandComponent {
   match component {
-    ast::CommandComponent::Literal(literal) => CommandComponent::Literal(literal),
-    ast::CommandComponent::Keyword(keyword) => CommandComponent::Keyword(keyword),
-    ast::CommandComponent::Slot(slot) => CommandComponent::Slot(slot)
+    ast::CommandComponent::Literal(literal, span) => CommandComponent::Literal(literal, span),
+    ast::CommandComponent::Number(number, span) => CommandComponent::Number(number, span),
+    ast::CommandComponent::Keyword(keyword, span) => CommandComponent::Keyword(keyword, span),
+    ast::CommandComponent::Slot(slot, span) => CommandComponent::Slot(convert_slot(slot), span)
+  }
+}
+
+fn convert_slot(slot: ast::Slot) -> Slot {
+  Slot {
+    name: slot.name,
+    kind: slot.kind.map(|kind| kind.into_iter().map(convert_command_component).collect())
+  }
+}
+
+fn convert_visibility(visibility: ast::Visibility) -> Visibility {
+  match visibility {
+    ast::Visibility::Public => Visibility::Public,
+    ast::Visibility::Private => Visibility::Private
   }
 }
 
 fn convert_howto(howto: ast::HowToStatement) -> HowToNode {
   HowToNode {
+    visibility: convert_visibility(howto.visibility),
     signature: howto.signature.into_iter().map(convert_command_component).collect(),
-    body: howto.body.into_iter().map(convert_howto_command).collect()
+    body: howto.body.into_iter().map(convert_howto_command).collect(),
+    span: howto.span
   }
 }
 
 fn convert_howto_command(command: ast::HowToCommand) -> HowToCommand {
   match command {
     ast::HowToCommand::HighLevel(command) => HowToCommand::HighLevel(convert_command(command)),
-    ast::HowToCommand::LowLevel(lowlevel) => HowToCommand::LowLevel(lowlevel)
+    ast::HowToCommand::LowLevel(lowlevel, span) => HowToCommand::LowLevel(lowlevel, span),
+    ast::HowToCommand::Primitive(steps, span) => HowToCommand::Primitive(steps.into_iter().map(convert_command).collect(), span),
+    ast::HowToCommand::Conditional(conditional) => HowToCommand::Conditional(convert_conditional(conditional)),
+    ast::HowToCommand::Loop(repeat) => HowToCommand::Loop(convert_loop(repeat)),
+    ast::HowToCommand::Binding(binding) => HowToCommand::Binding(convert_binding(binding)),
+    ast::HowToCommand::Qualified(qualified) => HowToCommand::Qualified(convert_qualified(qualified))
+  }
+}
+
+fn convert_conditional(conditional: ast::Conditional) -> ConditionalNode {
+  ConditionalNode {
+    condition: conditional.condition.into_iter().map(convert_command_component).collect(),
+    then: conditional.then.into_iter().map(convert_howto_command).collect(),
+    otherwise: conditional.otherwise.into_iter().map(convert_howto_command).collect(),
+    span: conditional.span
+  }
+}
+
+fn convert_loop(repeat: ast::Loop) -> LoopNode {
+  let convert_components = |components: Vec<ast::CommandComponent>| -> Vec<CommandComponent> {
+    components.into_iter().map(convert_command_component).collect()
+  };
+
+  let kind: LoopKind = match repeat.kind {
+    ast::LoopKind::ForEach { item, item_span, collection } => {
+      LoopKind::ForEach { item: convert_slot(item), item_span, collection: convert_components(collection) }
+    },
+    ast::LoopKind::Repeat { count } => LoopKind::Repeat { count: convert_components(count) },
+    ast::LoopKind::While { condition } => LoopKind::While { condition: convert_components(condition) }
+  };
+
+  LoopNode {
+    kind,
+    body: repeat.body.into_iter().map(convert_howto_command).collect(),
+    span: repeat.span
+  }
+}
+
+fn convert_binding(binding: ast::Binding) -> BindingNode {
+  BindingNode {
+    command: convert_command(binding.command),
+    name: binding.name,
+    name_span: binding.name_span,
+    span: binding.span
+  }
+}
+
+fn convert_module(module: ast::Module) -> ModuleNode {
+  ModuleNode {
+    name: module.name,
+    name_span: module.name_span,
+    span: module.span
+  }
+}
+
+fn convert_qualified(qualified: ast::Qualified) -> QualifiedNode {
+  QualifiedNode {
+    module: qualified.module,
+    module_span: qualified.module_span,
+    command: convert_command(qualified.command),
+    span: qualified.span
+  }
+}
+
+fn convert_query(query: ast::Query) -> QueryNode {
+  QueryNode {
+    kind: match query.kind {
+      ast::QueryKind::HowTo => QueryKind::HowTo,
+      ast::QueryKind::WhatIs => QueryKind::WhatIs
+    },
+    subject: query.subject.into_iter().map(convert_command_component).collect(),
+    span: query.span
   }
 }
 
 fn convert_whatis(whatis: ast::WhatIsStatement) -> WhatIsNode {
   WhatIsNode {
+    visibility: convert_visibility(whatis.visibility),
     signature: whatis.signature.into_iter().map(convert_command_component).collect(),
-    body: whatis.body.into_iter().map(convert_command).collect()
+    body: whatis.body.into_iter().map(convert_command).collect(),
+    span: whatis.span
   }
 }
\ No newline at end of file
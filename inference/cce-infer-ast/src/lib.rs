@@ -21,6 +21,12 @@ Circe. If not, see <https://www.gnu.org/licenses/>.
 
 pub mod nodes;
 mod convert;
+mod resolve;
+mod namespace;
+mod signature;
 
 pub use nodes::*;
-pub use convert::convert;
\ No newline at end of file
+pub use convert::convert;
+pub use resolve::{resolve, NameError};
+pub use namespace::{check_namespaces, Namespaces, NamespaceError};
+pub use signature::{fill_slots, same_word, Bindings};
\ No newline at end of file
@@ -0,0 +1,475 @@
+/*
+
+Copyright (C) 2023 Carlos Kieliszewski
+
+This file is part of the Circe Project.
+
+Circe is free software: you can redistribute it and/or modify it under
+the terms of the GNU General Public License as published by the Free
+Software Foundation, either version 3 of the License, or (at your option)
+any later version.
+
+Circe is distributed in the hope that it will be useful, but WITHOUT ANY
+WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+FOR A PARTICULAR PURPOSE. See the GNU General Public License for more details.
+
+You should have received a copy of the GNU General Public License along with
+Circe. If not, see <https://www.gnu.org/licenses/>. 
+
+*/
+
+
+use std::collections::HashMap;
+
+use cce_ast::Lexicon;
+use cce_diagnostics::{Diagnostic, Files, Label, ToDiagnostic};
+use cce_stream::{FileId, Span};
+use thiserror::Error;
+
+use crate::nodes::*;
+use crate::signature::fill_slots;
+
+
+#[derive(Debug, Clone, PartialEq, Error)]
+pub enum NamespaceError {
+  #[error("the module header must come first in its file")]
+  LateHeader {
+    span: Span,
+    first: Span
+  },
+  #[error("this file is already in module '{previous_name}'")]
+  DuplicateHeader {
+    previous_name: String,
+    span: Span,
+    previous: Span
+  },
+  #[error("'{kind} {signature}?' is defined more than once")]
+  DuplicateDescription {
+    kind: &'static str,
+    signature: String,
+    span: Span,
+    previous: Span,
+    /// The name of the file of the first definition, if it's another.
+    previous_file: Option<String>
+  },
+  #[error("there is no module named '{name}'")]
+  UnknownModule {
+    name: String,
+    span: Span
+  },
+  #[error("'{kind} {signature}?' is private to {file}")]
+  PrivateDescription {
+    kind: &'static str,
+    signature: String,
+    span: Span,
+    definition: Span,
+    file: String
+  },
+  #[error("'{command}' matches descriptions in more than one module")]
+  AmbiguousReference {
+    command: String,
+    span: Span,
+    /// The module of each matching description, if it has one, and
+    /// where the description is.
+    candidates: Vec<(Option<String>, Span)>
+  }
+}
+
+impl ToDiagnostic for NamespaceError {
+  fn to_diagnostic(&self) -> Diagnostic {
+    match self {
+      NamespaceError::LateHeader { span, first } => {
+        Diagnostic::error("the module header must come first in its file")
+          .with_code("E0400")
+          .with_label(Label::primary(*span, "this header"))
+          .with_label(Label::secondary(*first, "comes after this"))
+      },
+      NamespaceError::DuplicateHeader { previous_name, span, previous } => {
+        Diagnostic::error(format!("this file is already in module '{}'", previous_name))
+          .with_code("E0401")
+          .with_label(Label::primary(*span, "second module header"))
+          .with_label(Label::secondary(*previous, "first declared here"))
+          .with_note("a file can only be in one module")
+      },
+      NamespaceError::DuplicateDescription { kind, signature, span, previous, previous_file } => {
+        let diagnostic = Diagnostic::error(format!("'{} {}?' is defined more than once", kind, signature))
+          .with_code("E0402")
+          .with_label(Label::primary(*span, "defined again here"))
+          .with_label(Label::secondary(*previous, "first defined here"));
+
+        let diagnostic = match previous_file {
+          Some(file) => diagnostic.with_note(format!("the first definition is in {}", file)),
+          None => diagnostic
+        };
+
+        diagnostic.with_help("put one of them in a different module, or make them private to different files")
+      },
+      NamespaceError::UnknownModule { name, span } => {
+        Diagnostic::error(format!("there is no module named '{}'", name))
+          .with_code("E0403")
+          .with_label(Label::primary(*span, "not found"))
+          .with_help(format!("start a file with 'module {}.' to declare it", name))
+      },
+      NamespaceError::PrivateDescription { kind, signature, span, definition, file } => {
+        Diagnostic::error(format!("'{} {}?' is private to {}", kind, signature, file))
+          .with_code("E0404")
+          .with_label(Label::primary(*span, "used here"))
+          .with_label(Label::secondary(*definition, "defined as private here"))
+          .with_help(format!("remove 'private' from the {}, or describe this again in this file", kind))
+      },
+      NamespaceError::AmbiguousReference { command, span, candidates } => {
+        let diagnostic = Diagnostic::error(format!("'{}' matches descriptions in more than one module", command))
+          .with_code("E0405")
+          .with_label(Label::primary(*span, "used here"));
+
+        let diagnostic = candidates.iter().fold(diagnostic, |diagnostic, (module, definition)| {
+          let message: String = match module {
+            Some(module) => format!("matches this in module '{}'", module),
+            None => "matches this, which is in no module".to_string()
+          };
+
+          diagnostic.with_label(Label::secondary(*definition, message))
+        });
+
+        let module: &str = candidates.iter().find_map(|(module, _)| module.as_deref()).unwrap_or("<module>");
+        diagnostic.with_help(format!("say which one to use with 'within {}, {}'", module, command))
+      }
+    }
+  }
+}
+
+
+/// Which module each file of a program is in, as declared by a
+/// `module <name>.` header at its top. Files without a header are in
+/// no module.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Namespaces {
+  modules: HashMap<FileId, (String, Span)>
+}
+
+impl Namespaces {
+  /// Takes the first module header of each file, wherever it is.
+  pub fn new(program: &[ProgramNode]) -> Self {
+    let mut modules: HashMap<FileId, (String, Span)> = HashMap::new();
+
+    for node in program.iter() {
+      if let ProgramNode::Module(module) = node {
+        modules.entry(module.span.file).or_insert_with(|| (module.name.clone(), module.span));
+      }
+    }
+
+    Self { modules }
+  }
+
+  pub fn module_of(&self, file: FileId) -> Option<&str> {
+    self.modules.get(&file).map(|(name, _)| name.as_str())
+  }
+
+  pub fn has_module(&self, name: &str) -> bool {
+    self.modules.values().any(|(module, _)| module == name)
+  }
+
+  /// Whether a command in `from` can use a description defined at
+  /// `span`. Public descriptions can be used anywhere, private ones only
+  /// in their own file.
+  pub fn can_see(&self, from: FileId, visibility: Visibility, span: Span) -> bool {
+    visibility == Visibility::Public || span.file == from
+  }
+
+  /// Whether two descriptions could both be seen by the same command
+  /// without qualifying it: they are in the same module, and either are
+  /// in the same file or aren't both private.
+  fn overlap(&self, a: (Visibility, Span), b: (Visibility, Span)) -> bool {
+    let (a_visibility, a_span) = a;
+    let (b_visibility, b_span) = b;
+
+    self.module_of(a_span.file) == self.module_of(b_span.file)
+      && (a_span.file == b_span.file || a_visibility == Visibility::Public || b_visibility == Visibility::Public)
+  }
+}
+
+
+/// Checks the module headers of a program, that no two howto or whatis
+/// statements a command could see at once have the same signature, that
+/// every `within <module>` names a module, that no command or query only
+/// matches a description private to another file, and that no command
+/// without `within` matches howtos in more than one other module.
+/// `files` names the files that errors point into.
+pub fn check_namespaces(
+  program: &[ProgramNode],
+  lexicon: &Lexicon,
+  files: &dyn Files
+) -> (Namespaces, Vec<NamespaceError>) {
+  let namespaces: Namespaces = Namespaces::new(program);
+  let mut errors: Vec<NamespaceError> = Vec::new();
+
+  let mut firsts: HashMap<FileId, Span> = HashMap::new();
+  let mut headers: HashMap<FileId, &ModuleNode> = HashMap::new();
+
+  let mut descriptions: Descriptions = Descriptions { namespaces: &namespaces, lexicon, files, seen: HashMap::new() };
+
+  for node in program.iter() {
+    let first: Span = *firsts.entry(node.span().file).or_insert(node.span());
+
+    match node {
+      ProgramNode::Module(module) => {
+        if let Some(previous) = headers.get(&module.span.file) {
+          errors.push(NamespaceError::DuplicateHeader {
+            previous_name: previous.name.clone(),
+            span: module.span,
+            previous: previous.span
+          });
+        } else {
+          headers.insert(module.span.file, module);
+
+          if first != module.span {
+            errors.push(NamespaceError::LateHeader { span: module.span, first });
+          }
+        }
+      },
+      ProgramNode::HowTo(howto) => {
+        let found = (howto.visibility, signature_span(&howto.signature, howto.span));
+        descriptions.add("howto", &howto.signature, found, &mut errors);
+        qualified_steps(&namespaces, &howto.body, &mut errors);
+      },
+      ProgramNode::WhatIs(whatis) => {
+        let found = (whatis.visibility, signature_span(&whatis.signature, whatis.span));
+        descriptions.add("whatis", &whatis.signature, found, &mut errors);
+      },
+      ProgramNode::Qualified(qualified) => check_module(&namespaces, qualified, &mut errors),
+      ProgramNode::Conditional(conditional) => {
+        qualified_steps(&namespaces, &conditional.then, &mut errors);
+        qualified_steps(&namespaces, &conditional.otherwise, &mut errors);
+      },
+      ProgramNode::Loop(repeat) => qualified_steps(&namespaces, &repeat.body, &mut errors),
+      ProgramNode::Command(_) | ProgramNode::Binding(_) | ProgramNode::Query(_) => {}
+    }
+  }
+
+  let uses: Uses = Uses { namespaces: &namespaces, program, lexicon, files };
+  for node in program.iter() {
+    uses.node(node, &mut errors);
+  }
+
+  (namespaces, errors)
+}
+
+fn file_name(files: &dyn Files, file: FileId) -> String {
+  files.name(file).map_or_else(|| format!("file {}", file.0), str::to_string)
+}
+
+/// The howto and whatis statements seen so far, by kind and signature.
+struct Descriptions<'a> {
+  namespaces: &'a Namespaces,
+  lexicon: &'a Lexicon,
+  files: &'a dyn Files,
+  seen: HashMap<(&'static str, String), Vec<(Visibility, Span)>>
+}
+
+impl<'a> Descriptions<'a> {
+  fn add(
+    &mut self,
+    kind: &'static str,
+    signature: &[CommandComponent],
+    found: (Visibility, Span),
+    errors: &mut Vec<NamespaceError>
+  ) {
+    let seen: &mut Vec<(Visibility, Span)> = self.seen.entry((kind, signature_key(signature, self.lexicon))).or_default();
+
+    if let Some((_, previous)) = seen.iter().find(|previous| self.namespaces.overlap(**previous, found)) {
+      errors.push(NamespaceError::DuplicateDescription {
+        kind,
+        signature: phrase(signature),
+        span: found.1,
+        previous: *previous,
+        previous_file: (previous.file != found.1.file).then(|| file_name(self.files, previous.file))
+      });
+    }
+
+    seen.push(found);
+  }
+}
+
+fn check_module(namespaces: &Namespaces, qualified: &QualifiedNode, errors: &mut Vec<NamespaceError>) {
+  if !namespaces.has_module(&qualified.module) {
+    errors.push(NamespaceError::UnknownModule { name: qualified.module.clone(), span: qualified.module_span });
+  }
+}
+
+fn qualified_steps(namespaces: &Namespaces, steps: &[HowToCommand], errors: &mut Vec<NamespaceError>) {
+  for step in steps.iter() {
+    match step {
+      HowToCommand::Qualified(qualified) => check_module(namespaces, qualified, errors),
+      HowToCommand::Conditional(conditional) => {
+        qualified_steps(namespaces, &conditional.then, errors);
+        qualified_steps(namespaces, &conditional.otherwise, errors);
+      },
+      HowToCommand::Loop(repeat) => qualified_steps(namespaces, &repeat.body, errors),
+      HowToCommand::HighLevel(_) | HowToCommand::LowLevel(_, _) | HowToCommand::Primitive(_, _) | HowToCommand::Binding(_) => {}
+    }
+  }
+}
+
+/// Finds the commands and queries that only match a description private
+/// to another file, and the commands that match howtos in more than one
+/// other module and don't say which with `within`. Conditions and
+/// modifiers are commands too. Slot kinds aren't looked at, since only
+/// the matcher knows what each whatis statement describes, so a command
+/// that could match a description it can see is never reported private,
+/// and one whose slots would tell two modules' howtos apart is still
+/// reported ambiguous.
+struct Uses<'a> {
+  namespaces: &'a Namespaces,
+  program: &'a [ProgramNode],
+  lexicon: &'a Lexicon,
+  files: &'a dyn Files
+}
+
+impl<'a> Uses<'a> {
+  fn node(&self, node: &ProgramNode, errors: &mut Vec<NamespaceError>) {
+    match node {
+      ProgramNode::Command(command) => self.command(command, None, errors),
+      ProgramNode::Binding(binding) => self.command(&binding.command, None, errors),
+      ProgramNode::Qualified(qualified) => self.command(&qualified.command, Some(&qualified.module), errors),
+      ProgramNode::Conditional(conditional) => self.conditional(conditional, errors),
+      ProgramNode::Loop(repeat) => self.repeat(repeat, errors),
+      ProgramNode::HowTo(howto) => self.steps(&howto.body, errors),
+      ProgramNode::Query(query) => {
+        let kind: &'static str = match query.kind {
+          QueryKind::HowTo => "howto",
+          QueryKind::WhatIs => "whatis"
+        };
+
+        self.check(kind, &query.subject, query.span, None, errors);
+      },
+      ProgramNode::Module(_) | ProgramNode::WhatIs(_) => {}
+    }
+  }
+
+  fn steps(&self, steps: &[HowToCommand], errors: &mut Vec<NamespaceError>) {
+    for step in steps.iter() {
+      match step {
+        HowToCommand::HighLevel(command) => self.command(command, None, errors),
+        HowToCommand::Binding(binding) => self.command(&binding.command, None, errors),
+        HowToCommand::Qualified(qualified) => self.command(&qualified.command, Some(&qualified.module), errors),
+        HowToCommand::Conditional(conditional) => self.conditional(conditional, errors),
+        HowToCommand::Loop(repeat) => self.repeat(repeat, errors),
+        HowToCommand::LowLevel(_, _) | HowToCommand::Primitive(_, _) => {}
+      }
+    }
+  }
+
+  fn conditional(&self, conditional: &ConditionalNode, errors: &mut Vec<NamespaceError>) {
+    let span: Span = signature_span(&conditional.condition, conditional.span);
+    self.check("howto", &conditional.condition, span, None, errors);
+
+    self.steps(&conditional.then, errors);
+    self.steps(&conditional.otherwise, errors);
+  }
+
+  fn repeat(&self, repeat: &LoopNode, errors: &mut Vec<NamespaceError>) {
+    if let LoopKind::While { condition } = &repeat.kind {
+      self.check("howto", condition, signature_span(condition, repeat.span), None, errors);
+    }
+
+    self.steps(&repeat.body, errors);
+  }
+
+  fn command(&self, command: &CommandNode, within: Option<&str>, errors: &mut Vec<NamespaceError>) {
+    self.check("howto", &command.command, command.span, within, errors);
+
+    for modifier in command.modifiers.iter() {
+      self.check("howto", modifier, signature_span(modifier, command.span), None, errors);
+    }
+
+    for child in command.children.iter() {
+      self.command(child, None, errors);
+    }
+  }
+
+  fn check(
+    &self,
+    kind: &'static str,
+    command: &[CommandComponent],
+    span: Span,
+    within: Option<&str>,
+    errors: &mut Vec<NamespaceError>
+  ) {
+    let (visible, hidden): (Vec<Definition>, Vec<Definition>) = self.program.iter()
+      .filter_map(|node| match (kind, node) {
+        ("howto", ProgramNode::HowTo(howto)) => Some((howto.visibility, howto.signature.as_slice(), howto.span)),
+        ("whatis", ProgramNode::WhatIs(whatis)) => Some((whatis.visibility, whatis.signature.as_slice(), whatis.span)),
+        _ => None
+      })
+      .filter(|(_, _, definition)| within.is_none_or(|module| self.namespaces.module_of(definition.file) == Some(module)))
+      .filter(|(_, signature, _)| fill_slots(signature, command, self.lexicon, &mut Vec::new(), &mut |_, _| true))
+      .partition(|(visibility, _, definition)| self.namespaces.can_see(span.file, *visibility, *definition));
+
+    if let (None, Some((_, signature, definition))) = (visible.first(), hidden.first()) {
+      errors.push(NamespaceError::PrivateDescription {
+        kind,
+        signature: phrase(signature),
+        span,
+        definition: signature_span(signature, *definition),
+        file: file_name(self.files, definition.file)
+      });
+    }
+
+    if kind == "howto" && within.is_none() {
+      self.ambiguity(command, span, &visible, errors);
+    }
+  }
+
+  /// Reports a command that matches howtos in more than one module other
+  /// than its own, when none in its own module match it.
+  fn ambiguity(&self, command: &[CommandComponent], span: Span, matching: &[Definition], errors: &mut Vec<NamespaceError>) {
+    let own: Option<&str> = self.namespaces.module_of(span.file);
+
+    if matching.iter().any(|(_, _, definition)| self.namespaces.module_of(definition.file) == own) {
+      return;
+    }
+
+    let mut candidates: Vec<(Option<String>, Span)> = Vec::new();
+
+    for (_, signature, definition) in matching.iter() {
+      let module: Option<&str> = self.namespaces.module_of(definition.file);
+
+      if !candidates.iter().any(|(seen, _)| seen.as_deref() == module) {
+        candidates.push((module.map(str::to_string), signature_span(signature, *definition)));
+      }
+    }
+
+    if candidates.len() > 1 {
+      errors.push(NamespaceError::AmbiguousReference { command: phrase(command), span, candidates });
+    }
+  }
+}
+
+/// A description's visibility, signature and span.
+type Definition<'a> = (Visibility, &'a [CommandComponent], Span);
+
+/// Where a signature or phrase is, or `span` for an empty one.
+fn signature_span(signature: &[CommandComponent], span: Span) -> Span {
+  match (signature.first(), signature.last()) {
+    (Some(first), Some(last)) => first.span().to(last.span()),
+    _ => span
+  }
+}
+
+/// What makes two signatures match the same commands: the words, with
+/// every article the same, and the kinds of the slots but not their
+/// names.
+fn signature_key(signature: &[CommandComponent], lexicon: &Lexicon) -> String {
+  let parts: Vec<String> = signature.iter().map(|component| match component {
+    CommandComponent::Keyword(word, _) if lexicon.is_article(word) => "a".to_string(),
+    CommandComponent::Keyword(word, _) => word.clone(),
+    CommandComponent::Literal(literal, _) => format!("'{}'", literal),
+    CommandComponent::Number(number, _) => number.to_string(),
+    CommandComponent::Slot(slot, _) => match &slot.kind {
+      Some(kind) => format!("%({})", signature_key(kind, lexicon)),
+      None => "%".to_string()
+    }
+  }).collect();
+
+  parts.join(" ")
+}
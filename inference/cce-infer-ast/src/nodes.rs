@@ -18,42 +18,223 @@ Circe. If not, see <https://www.gnu.org/licenses/>.
 
 */
 
+use std::fmt;
+
+use cce_ast::{Lexicon, WordClass};
 use cce_llast::ast::*;
+use cce_stream::Span;
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum ProgramNode {
   Command(CommandNode),
+  Conditional(ConditionalNode),
+  Loop(LoopNode),
+  Binding(BindingNode),
+  Module(ModuleNode),
+  Qualified(QualifiedNode),
+  Query(QueryNode),
   HowTo(HowToNode),
   WhatIs(WhatIsNode)
 }
 
+impl ProgramNode {
+  pub fn span(&self) -> Span {
+    match self {
+      ProgramNode::Command(command) => command.span,
+      ProgramNode::Conditional(conditional) => conditional.span,
+      ProgramNode::Loop(repeat) => repeat.span,
+      ProgramNode::Binding(binding) => binding.span,
+      ProgramNode::Module(module) => module.span,
+      ProgramNode::Qualified(qualified) => qualified.span,
+      ProgramNode::Query(query) => query.span,
+      ProgramNode::HowTo(howto) => howto.span,
+      ProgramNode::WhatIs(whatis) => whatis.span
+    }
+  }
+}
+
 #[derive(Debug, Clone, PartialEq)]
 pub struct CommandNode {
   pub command: Vec<CommandComponent>,
-  pub modifiers: Vec<Vec<CommandComponent>>
+  pub modifiers: Vec<Vec<CommandComponent>>,
+  /// The bullets nested under this one in a howto or whatis body.
+  pub children: Vec<CommandNode>,
+  pub span: Span
+}
+
+/// Who can use a howto or whatis statement.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Visibility {
+  /// Any file that loads this one.
+  #[default]
+  Public,
+  /// Only the file it is in.
+  Private
 }
 
 #[derive(Debug, Clone, PartialEq)]
 pub struct HowToNode {
+  pub visibility: Visibility,
   pub signature: Vec<CommandComponent>,
-  pub body: Vec<HowToCommand>
+  pub body: Vec<HowToCommand>,
+  pub span: Span
 }
 
 #[derive(Debug, Clone, PartialEq)]
 pub struct WhatIsNode {
+  pub visibility: Visibility,
   pub signature: Vec<CommandComponent>,
-  pub body: Vec<CommandNode>
+  pub body: Vec<CommandNode>,
+  pub span: Span
 }
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum HowToCommand {
   HighLevel(CommandNode),
-  LowLevel(Vec<LLTopStatement>)
+  LowLevel(Vec<LLTopStatement>, Span),
+  Primitive(Vec<CommandNode>, Span),
+  Conditional(ConditionalNode),
+  Loop(LoopNode),
+  Binding(BindingNode),
+  Qualified(QualifiedNode)
+}
+
+/// Runs the `then` steps if the condition holds, and the `otherwise`
+/// steps, if there are any, when it doesn't.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConditionalNode {
+  pub condition: Vec<CommandComponent>,
+  pub then: Vec<HowToCommand>,
+  pub otherwise: Vec<HowToCommand>,
+  pub span: Span
+}
+
+/// Runs a command and names its result, so that the commands after it
+/// can refer to it as `%name`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BindingNode {
+  pub command: CommandNode,
+  pub name: String,
+  pub name_span: Span,
+  pub span: Span
+}
+
+/// Puts the howto and whatis statements of its file in a named module.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ModuleNode {
+  pub name: String,
+  pub name_span: Span,
+  pub span: Span
+}
+
+/// A command that only uses the howto and whatis statements of the
+/// given module.
+#[derive(Debug, Clone, PartialEq)]
+pub struct QualifiedNode {
+  pub module: String,
+  pub module_span: Span,
+  pub command: CommandNode,
+  pub span: Span
+}
+
+/// Asks how a command would be carried out, or what a phrase is.
+#[derive(Debug, Clone, PartialEq)]
+pub struct QueryNode {
+  pub kind: QueryKind,
+  pub subject: Vec<CommandComponent>,
+  pub span: Span
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum QueryKind {
+  HowTo,
+  WhatIs
+}
+
+/// Runs the body over and over, as set out by its kind.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LoopNode {
+  pub kind: LoopKind,
+  pub body: Vec<HowToCommand>,
+  pub span: Span
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum LoopKind {
+  /// Once for every item of the collection, with the slot standing for
+  /// the current one.
+  ForEach {
+    item: Slot,
+    item_span: Span,
+    collection: Vec<CommandComponent>
+  },
+  /// A number of times.
+  Repeat {
+    count: Vec<CommandComponent>
+  },
+  /// For as long as the condition holds.
+  While {
+    condition: Vec<CommandComponent>
+  }
+}
+
+/// A `%name` placeholder, and the kind of value it accepts, if any.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Slot {
+  pub name: String,
+  pub kind: Option<Vec<CommandComponent>>
 }
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum CommandComponent {
-  Literal(String),
-  Keyword(String),
-  Slot(String)
+  Literal(String, Span),
+  Number(u64, Span),
+  Keyword(String, Span),
+  Slot(Slot, Span)
+}
+
+impl CommandComponent {
+  pub fn span(&self) -> Span {
+    match self {
+      CommandComponent::Literal(_, span) => *span,
+      CommandComponent::Number(_, span) => *span,
+      CommandComponent::Keyword(_, span) => *span,
+      CommandComponent::Slot(_, span) => *span
+    }
+  }
+
+  /// The class of a word, or `None` for literals, numbers and slots.
+  pub fn word_class(&self, lexicon: &Lexicon) -> Option<WordClass> {
+    match self {
+      CommandComponent::Keyword(word, _) => Some(lexicon.classify(word)),
+      _ => None
+    }
+  }
+}
+
+/// Writes a component as it would be written in source, with a slot's
+/// kind after its name.
+impl fmt::Display for CommandComponent {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      CommandComponent::Literal(literal, _) => write!(f, "'{}'", literal),
+      CommandComponent::Number(number, _) => write!(f, "{}", number),
+      CommandComponent::Keyword(keyword, _) => write!(f, "{}", keyword),
+      CommandComponent::Slot(slot, _) => {
+        write!(f, "%{}", slot.name)?;
+
+        if let Some(kind) = &slot.kind {
+          write!(f, ": {}", phrase(kind))?;
+        }
+
+        Ok(())
+      }
+    }
+  }
+}
+
+/// Writes components as they would be written in source, separated by
+/// spaces.
+pub fn phrase(components: &[CommandComponent]) -> String {
+  components.iter().map(ToString::to_string).collect::<Vec<String>>().join(" ")
 }
\ No newline at end of file
@@ -0,0 +1,219 @@
+/*
+
+Copyright (C) 2023 Carlos Kieliszewski
+
+This file is part of the Circe Project.
+
+Circe is free software: you can redistribute it and/or modify it under
+the terms of the GNU General Public License as published by the Free
+Software Foundation, either version 3 of the License, or (at your option)
+any later version.
+
+Circe is distributed in the hope that it will be useful, but WITHOUT ANY
+WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+FOR A PARTICULAR PURPOSE. See the GNU General Public License for more details.
+
+You should have received a copy of the GNU General Public License along with
+Circe. If not, see <https://www.gnu.org/licenses/>. 
+
+*/
+
+
+use cce_diagnostics::{Diagnostic, Label, ToDiagnostic};
+use cce_stream::Span;
+use thiserror::Error;
+
+use crate::nodes::*;
+
+
+#[derive(Debug, Clone, PartialEq, Error)]
+pub enum NameError {
+  #[error("'%{name}' isn't defined")]
+  Undefined {
+    name: String,
+    span: Span
+  },
+  #[error("'{name}' shadows an earlier '%{name}'")]
+  Shadowed {
+    name: String,
+    span: Span,
+    previous: Span
+  }
+}
+
+impl ToDiagnostic for NameError {
+  fn to_diagnostic(&self) -> Diagnostic {
+    match self {
+      NameError::Undefined { name, span } => {
+        Diagnostic::error(format!("'%{}' isn't defined", name))
+          .with_code("E0200")
+          .with_label(Label::primary(*span, "not found in this scope"))
+          .with_help(format!("name something with 'and call it {}' before using it", name))
+      },
+      NameError::Shadowed { name, span, previous } => {
+        Diagnostic::warning(format!("'{}' shadows an earlier '%{}'", name, name))
+          .with_code("W0201")
+          .with_label(Label::primary(*span, "this hides the earlier one"))
+          .with_label(Label::secondary(*previous, "first defined here"))
+      }
+    }
+  }
+}
+
+/// Checks that every `%name` refers to something in scope, and warns
+/// about names that hide another.
+///
+/// A howto's or whatis's signature slots are in scope in its body, and
+/// a binding is in scope from the command after it to the end of the
+/// steps it is among. The steps of a branch or a loop are a scope of
+/// their own, which a `for each` item is also defined in. Howtos and
+/// whatis statements can't see the bindings around them.
+pub fn resolve(program: &[ProgramNode]) -> Vec<NameError> {
+  let mut resolver = Resolver { scopes: vec![Vec::new()], errors: Vec::new() };
+
+  for node in program.iter() {
+    resolver.node(node);
+  }
+
+  resolver.errors
+}
+
+struct Resolver {
+  /// The names defined in each enclosing scope, innermost last.
+  scopes: Vec<Vec<(String, Span)>>,
+  errors: Vec<NameError>
+}
+
+impl Resolver {
+  fn lookup(&self, name: &str) -> Option<Span> {
+    self.scopes.iter().rev()
+      .flat_map(|scope| scope.iter().rev())
+      .find(|(defined, _)| defined == name)
+      .map(|(_, span)| *span)
+  }
+
+  fn define(&mut self, name: &str, span: Span) {
+    if let Some(previous) = self.lookup(name) {
+      self.errors.push(NameError::Shadowed { name: name.to_string(), span, previous });
+    }
+
+    if let Some(scope) = self.scopes.last_mut() {
+      scope.push((name.to_string(), span));
+    }
+  }
+
+  /// Runs `f` in a scope nested in the current one.
+  fn nested(&mut self, f: impl FnOnce(&mut Self)) {
+    self.scopes.push(Vec::new());
+    f(self);
+    self.scopes.pop();
+  }
+
+  /// Runs `f` in a scope of its own, with only `signature`'s slots.
+  fn isolated(&mut self, signature: &[CommandComponent], f: impl FnOnce(&mut Self)) {
+    let outer: Vec<Vec<(String, Span)>> = std::mem::replace(&mut self.scopes, vec![Vec::new()]);
+
+    for component in signature.iter() {
+      if let CommandComponent::Slot(slot, span) = component {
+        self.define(&slot.name, *span);
+      }
+    }
+
+    f(self);
+    self.scopes = outer;
+  }
+
+  fn node(&mut self, node: &ProgramNode) {
+    match node {
+      ProgramNode::Command(command) => self.command(command),
+      ProgramNode::Conditional(conditional) => self.conditional(conditional),
+      ProgramNode::Loop(repeat) => self.repeat(repeat),
+      ProgramNode::Binding(binding) => self.binding(binding),
+      ProgramNode::Module(_) | ProgramNode::Query(_) => {},
+      ProgramNode::Qualified(qualified) => self.command(&qualified.command),
+      ProgramNode::HowTo(howto) => {
+        self.isolated(&howto.signature, |resolver| resolver.steps(&howto.body));
+      },
+      ProgramNode::WhatIs(whatis) => {
+        self.isolated(&whatis.signature, |resolver| {
+          for command in whatis.body.iter() {
+            resolver.command(command);
+          }
+        });
+      }
+    }
+  }
+
+  fn references(&mut self, components: &[CommandComponent]) {
+    for component in components.iter() {
+      if let CommandComponent::Slot(slot, span) = component {
+        if self.lookup(&slot.name).is_none() {
+          self.errors.push(NameError::Undefined { name: slot.name.clone(), span: *span });
+        }
+      }
+    }
+  }
+
+  fn command(&mut self, command: &CommandNode) {
+    self.references(&command.command);
+
+    for modifier in command.modifiers.iter() {
+      self.references(modifier);
+    }
+
+    for child in command.children.iter() {
+      self.command(child);
+    }
+  }
+
+  fn steps(&mut self, steps: &[HowToCommand]) {
+    for step in steps.iter() {
+      match step {
+        HowToCommand::HighLevel(command) => self.command(command),
+        HowToCommand::LowLevel(_, _) => {},
+        HowToCommand::Primitive(commands, _) => {
+          for command in commands.iter() {
+            self.command(command);
+          }
+        },
+        HowToCommand::Conditional(conditional) => self.conditional(conditional),
+        HowToCommand::Loop(repeat) => self.repeat(repeat),
+        HowToCommand::Binding(binding) => self.binding(binding),
+        HowToCommand::Qualified(qualified) => self.command(&qualified.command)
+      }
+    }
+  }
+
+  fn conditional(&mut self, conditional: &ConditionalNode) {
+    self.references(&conditional.condition);
+    self.nested(|resolver| resolver.steps(&conditional.then));
+    self.nested(|resolver| resolver.steps(&conditional.otherwise));
+  }
+
+  fn repeat(&mut self, repeat: &LoopNode) {
+    match &repeat.kind {
+      LoopKind::ForEach { item, item_span, collection } => {
+        self.references(collection);
+        self.nested(|resolver| {
+          resolver.define(&item.name, *item_span);
+          resolver.steps(&repeat.body);
+        });
+      },
+      LoopKind::Repeat { count } => {
+        self.references(count);
+        self.nested(|resolver| resolver.steps(&repeat.body));
+      },
+      LoopKind::While { condition } => {
+        self.references(condition);
+        self.nested(|resolver| resolver.steps(&repeat.body));
+      }
+    }
+  }
+
+  /// The name is only defined once the command has run, so the command
+  /// can't refer to it.
+  fn binding(&mut self, binding: &BindingNode) {
+    self.command(&binding.command);
+    self.define(&binding.name, binding.name_span);
+  }
+}
@@ -0,0 +1,81 @@
+/*
+
+Copyright (C) 2023 Carlos Kieliszewski
+
+This file is part of the Circe Project.
+
+Circe is free software: you can redistribute it and/or modify it under
+the terms of the GNU General Public License as published by the Free
+Software Foundation, either version 3 of the License, or (at your option)
+any later version.
+
+Circe is distributed in the hope that it will be useful, but WITHOUT ANY
+WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+FOR A PARTICULAR PURPOSE. See the GNU General Public License for more details.
+
+You should have received a copy of the GNU General Public License along with
+Circe. If not, see <https://www.gnu.org/licenses/>. 
+
+*/
+
+
+
+use cce_ast::Lexicon;
+
+use crate::nodes::*;
+
+
+/// What each slot of a signature was filled with, in order.
+pub type Bindings = Vec<(String, Vec<CommandComponent>)>;
+
+/// Matches `command` against `signature`, word for word, with articles
+/// all the same. Each slot takes one or more components, as long as
+/// `fits` takes them for it, and what it took is pushed onto `bindings`.
+/// `bindings` is left as it was if they don't match.
+pub fn fill_slots(
+  signature: &[CommandComponent],
+  command: &[CommandComponent],
+  lexicon: &Lexicon,
+  bindings: &mut Bindings,
+  fits: &mut dyn FnMut(&Slot, &[CommandComponent]) -> bool
+) -> bool {
+  match signature.split_first() {
+    None => command.is_empty(),
+    Some((CommandComponent::Slot(slot, _), rest)) => {
+      for len in 1..=command.len() {
+        let (value, remaining) = command.split_at(len);
+
+        if !fits(slot, value) {
+          continue;
+        }
+
+        bindings.push((slot.name.clone(), value.to_vec()));
+
+        if fill_slots(rest, remaining, lexicon, bindings, fits) {
+          return true;
+        }
+
+        bindings.pop();
+      }
+
+      false
+    },
+    Some((word, rest)) => match command.split_first() {
+      Some((first, remaining)) if same_word(word, first, lexicon) => fill_slots(rest, remaining, lexicon, bindings, fits),
+      _ => false
+    }
+  }
+}
+
+/// Whether two components are the same word, literal or number. Every
+/// article is the same word.
+pub fn same_word(a: &CommandComponent, b: &CommandComponent, lexicon: &Lexicon) -> bool {
+  match (a, b) {
+    (CommandComponent::Keyword(a, _), CommandComponent::Keyword(b, _)) => {
+      a == b || (lexicon.is_article(a) && lexicon.is_article(b))
+    },
+    (CommandComponent::Literal(a, _), CommandComponent::Literal(b, _)) => a == b,
+    (CommandComponent::Number(a, _), CommandComponent::Number(b, _)) => a == b,
+    _ => false
+  }
+}
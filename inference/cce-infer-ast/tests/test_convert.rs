@@ -21,29 +21,32 @@ Circe. If not, see <https://www.gnu.org/licenses/>.
 
 use cce_infer_ast::*;
 use cce_ast::{Parser, ParseNode};
+use cce_stream::{Span, FileId};
+
+fn span(start: usize, end: usize) -> Span {
+  Span::new(FileId(0), start, end)
+}
 
 
 #[test]
 fn test_convert_basic() {
   let mut parser: Parser = Parser::from("print 'Hello, world!' to the console.");
-
-  let mut parse_nodes: Vec<ParseNode> = Vec::new();
-  while let Some(node) = parser.next().unwrap() {
-    parse_nodes.push(node);
-  }
+  let parse_nodes: Vec<ParseNode> = parser.parse_all().unwrap().nodes;
 
   let ast_nodes: Vec<ProgramNode> = convert(parse_nodes);
 
   let expected: Vec<ProgramNode> = vec![
     ProgramNode::Command(CommandNode {
       command: vec![
-        CommandComponent::Keyword("print".to_string()),
-        CommandComponent::Literal("Hello, world!".to_string()),
-        CommandComponent::Keyword("to".to_string()),
-        CommandComponent::Keyword("the".to_string()),
-        CommandComponent::Keyword("console".to_string()),
+        CommandComponent::Keyword("print".to_string(), span(0, 5)),
+        CommandComponent::Literal("Hello, world!".to_string(), span(6, 21)),
+        CommandComponent::Keyword("to".to_string(), span(22, 24)),
+        CommandComponent::Keyword("the".to_string(), span(25, 28)),
+        CommandComponent::Keyword("console".to_string(), span(29, 36)),
       ],
-      modifiers: vec![]
+      modifiers: vec![],
+      children: vec![],
+      span: span(0, 36)
     })
   ];
 
@@ -53,30 +56,28 @@ fn test_convert_basic() {
 #[test]
 fn test_convert_modifiers() {
   let mut parser: Parser = Parser::from("print 'Hello, world!' to the console | add a newline.");
-
-  let mut parse_nodes: Vec<ParseNode> = Vec::new();
-  while let Some(node) = parser.next().unwrap() {
-    parse_nodes.push(node);
-  }
+  let parse_nodes: Vec<ParseNode> = parser.parse_all().unwrap().nodes;
 
   let ast_nodes: Vec<ProgramNode> = convert(parse_nodes);
 
   let expected: Vec<ProgramNode> = vec![
     ProgramNode::Command(CommandNode {
       command: vec![
-        CommandComponent::Keyword("print".to_string()),
-        CommandComponent::Literal("Hello, world!".to_string()),
-        CommandComponent::Keyword("to".to_string()),
-        CommandComponent::Keyword("the".to_string()),
-        CommandComponent::Keyword("console".to_string()),
+        CommandComponent::Keyword("print".to_string(), span(0, 5)),
+        CommandComponent::Literal("Hello, world!".to_string(), span(6, 21)),
+        CommandComponent::Keyword("to".to_string(), span(22, 24)),
+        CommandComponent::Keyword("the".to_string(), span(25, 28)),
+        CommandComponent::Keyword("console".to_string(), span(29, 36)),
       ],
       modifiers: vec![
         vec![
-          CommandComponent::Keyword("add".to_string()),
-          CommandComponent::Keyword("a".to_string()),
-          CommandComponent::Keyword("newline".to_string())
+          CommandComponent::Keyword("add".to_string(), span(39, 42)),
+          CommandComponent::Keyword("a".to_string(), span(43, 44)),
+          CommandComponent::Keyword("newline".to_string(), span(45, 52))
         ]
-      ]
+      ],
+      children: vec![],
+      span: span(0, 52)
     })
   ];
 
@@ -86,34 +87,34 @@ fn test_convert_modifiers() {
 #[test]
 fn test_convert_multiple_commands() {
   let mut parser: Parser = Parser::from("print 'Hello, world!' to the console. print 'Goodbye, world!' to the console.");
-
-  let mut parse_nodes: Vec<ParseNode> = Vec::new();
-  while let Some(node) = parser.next().unwrap() {
-    parse_nodes.push(node);
-  }
+  let parse_nodes: Vec<ParseNode> = parser.parse_all().unwrap().nodes;
 
   let ast_nodes: Vec<ProgramNode> = convert(parse_nodes);
 
   let expected: Vec<ProgramNode> = vec![
     ProgramNode::Command(CommandNode {
       command: vec![
-        CommandComponent::Keyword("print".to_string()),
-        CommandComponent::Literal("Hello, world!".to_string()),
-        CommandComponent::Keyword("to".to_string()),
-        CommandComponent::Keyword("the".to_string()),
-        CommandComponent::Keyword("console".to_string()),
+        CommandComponent::Keyword("print".to_string(), span(0, 5)),
+        CommandComponent::Literal("Hello, world!".to_string(), span(6, 21)),
+        CommandComponent::Keyword("to".to_string(), span(22, 24)),
+        CommandComponent::Keyword("the".to_string(), span(25, 28)),
+        CommandComponent::Keyword("console".to_string(), span(29, 36)),
       ],
-      modifiers: vec![]
+      modifiers: vec![],
+      children: vec![],
+      span: span(0, 36)
     }),
     ProgramNode::Command(CommandNode {
       command: vec![
-        CommandComponent::Keyword("print".to_string()),
-        CommandComponent::Literal("Goodbye, world!".to_string()),
-        CommandComponent::Keyword("to".to_string()),
-        CommandComponent::Keyword("the".to_string()),
-        CommandComponent::Keyword("console".to_string()),
+        CommandComponent::Keyword("print".to_string(), span(38, 43)),
+        CommandComponent::Literal("Goodbye, world!".to_string(), span(44, 61)),
+        CommandComponent::Keyword("to".to_string(), span(62, 64)),
+        CommandComponent::Keyword("the".to_string(), span(65, 68)),
+        CommandComponent::Keyword("console".to_string(), span(69, 76)),
       ],
-      modifiers: vec![]
+      modifiers: vec![],
+      children: vec![],
+      span: span(38, 76)
     })
   ];
 
@@ -123,31 +124,31 @@ fn test_convert_multiple_commands() {
 #[test]
 fn test_convert_howto() {
   let mut parser: Parser = Parser::from("howto print a string?\n- write the string");
-
-  let mut parse_nodes: Vec<ParseNode> = Vec::new();
-  while let Some(node) = parser.next().unwrap() {
-    parse_nodes.push(node);
-  }
+  let parse_nodes: Vec<ParseNode> = parser.parse_all().unwrap().nodes;
 
   let ast_nodes: Vec<ProgramNode> = convert(parse_nodes);
 
   let expected: Vec<ProgramNode> = vec![
     ProgramNode::HowTo(HowToNode {
+      visibility: Visibility::Public,
       signature: vec![
-        CommandComponent::Keyword("print".to_string()),
-        CommandComponent::Keyword("a".to_string()),
-        CommandComponent::Keyword("string".to_string()),
+        CommandComponent::Keyword("print".to_string(), span(6, 11)),
+        CommandComponent::Keyword("a".to_string(), span(12, 13)),
+        CommandComponent::Keyword("string".to_string(), span(14, 20)),
       ],
       body: vec![
         HowToCommand::HighLevel(CommandNode {
           command: vec![
-            CommandComponent::Keyword("write".to_string()),
-            CommandComponent::Keyword("the".to_string()),
-            CommandComponent::Keyword("string".to_string()),
+            CommandComponent::Keyword("write".to_string(), span(24, 29)),
+            CommandComponent::Keyword("the".to_string(), span(30, 33)),
+            CommandComponent::Keyword("string".to_string(), span(34, 40)),
           ],
-          modifiers: vec![]
+          modifiers: vec![],
+          children: vec![],
+          span: span(24, 40)
         })
-      ]
+      ],
+      span: span(0, 40)
     })
   ];
 
@@ -157,37 +158,37 @@ fn test_convert_howto() {
 #[test]
 fn test_convert_howto_modifiers() {
   let mut parser: Parser = Parser::from("howto print a string?\n- write the string | add a newline");
-
-  let mut parse_nodes: Vec<ParseNode> = Vec::new();
-  while let Some(node) = parser.next().unwrap() {
-    parse_nodes.push(node);
-  }
+  let parse_nodes: Vec<ParseNode> = parser.parse_all().unwrap().nodes;
 
   let ast_nodes: Vec<ProgramNode> = convert(parse_nodes);
 
   let expected: Vec<ProgramNode> = vec![
     ProgramNode::HowTo(HowToNode {
+      visibility: Visibility::Public,
       signature: vec![
-        CommandComponent::Keyword("print".to_string()),
-        CommandComponent::Keyword("a".to_string()),
-        CommandComponent::Keyword("string".to_string()),
+        CommandComponent::Keyword("print".to_string(), span(6, 11)),
+        CommandComponent::Keyword("a".to_string(), span(12, 13)),
+        CommandComponent::Keyword("string".to_string(), span(14, 20)),
       ],
       body: vec![
         HowToCommand::HighLevel(CommandNode {
           command: vec![
-            CommandComponent::Keyword("write".to_string()),
-            CommandComponent::Keyword("the".to_string()),
-            CommandComponent::Keyword("string".to_string()),
+            CommandComponent::Keyword("write".to_string(), span(24, 29)),
+            CommandComponent::Keyword("the".to_string(), span(30, 33)),
+            CommandComponent::Keyword("string".to_string(), span(34, 40)),
           ],
           modifiers: vec![
             vec![
-              CommandComponent::Keyword("add".to_string()),
-              CommandComponent::Keyword("a".to_string()),
-              CommandComponent::Keyword("newline".to_string())
+              CommandComponent::Keyword("add".to_string(), span(43, 46)),
+              CommandComponent::Keyword("a".to_string(), span(47, 48)),
+              CommandComponent::Keyword("newline".to_string(), span(49, 56))
             ]
-          ]
+          ],
+          children: vec![],
+          span: span(24, 56)
         })
-      ]
+      ],
+      span: span(0, 56)
     })
   ];
 
@@ -197,33 +198,338 @@ fn test_convert_howto_modifiers() {
 #[test]
 fn test_convert_whatis() {
   let mut parser: Parser = Parser::from("whatis a string?\n- a sequence of characters");
+  let parse_nodes: Vec<ParseNode> = parser.parse_all().unwrap().nodes;
 
-  let mut parse_nodes: Vec<ParseNode> = Vec::new();
-  while let Some(node) = parser.next().unwrap() {
-    parse_nodes.push(node);
-  }
+  let ast_nodes: Vec<ProgramNode> = convert(parse_nodes);
+
+  let expected: Vec<ProgramNode> = vec![
+    ProgramNode::WhatIs(WhatIsNode {
+      visibility: Visibility::Public,
+      signature: vec![
+        CommandComponent::Keyword("a".to_string(), span(7, 8)),
+        CommandComponent::Keyword("string".to_string(), span(9, 15)),
+      ],
+      body: vec![
+        CommandNode {
+          command: vec![
+            CommandComponent::Keyword("a".to_string(), span(19, 20)),
+            CommandComponent::Keyword("sequence".to_string(), span(21, 29)),
+            CommandComponent::Keyword("of".to_string(), span(30, 32)),
+            CommandComponent::Keyword("characters".to_string(), span(33, 43)),
+          ],
+          modifiers: vec![],
+          children: vec![],
+          span: span(19, 43)
+        }
+      ],
+      span: span(0, 43)
+    })
+  ];
+
+  assert_eq!(ast_nodes, expected);
+}
+#[test]
+fn test_convert_howto_primitive() {
+  let mut parser: Parser = Parser::from("howto exit?\n-*\n< run the 'exit' system call\n>\n-*.");
+  let parse_nodes: Vec<ParseNode> = parser.parse_all().unwrap().nodes;
+
+  let ast_nodes: Vec<ProgramNode> = convert(parse_nodes);
+
+  let expected: Vec<ProgramNode> = vec![
+    ProgramNode::HowTo(HowToNode {
+      visibility: Visibility::Public,
+      signature: vec![
+        CommandComponent::Keyword("exit".to_string(), span(6, 10)),
+      ],
+      body: vec![
+        HowToCommand::Primitive(vec![
+          CommandNode {
+            command: vec![
+              CommandComponent::Keyword("run".to_string(), span(17, 20)),
+              CommandComponent::Keyword("the".to_string(), span(21, 24)),
+              CommandComponent::Literal("exit".to_string(), span(25, 31)),
+              CommandComponent::Keyword("system".to_string(), span(32, 38)),
+              CommandComponent::Keyword("call".to_string(), span(39, 43)),
+            ],
+            modifiers: vec![],
+            children: vec![],
+            span: span(17, 43)
+          }
+        ], span(12, 48))
+      ],
+      span: span(0, 48)
+    })
+  ];
+
+  assert_eq!(ast_nodes, expected);
+}
+
+#[test]
+fn test_convert_number() {
+  let mut parser: Parser = Parser::from("write byte 10.");
+  let parse_nodes: Vec<ParseNode> = parser.parse_all().unwrap().nodes;
+
+  let ast_nodes: Vec<ProgramNode> = convert(parse_nodes);
+
+  let expected: Vec<ProgramNode> = vec![
+    ProgramNode::Command(CommandNode {
+      command: vec![
+        CommandComponent::Keyword("write".to_string(), span(0, 5)),
+        CommandComponent::Keyword("byte".to_string(), span(6, 10)),
+        CommandComponent::Number(10, span(11, 13)),
+      ],
+      modifiers: vec![],
+      children: vec![],
+      span: span(0, 13)
+    })
+  ];
+
+  assert_eq!(ast_nodes, expected);
+}
+
+#[test]
+fn test_convert_nested() {
+  let mut parser: Parser = Parser::from("whatis a byte?\n- a number\n  - small");
+  let parse_nodes: Vec<ParseNode> = parser.parse_all().unwrap().nodes;
 
   let ast_nodes: Vec<ProgramNode> = convert(parse_nodes);
 
   let expected: Vec<ProgramNode> = vec![
     ProgramNode::WhatIs(WhatIsNode {
+      visibility: Visibility::Public,
       signature: vec![
-        CommandComponent::Keyword("a".to_string()),
-        CommandComponent::Keyword("string".to_string()),
+        CommandComponent::Keyword("a".to_string(), span(7, 8)),
+        CommandComponent::Keyword("byte".to_string(), span(9, 13)),
       ],
       body: vec![
         CommandNode {
           command: vec![
-            CommandComponent::Keyword("a".to_string()),
-            CommandComponent::Keyword("sequence".to_string()),
-            CommandComponent::Keyword("of".to_string()),
-            CommandComponent::Keyword("characters".to_string()),
+            CommandComponent::Keyword("a".to_string(), span(17, 18)),
+            CommandComponent::Keyword("number".to_string(), span(19, 25)),
+          ],
+          modifiers: vec![],
+          children: vec![
+            CommandNode {
+              command: vec![
+                CommandComponent::Keyword("small".to_string(), span(30, 35)),
+              ],
+              modifiers: vec![],
+              children: vec![],
+              span: span(30, 35)
+            }
           ],
-          modifiers: vec![]
+          span: span(17, 25)
         }
-      ]
+      ],
+      span: span(0, 35)
+    })
+  ];
+
+  assert_eq!(ast_nodes, expected);
+}
+
+#[test]
+fn test_convert_typed_slot() {
+  let mut parser: Parser = Parser::from("howto show %n: a number?\n- print it");
+  let parse_nodes: Vec<ParseNode> = parser.parse_all().unwrap().nodes;
+
+  let ast_nodes: Vec<ProgramNode> = convert(parse_nodes);
+
+  let signature: &[CommandComponent] = match &ast_nodes[0] {
+    ProgramNode::HowTo(howto) => &howto.signature,
+    other => panic!("Expected a howto, got {:?}", other)
+  };
+
+  assert_eq!(signature, &[
+    CommandComponent::Keyword("show".to_string(), span(6, 10)),
+    CommandComponent::Slot(Slot {
+      name: "n".to_string(),
+      kind: Some(vec![
+        CommandComponent::Keyword("a".to_string(), span(15, 16)),
+        CommandComponent::Keyword("number".to_string(), span(17, 23)),
+      ])
+    }, span(11, 23))
+  ]);
+}
+
+#[test]
+fn test_convert_conditional() {
+  let mut parser: Parser = Parser::from("if it rains, stay.\notherwise, go.");
+  let parse_nodes: Vec<ParseNode> = parser.parse_all().unwrap().nodes;
+
+  let ast_nodes: Vec<ProgramNode> = convert(parse_nodes);
+
+  let expected: Vec<ProgramNode> = vec![
+    ProgramNode::Conditional(ConditionalNode {
+      condition: vec![
+        CommandComponent::Keyword("it".to_string(), span(3, 5)),
+        CommandComponent::Keyword("rains".to_string(), span(6, 11)),
+      ],
+      then: vec![
+        HowToCommand::HighLevel(CommandNode {
+          command: vec![
+            CommandComponent::Keyword("stay".to_string(), span(13, 17)),
+          ],
+          modifiers: vec![],
+          children: vec![],
+          span: span(13, 17)
+        })
+      ],
+      otherwise: vec![
+        HowToCommand::HighLevel(CommandNode {
+          command: vec![
+            CommandComponent::Keyword("go".to_string(), span(30, 32)),
+          ],
+          modifiers: vec![],
+          children: vec![],
+          span: span(30, 32)
+        })
+      ],
+      span: span(0, 17)
     })
   ];
 
   assert_eq!(ast_nodes, expected);
-}
\ No newline at end of file
+}
+
+#[test]
+fn test_convert_loop() {
+  let mut parser: Parser = Parser::from("for each %x in %xs, repeat 2 times, print %x.");
+  let parse_nodes: Vec<ParseNode> = parser.parse_all().unwrap().nodes;
+
+  let ast_nodes: Vec<ProgramNode> = convert(parse_nodes);
+
+  let expected: Vec<ProgramNode> = vec![
+    ProgramNode::Loop(LoopNode {
+      kind: LoopKind::ForEach {
+        item: Slot { name: "x".to_string(), kind: None },
+        item_span: span(9, 11),
+        collection: vec![
+          CommandComponent::Slot(Slot { name: "xs".to_string(), kind: None }, span(15, 18)),
+        ]
+      },
+      body: vec![
+        HowToCommand::Loop(LoopNode {
+          kind: LoopKind::Repeat {
+            count: vec![
+              CommandComponent::Number(2, span(27, 28)),
+            ]
+          },
+          body: vec![
+            HowToCommand::HighLevel(CommandNode {
+              command: vec![
+                CommandComponent::Keyword("print".to_string(), span(36, 41)),
+                CommandComponent::Slot(Slot { name: "x".to_string(), kind: None }, span(42, 44)),
+              ],
+              modifiers: vec![],
+              children: vec![],
+              span: span(36, 44)
+            })
+          ],
+          span: span(20, 44)
+        })
+      ],
+      span: span(0, 44)
+    })
+  ];
+
+  assert_eq!(ast_nodes, expected);
+}
+
+#[test]
+fn test_convert_binding() {
+  let mut parser: Parser = Parser::from("ask a name and call it who.\nsay %who.");
+  let parse_nodes: Vec<ParseNode> = parser.parse_all().unwrap().nodes;
+
+  let ast_nodes: Vec<ProgramNode> = convert(parse_nodes);
+
+  let expected: Vec<ProgramNode> = vec![
+    ProgramNode::Binding(BindingNode {
+      command: CommandNode {
+        command: vec![
+          CommandComponent::Keyword("ask".to_string(), span(0, 3)),
+          CommandComponent::Keyword("a".to_string(), span(4, 5)),
+          CommandComponent::Keyword("name".to_string(), span(6, 10)),
+        ],
+        modifiers: vec![],
+        children: vec![],
+        span: span(0, 10)
+      },
+      name: "who".to_string(),
+      name_span: span(23, 26),
+      span: span(0, 26)
+    }),
+    ProgramNode::Command(CommandNode {
+      command: vec![
+        CommandComponent::Keyword("say".to_string(), span(28, 31)),
+        CommandComponent::Slot(Slot { name: "who".to_string(), kind: None }, span(32, 36)),
+      ],
+      modifiers: vec![],
+      children: vec![],
+      span: span(28, 36)
+    })
+  ];
+
+  assert_eq!(ast_nodes, expected);
+}
+
+#[test]
+fn test_convert_skips_imports() {
+  let mut parser: Parser = Parser::from("use 'console'.\nsay hi.");
+  let parse_nodes: Vec<ParseNode> = parser.parse_all().unwrap().nodes;
+
+  let ast_nodes: Vec<ProgramNode> = convert(parse_nodes);
+
+  assert!(matches!(ast_nodes.as_slice(), [ProgramNode::Command(command)] if command.span == span(15, 21)));
+}
+
+#[test]
+fn test_convert_modules() {
+  let mut parser: Parser = Parser::from("module io.\nprivate whatis stdout?\n- a stream.\n\nwithin io, flush.");
+  let parse_nodes: Vec<ParseNode> = parser.parse_all().unwrap().nodes;
+
+  let ast_nodes: Vec<ProgramNode> = convert(parse_nodes);
+
+  assert_eq!(ast_nodes[0], ProgramNode::Module(ModuleNode {
+    name: "io".to_string(),
+    name_span: span(7, 9),
+    span: span(0, 9)
+  }));
+  assert!(matches!(&ast_nodes[1], ProgramNode::WhatIs(whatis) if whatis.visibility == Visibility::Private));
+  assert_eq!(ast_nodes[2], ProgramNode::Qualified(QualifiedNode {
+    module: "io".to_string(),
+    module_span: span(54, 56),
+    command: CommandNode {
+      command: vec![CommandComponent::Keyword("flush".to_string(), span(58, 63))],
+      modifiers: vec![],
+      children: vec![],
+      span: span(58, 63)
+    },
+    span: span(47, 63)
+  }));
+}
+
+#[test]
+fn test_convert_query() {
+  let mut parser: Parser = Parser::from("? howto print 42");
+  let parse_nodes: Vec<ParseNode> = parser.parse_all().unwrap().nodes;
+
+  let ast_nodes: Vec<ProgramNode> = convert(parse_nodes);
+
+  assert_eq!(ast_nodes, vec![
+    ProgramNode::Query(QueryNode {
+      kind: QueryKind::HowTo,
+      subject: vec![
+        CommandComponent::Keyword("print".to_string(), span(8, 13)),
+        CommandComponent::Number(42, span(14, 16))
+      ],
+      span: span(0, 16)
+    })
+  ]);
+
+  let mut parser: Parser = Parser::from("? whatis %text: a string in 'quotes'");
+  match convert(parser.parse_all().unwrap().nodes).remove(0) {
+    ProgramNode::Query(query) => assert_eq!(phrase(&query.subject), "%text: a string in 'quotes'"),
+    other => panic!("Expected a query, got {:?}", other)
+  }
+}
@@ -0,0 +1,219 @@
+/*
+
+Copyright (C) 2023 Carlos Kieliszewski
+
+This file is part of the Circe Project.
+
+Circe is free software: you can redistribute it and/or modify it under
+the terms of the GNU General Public License as published by the Free
+Software Foundation, either version 3 of the License, or (at your option)
+any later version.
+
+Circe is distributed in the hope that it will be useful, but WITHOUT ANY
+WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+FOR A PARTICULAR PURPOSE. See the GNU General Public License for more details.
+
+You should have received a copy of the GNU General Public License along with
+Circe. If not, see <https://www.gnu.org/licenses/>. 
+
+*/
+
+
+use cce_infer_ast::*;
+use cce_ast::{Lexer, Lexicon, LoadError, LoadedProgram, Loader, Parser};
+use cce_stream::{FileId, InputStream, SourceMap, Span};
+
+use std::fs;
+use std::path::PathBuf;
+
+fn span(file: usize, start: usize, end: usize) -> Span {
+  Span::new(FileId(file), start, end)
+}
+
+/// Each source as a file of its own, named `file0.cce`, `file1.cce` and
+/// so on.
+fn files(sources: &[&str]) -> SourceMap {
+  let mut files: SourceMap = SourceMap::new();
+
+  for (i, source) in sources.iter().enumerate() {
+    files.add_virtual(format!("file{}.cce", i), *source);
+  }
+
+  files
+}
+
+/// Parses each file in order, as a loader would put them together.
+fn program(files: &SourceMap) -> Vec<ProgramNode> {
+  files.files().flat_map(|file| {
+    let source: &str = file.source().unwrap();
+    let mut parser: Parser = Parser::new(Lexer::new(InputStream::with_file(source, file.id)));
+    convert(parser.parse_all().unwrap().nodes)
+  }).collect()
+}
+
+fn check(sources: &[&str]) -> Vec<NamespaceError> {
+  let files: SourceMap = files(sources);
+  check_namespaces(&program(&files), &Lexicon::default(), &files).1
+}
+
+
+#[test]
+fn test_namespace_modules() {
+  let files: SourceMap = files(&["module console.\nsay hi.", "say hi."]);
+  let (namespaces, errors) = check_namespaces(&program(&files), &Lexicon::default(), &files);
+
+  assert_eq!(errors, vec![]);
+  assert_eq!(namespaces.module_of(FileId(0)), Some("console"));
+  assert_eq!(namespaces.module_of(FileId(1)), None);
+  assert!(namespaces.has_module("console"));
+  assert!(!namespaces.has_module("io"));
+}
+
+#[test]
+fn test_namespace_headers() {
+  let errors: Vec<NamespaceError> = check(&["say hi.\nmodule console.\nmodule io."]);
+
+  assert_eq!(errors, vec![
+    NamespaceError::LateHeader { span: span(0, 8, 22), first: span(0, 0, 6) },
+    NamespaceError::DuplicateHeader { previous_name: "console".to_string(), span: span(0, 24, 33), previous: span(0, 8, 22) }
+  ]);
+}
+
+#[test]
+fn test_namespace_duplicates() {
+  let library: &str = "howto print a string?\n- write it.";
+
+  assert_eq!(check(&[library, "howto print the string?\n- say it."]), vec![
+    NamespaceError::DuplicateDescription {
+      kind: "howto",
+      signature: "print the string".to_string(),
+      span: span(1, 6, 22),
+      previous: span(0, 6, 20),
+      previous_file: Some("file0.cce".to_string())
+    }
+  ]);
+
+  // Different modules, slot kinds, or private to different files.
+  assert_eq!(check(&[library, "module console.\nhowto print a string?\n- say it."]), vec![]);
+  assert_eq!(check(&["howto print %n: a number?\n- a.", "howto print %s: a string?\n- b."]), vec![]);
+  assert_eq!(check(&["private howto print a string?\n- a.", "private howto print a string?\n- b."]), vec![]);
+
+  // A private description still collides with the public ones its file
+  // can see.
+  assert_eq!(check(&[library, "private howto print a string?\n- b."]).len(), 1);
+  assert_eq!(check(&["whatis stdout?\n- a stream.\n\nprivate whatis stdout?\n- a file."]).len(), 1);
+}
+
+#[test]
+fn test_namespace_unknown_module() {
+  let errors: Vec<NamespaceError> = check(&[
+    "module io.\nwithin io, flush.",
+    "howto greet?\n- if late, within console, say goodbye\n- within io, say hi."
+  ]);
+
+  assert_eq!(errors, vec![
+    NamespaceError::UnknownModule { name: "console".to_string(), span: span(1, 31, 38) }
+  ]);
+}
+
+#[test]
+fn test_namespace_private_uses() {
+  let library: &str = "module lib.\n\nprivate howto greet %name?\n- say hi to %name.\n\nprivate whatis a guest?\n- a person.\n\ngreet you.";
+
+  let errors: Vec<NamespaceError> = check(&[
+    library,
+    "greet me.\n\nhowto wave?\n- if late, within lib, greet them\n\n? whatis the guest"
+  ]);
+
+  assert_eq!(errors, vec![
+    NamespaceError::PrivateDescription {
+      kind: "howto",
+      signature: "greet %name".to_string(),
+      span: span(1, 0, 8),
+      definition: span(0, 27, 38),
+      file: "file0.cce".to_string()
+    },
+    NamespaceError::PrivateDescription {
+      kind: "howto",
+      signature: "greet %name".to_string(),
+      span: span(1, 46, 56),
+      definition: span(0, 27, 38),
+      file: "file0.cce".to_string()
+    },
+    NamespaceError::PrivateDescription {
+      kind: "whatis",
+      signature: "a guest".to_string(),
+      span: span(1, 58, 76),
+      definition: span(0, 75, 82),
+      file: "file0.cce".to_string()
+    }
+  ]);
+
+  // A description the command can see is enough, wherever the private
+  // one is.
+  assert_eq!(check(&[library, "module io.\nhowto greet %name?\n- wave.\n\ngreet me."]), vec![]);
+}
+
+#[test]
+fn test_namespace_private_conditions() {
+  let library: &str = "private howto it is late?\n- check the clock.\n\nprivate howto loudly?\n- shout.";
+  let errors: Vec<NamespaceError> = check(&[library, "if it is late, leave.\nwhile it is late, wait.\nsay hi | loudly."]);
+
+  let private = |span: Span, signature: &str, definition: Span| NamespaceError::PrivateDescription {
+    kind: "howto",
+    signature: signature.to_string(),
+    span,
+    definition,
+    file: "file0.cce".to_string()
+  };
+
+  assert_eq!(errors, vec![
+    private(span(1, 3, 13), "it is late", span(0, 14, 24)),
+    private(span(1, 28, 38), "it is late", span(0, 14, 24)),
+    private(span(1, 55, 61), "loudly", span(0, 60, 66))
+  ]);
+}
+
+#[test]
+fn test_namespace_ambiguous_references() {
+  let a: &str = "module a.\n\nhowto print %s to the console?\n- write %s.";
+  let b: &str = "module b.\n\nhowto print %s to the console?\n- draw %s.";
+
+  assert_eq!(check(&["print 'x' to the console.\nwithin a, print 'y' to the console.", a, b]), vec![
+    NamespaceError::AmbiguousReference {
+      command: "print 'x' to the console".to_string(),
+      span: span(0, 0, 24),
+      candidates: vec![(Some("a".to_string()), span(1, 17, 40)), (Some("b".to_string()), span(2, 17, 40))]
+    }
+  ]);
+
+  // A howto in the command's own module is used first.
+  assert_eq!(check(&["module a.\nprint 'x' to the console.", a, b]), vec![]);
+  assert_eq!(check(&["howto print %s to the console?\n- say %s.\n\nprint 'x' to the console.", a, b]), vec![]);
+
+  // One other module is no choice at all.
+  assert_eq!(check(&["print 'x' to the console.", a]), vec![]);
+}
+
+#[test]
+fn test_namespace_use_modules() {
+  let root: PathBuf = std::env::temp_dir().join(format!("cce-namespace-use-{}", std::process::id()));
+  let _ = fs::remove_dir_all(&root);
+  fs::create_dir_all(&root).unwrap();
+
+  fs::write(root.join("main.cce"), "use 'io'.\nuse 'screen'.\nwithin io, print 'hi' to the console.\n").unwrap();
+  fs::write(root.join("io.cce"), "module io.\n\nhowto print %s to the console?\n- write %s.\n").unwrap();
+  fs::write(root.join("screen.cce"), "howto print %s to the console?\n- draw %s.\n").unwrap();
+
+  let mut files: SourceMap = SourceMap::new();
+  let main: FileId = files.add_file(root.join("main.cce")).unwrap();
+  let (loaded, errors): (LoadedProgram, Vec<LoadError>) = Loader::new().load(&mut files, main);
+  assert!(errors.is_empty(), "{:?}", errors);
+
+  let program: Vec<ProgramNode> = convert(loaded.nodes);
+  let (namespaces, errors) = check_namespaces(&program, &Lexicon::default(), &files);
+
+  assert_eq!(errors, vec![]);
+  assert_eq!(namespaces.module_of(loaded.files[1].id), Some("io"));
+  assert_eq!(namespaces.module_of(loaded.files[2].id), None);
+}
@@ -0,0 +1,72 @@
+/*
+
+Copyright (C) 2023 Carlos Kieliszewski
+
+This file is part of the Circe Project.
+
+Circe is free software: you can redistribute it and/or modify it under
+the terms of the GNU General Public License as published by the Free
+Software Foundation, either version 3 of the License, or (at your option)
+any later version.
+
+Circe is distributed in the hope that it will be useful, but WITHOUT ANY
+WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+FOR A PARTICULAR PURPOSE. See the GNU General Public License for more details.
+
+You should have received a copy of the GNU General Public License along with
+Circe. If not, see <https://www.gnu.org/licenses/>. 
+
+*/
+
+
+use cce_infer_ast::*;
+use cce_ast::Parser;
+use cce_stream::{Span, FileId};
+
+fn span(start: usize, end: usize) -> Span {
+  Span::new(FileId(0), start, end)
+}
+
+fn resolve_source(source: &str) -> Vec<NameError> {
+  let program: Vec<ProgramNode> = convert(Parser::from(source).parse_all().unwrap().nodes);
+  resolve(&program)
+}
+
+
+#[test]
+fn test_resolve_binding() {
+  let errors: Vec<NameError> = resolve_source("print %name.\nread a line and call it name.\nprint %name.");
+
+  assert_eq!(errors, vec![
+    NameError::Undefined { name: "name".to_string(), span: span(6, 11) }
+  ]);
+}
+
+#[test]
+fn test_resolve_scopes() {
+  let errors: Vec<NameError> = resolve_source("howto greet %p?\n- if %p is empty, ask again and call it p2\n- say %p2\n- for each %c in %p, say %c\n- say %c.");
+
+  assert_eq!(errors, vec![
+    NameError::Undefined { name: "p2".to_string(), span: span(65, 68) },
+    NameError::Undefined { name: "c".to_string(), span: span(103, 105) }
+  ]);
+}
+
+#[test]
+fn test_resolve_howto_is_isolated() {
+  let errors: Vec<NameError> = resolve_source("ask a name and call it who.\nhowto greet?\n- say %who.");
+
+  assert_eq!(errors, vec![
+    NameError::Undefined { name: "who".to_string(), span: span(47, 51) }
+  ]);
+}
+
+#[test]
+fn test_resolve_shadowed() {
+  let errors: Vec<NameError> = resolve_source("ask a name and call it who.\nask again and call it who.\nfor each %who in the list, say %who.");
+
+  assert_eq!(errors, vec![
+    NameError::Shadowed { name: "who".to_string(), span: span(50, 53), previous: span(23, 26) },
+    NameError::Shadowed { name: "who".to_string(), span: span(64, 68), previous: span(50, 53) }
+  ]);
+}
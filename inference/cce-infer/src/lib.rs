@@ -21,6 +21,10 @@ Circe. If not, see <https://www.gnu.org/licenses/>.
 
 mod deduce;
 mod infer;
+mod matcher;
+mod query;
 
 pub use deduce::*;
-pub use infer::*;
\ No newline at end of file
+pub use infer::*;
+pub use matcher::*;
+pub use query::*;
\ No newline at end of file
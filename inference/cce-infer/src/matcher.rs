@@ -0,0 +1,182 @@
+/*
+
+Copyright (C) 2023 Carlos Kieliszewski
+
+This file is part of the Circe Project.
+
+Circe is free software: you can redistribute it and/or modify it under
+the terms of the GNU General Public License as published by the Free
+Software Foundation, either version 3 of the License, or (at your option)
+any later version.
+
+Circe is distributed in the hope that it will be useful, but WITHOUT ANY
+WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+FOR A PARTICULAR PURPOSE. See the GNU General Public License for more details.
+
+You should have received a copy of the GNU General Public License along with
+Circe. If not, see <https://www.gnu.org/licenses/>. 
+
+*/
+
+
+use cce_ast::Lexicon;
+use cce_infer_ast::{fill_slots, CommandComponent, HowToNode, Namespaces, ProgramNode, Slot, WhatIsNode};
+use cce_stream::FileId;
+
+pub use cce_infer_ast::Bindings;
+
+/// Matches commands against the howto and whatis statements of a program.
+/// Articles are interchangeable, so `print the string` matches
+/// `print a string`.
+pub struct Matcher<'p> {
+  pub(crate) howtos: Vec<&'p HowToNode>,
+  pub(crate) whatis: Vec<&'p WhatIsNode>,
+  pub(crate) lexicon: Lexicon,
+  pub(crate) namespaces: Namespaces
+}
+
+impl<'p> Matcher<'p> {
+  pub fn new(nodes: &'p [ProgramNode]) -> Self {
+    Self::with_lexicon(nodes, Lexicon::default())
+  }
+
+  pub fn with_lexicon(nodes: &'p [ProgramNode], lexicon: Lexicon) -> Self {
+    let mut howtos: Vec<&'p HowToNode> = Vec::new();
+    let mut whatis: Vec<&'p WhatIsNode> = Vec::new();
+
+    for node in nodes.iter() {
+      match node {
+        ProgramNode::HowTo(howto) => howtos.push(howto),
+        ProgramNode::WhatIs(statement) => whatis.push(statement),
+        ProgramNode::Command(_) | ProgramNode::Conditional(_) | ProgramNode::Loop(_) |
+        ProgramNode::Binding(_) | ProgramNode::Module(_) | ProgramNode::Qualified(_) |
+        ProgramNode::Query(_) => {}
+      }
+    }
+
+    Self { howtos, whatis, lexicon, namespaces: Namespaces::new(nodes) }
+  }
+
+  /// The first howto whose signature `command` matches, along with what
+  /// its slots were filled with.
+  pub fn find_howto(&self, command: &[CommandComponent]) -> Option<(&'p HowToNode, Bindings)> {
+    self.howtos.iter().find_map(|howto| {
+      self.match_signature(&howto.signature, command).map(|bindings| (*howto, bindings))
+    })
+  }
+
+  /// Like [`Matcher::find_howto`], but only with the howtos a command in
+  /// `from` can see. With `within`, only the howtos of that module are
+  /// tried. Without it, the howtos of `from`'s own module are tried
+  /// before the others.
+  pub fn find_howto_from(
+    &self,
+    command: &[CommandComponent],
+    from: FileId,
+    within: Option<&str>
+  ) -> Option<(&'p HowToNode, Bindings)> {
+    let visible = self.howtos.iter()
+      .filter(|howto| self.namespaces.can_see(from, howto.visibility, howto.span));
+
+    let module: Option<&str> = within.or(self.namespaces.module_of(from));
+    let (own, others): (Vec<&&'p HowToNode>, Vec<&&'p HowToNode>) = visible
+      .partition(|howto| self.namespaces.module_of(howto.span.file) == module);
+
+    let candidates: Vec<&&'p HowToNode> = match within {
+      Some(_) => own,
+      None => own.into_iter().chain(others).collect()
+    };
+
+    candidates.into_iter().find_map(|howto| {
+      self.match_signature(&howto.signature, command).map(|bindings| (*howto, bindings))
+    })
+  }
+
+  /// Matches `command` against `signature`, word for word. Each slot
+  /// takes one or more components, as long as they are of the slot's
+  /// kind, if it has one.
+  pub fn match_signature(&self, signature: &[CommandComponent], command: &[CommandComponent]) -> Option<Bindings> {
+    let mut bindings: Bindings = Vec::new();
+    self.match_from(signature, command, &mut bindings, &mut Vec::new()).then_some(bindings)
+  }
+
+  /// Whether `value` is of `kind`. Literals are strings and numbers are
+  /// numbers. Anything else is what a whatis statement describes it as,
+  /// either directly or through the descriptions of its descriptions.
+  pub fn is_kind(&self, value: &[CommandComponent], kind: &[CommandComponent]) -> bool {
+    self.is_kind_in(value, kind, &mut Vec::new())
+  }
+
+  /// `visiting` holds the whatis statements already being followed, so
+  /// that descriptions that refer back to each other don't loop forever.
+  fn match_from(
+    &self,
+    signature: &[CommandComponent],
+    command: &[CommandComponent],
+    bindings: &mut Bindings,
+    visiting: &mut Vec<usize>
+  ) -> bool {
+    fill_slots(signature, command, &self.lexicon, bindings, &mut |slot, value| self.fits(slot, value, visiting))
+  }
+
+  fn fits(&self, slot: &Slot, value: &[CommandComponent], visiting: &mut Vec<usize>) -> bool {
+    match &slot.kind {
+      Some(kind) => self.is_kind_in(value, kind, visiting),
+      None => true
+    }
+  }
+
+  fn is_kind_in(&self, value: &[CommandComponent], kind: &[CommandComponent], visiting: &mut Vec<usize>) -> bool {
+    // The kind is an optional article and a single word, so the last
+    // component is the one that names it.
+    let noun: Vec<&str> = match kind.last().and_then(|noun| words(std::slice::from_ref(noun))) {
+      Some(noun) => noun,
+      None => return false
+    };
+
+    match value {
+      [CommandComponent::Literal(_, _)] if noun == ["string"] => return true,
+      [CommandComponent::Number(_, _)] if noun == ["number"] => return true,
+      _ => {}
+    }
+
+    for (i, whatis) in self.whatis.iter().enumerate() {
+      if visiting.contains(&i) {
+        continue;
+      }
+
+      visiting.push(i);
+
+      let found: bool = self.match_from(&whatis.signature, value, &mut Vec::new(), visiting)
+        && whatis.body.iter().any(|description| {
+          words(&description.command).is_some_and(|words| words.ends_with(&noun))
+            || self.is_kind_in(&description.command, kind, visiting)
+        });
+
+      visiting.pop();
+
+      if found {
+        return true;
+      }
+    }
+
+    false
+  }
+}
+
+
+/// The words of a phrase, with literals split on whitespace. Phrases
+/// with numbers or slots in them aren't made of words.
+fn words(components: &[CommandComponent]) -> Option<Vec<&str>> {
+  let mut words: Vec<&str> = Vec::new();
+
+  for component in components.iter() {
+    match component {
+      CommandComponent::Keyword(word, _) => words.push(word),
+      CommandComponent::Literal(literal, _) => words.extend(literal.split_whitespace()),
+      _ => return None
+    }
+  }
+
+  Some(words)
+}
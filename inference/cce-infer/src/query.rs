@@ -0,0 +1,171 @@
+/*
+
+Copyright (C) 2023 Carlos Kieliszewski
+
+This file is part of the Circe Project.
+
+Circe is free software: you can redistribute it and/or modify it under
+the terms of the GNU General Public License as published by the Free
+Software Foundation, either version 3 of the License, or (at your option)
+any later version.
+
+Circe is distributed in the hope that it will be useful, but WITHOUT ANY
+WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+FOR A PARTICULAR PURPOSE. See the GNU General Public License for more details.
+
+You should have received a copy of the GNU General Public License along with
+Circe. If not, see <https://www.gnu.org/licenses/>. 
+
+*/
+
+
+use cce_infer_ast::{
+  CommandComponent, HowToCommand, HowToNode, QueryKind, QueryNode, WhatIsNode
+};
+use cce_stream::{FileId, Span};
+
+use crate::matcher::{Bindings, Matcher};
+
+
+/// What the engine knows about the subject of a query, and in turn about
+/// everything that describes it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Answer<'p> {
+  /// The command or phrase that was looked up, with any slots filled in.
+  pub subject: Vec<CommandComponent>,
+  pub found: Found<'p>
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Found<'p> {
+  /// Nothing describes the subject.
+  Nothing,
+  /// The subject leads back to a description already being followed.
+  Recursive(Span),
+  /// The howto that carries out a command, what its slots were filled
+  /// with, and the answers for each of the commands among its steps.
+  HowTo {
+    howto: &'p HowToNode,
+    bindings: Bindings,
+    steps: Vec<Answer<'p>>
+  },
+  /// Each whatis statement describing a phrase, with the answers for
+  /// each of its descriptions.
+  WhatIs(Vec<(&'p WhatIsNode, Vec<Answer<'p>>)>)
+}
+
+impl<'p> Matcher<'p> {
+  /// The whatis statements whose signature `phrase` matches, out of the
+  /// ones a command in `from` can see.
+  pub fn find_whatis_from(&self, phrase: &[CommandComponent], from: FileId) -> Vec<(&'p WhatIsNode, Bindings)> {
+    self.whatis.iter()
+      .filter(|whatis| self.namespaces.can_see(from, whatis.visibility, whatis.span))
+      .filter_map(|whatis| self.match_signature(&whatis.signature, phrase).map(|bindings| (*whatis, bindings)))
+      .collect()
+  }
+
+  /// Follows a query through the descriptions of the program: a howto
+  /// query to the howto that carries the command out and on through the
+  /// commands of its steps, and a whatis query through the whatis
+  /// statements describing the phrase and what they describe it as.
+  pub fn answer(&self, query: &QueryNode) -> Answer<'p> {
+    let mut chain: Vec<Span> = Vec::new();
+
+    match query.kind {
+      QueryKind::HowTo => self.answer_howto(&query.subject, query.span.file, None, &mut chain),
+      QueryKind::WhatIs => self.answer_whatis(&query.subject, query.span.file, &mut chain)
+    }
+  }
+
+  /// `chain` holds the descriptions being followed, so that ones which
+  /// refer back to each other don't loop forever.
+  fn answer_howto(&self, command: &[CommandComponent], from: FileId, within: Option<&str>, chain: &mut Vec<Span>) -> Answer<'p> {
+    let found: Found<'p> = match self.find_howto_from(command, from, within) {
+      None => Found::Nothing,
+      Some((howto, _)) if chain.contains(&howto.span) => Found::Recursive(howto.span),
+      Some((howto, bindings)) => {
+        chain.push(howto.span);
+
+        let mut steps: Vec<Answer<'p>> = Vec::new();
+        self.answer_steps(&howto.body, &bindings, howto.span.file, chain, &mut steps);
+
+        chain.pop();
+        Found::HowTo { howto, bindings, steps }
+      }
+    };
+
+    Answer { subject: command.to_vec(), found }
+  }
+
+  fn answer_steps(
+    &self,
+    steps: &[HowToCommand],
+    bindings: &Bindings,
+    from: FileId,
+    chain: &mut Vec<Span>,
+    answers: &mut Vec<Answer<'p>>
+  ) {
+    for step in steps.iter() {
+      match step {
+        HowToCommand::HighLevel(command) => {
+          answers.push(self.answer_howto(&substitute(&command.command, bindings), from, None, chain));
+        },
+        HowToCommand::Binding(binding) => {
+          answers.push(self.answer_howto(&substitute(&binding.command.command, bindings), from, None, chain));
+        },
+        HowToCommand::Qualified(qualified) => {
+          let command: Vec<CommandComponent> = substitute(&qualified.command.command, bindings);
+          answers.push(self.answer_howto(&command, from, Some(&qualified.module), chain));
+        },
+        HowToCommand::Conditional(conditional) => {
+          self.answer_steps(&conditional.then, bindings, from, chain, answers);
+          self.answer_steps(&conditional.otherwise, bindings, from, chain, answers);
+        },
+        HowToCommand::Loop(repeat) => self.answer_steps(&repeat.body, bindings, from, chain, answers),
+        HowToCommand::LowLevel(_, _) | HowToCommand::Primitive(_, _) => {}
+      }
+    }
+  }
+
+  fn answer_whatis(&self, phrase: &[CommandComponent], from: FileId, chain: &mut Vec<Span>) -> Answer<'p> {
+    let matches: Vec<(&'p WhatIsNode, Bindings)> = self.find_whatis_from(phrase, from);
+
+    let recursive: Option<Span> = matches.iter()
+      .map(|(whatis, _)| whatis.span)
+      .find(|span| chain.contains(span));
+
+    let found: Found<'p> = if matches.is_empty() {
+      Found::Nothing
+    } else if let Some(span) = recursive {
+      Found::Recursive(span)
+    } else {
+      let described = matches.into_iter().map(|(whatis, bindings)| {
+        chain.push(whatis.span);
+
+        let descriptions: Vec<Answer<'p>> = whatis.body.iter().map(|description| {
+          self.answer_whatis(&substitute(&description.command, &bindings), whatis.span.file, chain)
+        }).collect();
+
+        chain.pop();
+        (whatis, descriptions)
+      }).collect();
+
+      Found::WhatIs(described)
+    };
+
+    Answer { subject: phrase.to_vec(), found }
+  }
+}
+
+
+/// Replaces the slots of `components` with what they were filled with.
+/// Slots that weren't filled are kept.
+fn substitute(components: &[CommandComponent], bindings: &Bindings) -> Vec<CommandComponent> {
+  components.iter().flat_map(|component| match component {
+    CommandComponent::Slot(slot, _) => match bindings.iter().find(|(name, _)| *name == slot.name) {
+      Some((_, value)) => value.clone(),
+      None => vec![component.clone()]
+    },
+    _ => vec![component.clone()]
+  }).collect()
+}
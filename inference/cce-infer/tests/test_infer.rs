@@ -27,11 +27,7 @@ use cce_ast as ast;
 #[test]
 fn test_infer_basic() {
   let mut parser: ast::Parser = ast::Parser::from("print 'Hello, world!' to the console.");
-
-  let mut parse_nodes: Vec<ast::ParseNode> = Vec::new();
-  while let Some(node) = parser.next().unwrap() {
-    parse_nodes.push(node);
-  }
+  let parse_nodes: Vec<ast::ParseNode> = parser.parse_all().unwrap().nodes;
 
   let infer_ast: Vec<ProgramNode> = convert(parse_nodes);
 
@@ -0,0 +1,180 @@
+/*
+
+Copyright (C) 2023 Carlos Kieliszewski
+
+This file is part of the Circe Project.
+
+Circe is free software: you can redistribute it and/or modify it under
+the terms of the GNU General Public License as published by the Free
+Software Foundation, either version 3 of the License, or (at your option)
+any later version.
+
+Circe is distributed in the hope that it will be useful, but WITHOUT ANY
+WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+FOR A PARTICULAR PURPOSE. See the GNU General Public License for more details.
+
+You should have received a copy of the GNU General Public License along with
+Circe. If not, see <https://www.gnu.org/licenses/>. 
+
+*/
+
+
+use cce_infer::Matcher;
+use cce_infer_ast::{convert, CommandComponent, ProgramNode};
+use cce_ast::Lexicon;
+use cce_ast as ast;
+use cce_stream::{FileId, InputStream};
+
+
+fn program(source: &str) -> Vec<ProgramNode> {
+  let mut parser: ast::Parser = ast::Parser::from(source);
+  convert(parser.parse_all().unwrap().nodes)
+}
+
+fn command(source: &str) -> Vec<CommandComponent> {
+  match program(source).remove(0) {
+    ProgramNode::Command(command) => command.command,
+    other => panic!("Expected a command, got {:?}", other)
+  }
+}
+
+fn names(bindings: &[(String, Vec<CommandComponent>)]) -> Vec<(&str, usize)> {
+  bindings.iter().map(|(name, value)| (name.as_str(), value.len())).collect()
+}
+
+const PRINTING: &str = "\
+howto print %n: a number to the console?
+- convert the number to text
+
+howto print %text: a string to the console?
+- write the string to stdout
+
+howto print %thing to %place?
+- send the thing
+";
+
+
+#[test]
+fn test_match_untyped() {
+  let nodes: Vec<ProgramNode> = program("howto greet %name loudly?\n- shout.");
+  let matcher: Matcher = Matcher::new(&nodes);
+
+  let (_, bindings) = matcher.find_howto(&command("greet the new user loudly.")).unwrap();
+  assert_eq!(names(&bindings), vec![("name", 3)]);
+
+  assert!(matcher.find_howto(&command("greet loudly.")).is_none());
+  assert!(matcher.find_howto(&command("greet the user quietly.")).is_none());
+}
+
+#[test]
+fn test_match_typed() {
+  let nodes: Vec<ProgramNode> = program(PRINTING);
+  let matcher: Matcher = Matcher::new(&nodes);
+
+  let (howto, bindings) = matcher.find_howto(&command("print 42 to the console.")).unwrap();
+  assert_eq!(howto.body.len(), 1);
+  assert_eq!(names(&bindings), vec![("n", 1)]);
+  assert!(matches!(howto.signature[1], CommandComponent::Slot(ref slot, _) if slot.name == "n"));
+
+  let (howto, _) = matcher.find_howto(&command("print 'hi' to the console.")).unwrap();
+  assert!(matches!(howto.signature[1], CommandComponent::Slot(ref slot, _) if slot.name == "text"));
+
+  let (howto, bindings) = matcher.find_howto(&command("print the time to the console.")).unwrap();
+  assert!(matches!(howto.signature[1], CommandComponent::Slot(ref slot, _) if slot.name == "thing"));
+  assert_eq!(names(&bindings), vec![("thing", 2), ("place", 2)]);
+}
+
+#[test]
+fn test_match_kind_from_whatis() {
+  let nodes: Vec<ProgramNode> = program("\
+howto close %f: a 'file stream'?
+- stop using it
+
+whatis stdout?
+- the standard output
+
+whatis the standard output?
+- a file stream
+
+whatis stdin?
+- where input comes from
+");
+  let matcher: Matcher = Matcher::new(&nodes);
+
+  assert!(matcher.find_howto(&command("close stdout.")).is_some());
+  assert!(matcher.find_howto(&command("close stdin.")).is_none());
+  assert!(matcher.find_howto(&command("close 'stdout'.")).is_none());
+}
+
+#[test]
+fn test_match_kind_cycle() {
+  let nodes: Vec<ProgramNode> = program("\
+whatis a foo?
+- a bar
+
+whatis a bar?
+- a foo
+");
+  let matcher: Matcher = Matcher::new(&nodes);
+
+  assert!(matcher.is_kind(&command("a foo."), &command("a bar.")));
+  assert!(!matcher.is_kind(&command("a foo."), &command("a number.")));
+}
+
+#[test]
+fn test_match_articles() {
+  let nodes: Vec<ProgramNode> = program("\
+howto write the string to a file?
+- open it
+
+whatis the console?
+- an output stream
+
+howto flush %s: a stream?
+- empty it
+");
+  let matcher: Matcher = Matcher::new(&nodes);
+
+  assert!(matcher.find_howto(&command("write a string to the file.")).is_some());
+  assert!(matcher.find_howto(&command("write an string to an file.")).is_some());
+  assert!(matcher.find_howto(&command("write string to file.")).is_none());
+  assert!(matcher.find_howto(&command("flush a console.")).is_some());
+
+  let matcher: Matcher = Matcher::with_lexicon(&nodes, Lexicon::new());
+  assert!(matcher.find_howto(&command("write the string to the file.")).is_none());
+  assert!(matcher.find_howto(&command("write the string to a file.")).is_some());
+}
+
+#[test]
+fn test_match_namespaces() {
+  let files: [&str; 3] = [
+    "module console.\nhowto print %s?\n- write %s to the screen.",
+    "module printer.\nhowto print %s?\n- send %s to the printer.\n\nprivate howto feed the paper?\n- roll.",
+    "private howto print %s?\n- say %s."
+  ];
+
+  let nodes: Vec<ProgramNode> = files.iter().enumerate().flat_map(|(file, source)| {
+    let lexer: ast::Lexer = ast::Lexer::new(InputStream::with_file(source, FileId(file)));
+    convert(ast::Parser::new(lexer).parse_all().unwrap().nodes)
+  }).collect();
+
+  let matcher: Matcher = Matcher::new(&nodes);
+  let print: Vec<CommandComponent> = command("print 'hi'.");
+  let found = |from: usize, within: Option<&str>| {
+    matcher.find_howto_from(&print, FileId(from), within).map(|(howto, _)| howto.span.file)
+  };
+
+  // A file's own module comes first, then its private howtos are only
+  // seen from itself.
+  assert_eq!(found(0, None), Some(FileId(0)));
+  assert_eq!(found(1, None), Some(FileId(1)));
+  assert_eq!(found(2, None), Some(FileId(2)));
+  assert_eq!(found(2, Some("printer")), Some(FileId(1)));
+  assert_eq!(found(0, Some("printer")), Some(FileId(1)));
+  assert_eq!(found(0, Some("io")), None);
+
+  let feed: Vec<CommandComponent> = command("feed the paper.");
+  assert!(matcher.find_howto_from(&feed, FileId(1), None).is_some());
+  assert!(matcher.find_howto_from(&feed, FileId(0), None).is_none());
+  assert!(matcher.find_howto_from(&feed, FileId(0), Some("printer")).is_none());
+}
@@ -0,0 +1,151 @@
+/*
+
+Copyright (C) 2023 Carlos Kieliszewski
+
+This file is part of the Circe Project.
+
+Circe is free software: you can redistribute it and/or modify it under
+the terms of the GNU General Public License as published by the Free
+Software Foundation, either version 3 of the License, or (at your option)
+any later version.
+
+Circe is distributed in the hope that it will be useful, but WITHOUT ANY
+WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+FOR A PARTICULAR PURPOSE. See the GNU General Public License for more details.
+
+You should have received a copy of the GNU General Public License along with
+Circe. If not, see <https://www.gnu.org/licenses/>. 
+
+*/
+
+
+use cce_infer::{Answer, Found, Matcher};
+use cce_infer_ast::{convert, phrase, ProgramNode, QueryNode};
+use cce_ast as ast;
+use cce_stream::{FileId, InputStream};
+
+
+/// Parses each source as a file of its own, in order.
+fn program(sources: &[&str]) -> Vec<ProgramNode> {
+  sources.iter().enumerate().flat_map(|(file, source)| {
+    let lexer: ast::Lexer = ast::Lexer::new(InputStream::with_file(source, FileId(file)));
+    convert(ast::Parser::new(lexer).parse_all().unwrap().nodes)
+  }).collect()
+}
+
+fn queries(nodes: &[ProgramNode]) -> Vec<&QueryNode> {
+  nodes.iter().filter_map(|node| match node {
+    ProgramNode::Query(query) => Some(query),
+    _ => None
+  }).collect()
+}
+
+/// The answer as lines of the subjects and signatures it went through,
+/// indented by how deep they are.
+fn outline(answer: &Answer, depth: usize, lines: &mut Vec<String>) {
+  let indent: String = "  ".repeat(depth);
+  lines.push(format!("{}{}", indent, phrase(&answer.subject)));
+
+  match &answer.found {
+    Found::Nothing => lines.push(format!("{}  ?", indent)),
+    Found::Recursive(_) => lines.push(format!("{}  ...", indent)),
+    Found::HowTo { howto, steps, .. } => {
+      lines.push(format!("{}  howto {}", indent, phrase(&howto.signature)));
+
+      for step in steps.iter() {
+        outline(step, depth + 2, lines);
+      }
+    },
+    Found::WhatIs(described) => {
+      for (whatis, descriptions) in described.iter() {
+        lines.push(format!("{}  whatis {}", indent, phrase(&whatis.signature)));
+
+        for description in descriptions.iter() {
+          outline(description, depth + 2, lines);
+        }
+      }
+    }
+  }
+}
+
+fn answers(sources: &[&str]) -> Vec<Vec<String>> {
+  let nodes: Vec<ProgramNode> = program(sources);
+  let matcher: Matcher = Matcher::new(&nodes);
+
+  queries(&nodes).into_iter().map(|query| {
+    let mut lines: Vec<String> = Vec::new();
+    outline(&matcher.answer(query), 0, &mut lines);
+    lines
+  }).collect()
+}
+
+
+#[test]
+fn test_query_howto() {
+  let found = answers(&["\
+howto greet %name?
+- if %name is empty, greet 'stranger'
+- print %name to the console
+
+howto print %text: a string to the console?
+- write %text to stdout
+
+? howto greet 'Ann'
+? howto fly"]);
+
+  assert_eq!(found, vec![
+    vec![
+      "greet 'Ann'",
+      "  howto greet %name",
+      "    greet 'stranger'",
+      "      ...",
+      "    print 'Ann' to the console",
+      "      howto print %text: a string to the console",
+      "        write 'Ann' to stdout",
+      "          ?"
+    ],
+    vec![
+      "fly",
+      "  ?"
+    ]
+  ]);
+}
+
+#[test]
+fn test_query_whatis() {
+  let found = answers(&["\
+whatis stdout?
+- a file stream
+- the console
+
+whatis a file stream?
+- a file
+
+? whatis stdout."]);
+
+  assert_eq!(found, vec![
+    vec![
+      "stdout",
+      "  whatis stdout",
+      "    a file stream",
+      "      whatis a file stream",
+      "        a file",
+      "          ?",
+      "    the console",
+      "      ?"
+    ]
+  ]);
+}
+
+#[test]
+fn test_query_visibility() {
+  let found = answers(&[
+    "module console.\nprivate howto clear?\n- wipe the screen.\n\n? howto clear",
+    "? howto clear"
+  ]);
+
+  assert_eq!(found, vec![
+    vec!["clear", "  howto clear", "    wipe the screen", "      ?"],
+    vec!["clear", "  ?"]
+  ]);
+}
@@ -18,7 +18,9 @@ Circe. If not, see <https://www.gnu.org/licenses/>.
 
 */
 
+use crate::LLError;
 use circelang_hash::CirceHash;
+use syn::spanned::Spanned;
 
 /****************************************
 * LLTopStatement
@@ -29,12 +31,35 @@ pub enum LLTopStatement {
   LLStruct(LLStruct)
 }
 
-impl From<syn::Item> for LLTopStatement {
-  fn from(item: syn::Item) -> Self {
+fn item_kind(item: &syn::Item) -> &'static str {
+  match item {
+    syn::Item::Const(_) => "const",
+    syn::Item::Enum(_) => "enum",
+    syn::Item::ExternCrate(_) => "extern crate",
+    syn::Item::Fn(_) => "fn",
+    syn::Item::ForeignMod(_) => "extern block",
+    syn::Item::Impl(_) => "impl",
+    syn::Item::Macro(_) => "macro invocation",
+    syn::Item::Mod(_) => "mod",
+    syn::Item::Static(_) => "static",
+    syn::Item::Struct(_) => "struct",
+    syn::Item::Trait(_) => "trait",
+    syn::Item::TraitAlias(_) => "trait alias",
+    syn::Item::Type(_) => "type alias",
+    syn::Item::Union(_) => "union",
+    syn::Item::Use(_) => "use",
+    _ => "item"
+  }
+}
+
+impl TryFrom<syn::Item> for LLTopStatement {
+  type Error = LLError;
+
+  fn try_from(item: syn::Item) -> Result<Self, LLError> {
     match item {
-      syn::Item::Fn(f) => LLTopStatement::LLFunction(LLFunction::from(f)),
-      syn::Item::Struct(s) => LLTopStatement::LLStruct(LLStruct::from(s)),
-      _ => panic!("Not implemented")
+      syn::Item::Fn(f) => Ok(LLTopStatement::LLFunction(LLFunction::try_from(f)?)),
+      syn::Item::Struct(s) => Ok(LLTopStatement::LLStruct(LLStruct::try_from(s)?)),
+      _ => Err(LLError::UnsupportedItem { kind: item_kind(&item), span: item.span() })
     }
   }
 }
@@ -49,13 +74,15 @@ pub struct LLFunction {
   pub ret: LLType
 }
 
-impl From<syn::ItemFn> for LLFunction {
-  fn from(item: syn::ItemFn) -> Self {
+impl TryFrom<syn::ItemFn> for LLFunction {
+  type Error = LLError;
+
+  fn try_from(item: syn::ItemFn) -> Result<Self, LLError> {
     let name = item.sig.ident.to_string();
-    let args = item.sig.inputs.iter().map(LLArgument::from).collect();
-    let ret = LLType::from(&item.sig.output);
+    let args = item.sig.inputs.iter().map(LLArgument::try_from).collect::<Result<Vec<LLArgument>, LLError>>()?;
+    let ret = LLType::try_from(&item.sig.output)?;
 
-    LLFunction { name, args, ret }
+    Ok(LLFunction { name, args, ret })
   }
 }
 
@@ -68,16 +95,23 @@ pub struct LLStruct {
   pub fields: Vec<LLStructField>
 }
 
-impl From<syn::ItemStruct> for LLStruct {
-  fn from(item: syn::ItemStruct) -> Self {
+impl TryFrom<syn::ItemStruct> for LLStruct {
+  type Error = LLError;
+
+  fn try_from(item: syn::ItemStruct) -> Result<Self, LLError> {
     let name = item.ident.to_string();
-    let fields: Vec<LLStructField> = item.fields.iter().map(LLStructField::from).collect();
+
+    if let syn::Fields::Unnamed(_) = item.fields {
+      return Err(LLError::TupleStruct { name, span: item.span() });
+    }
+
+    let fields = item.fields.iter().map(LLStructField::try_from).collect::<Result<Vec<LLStructField>, LLError>>()?;
 
     if fields.is_empty() {
-      panic!("Structs must have at least one field");
+      return Err(LLError::EmptyStruct { name, span: item.span() });
     }
 
-    LLStruct { name, fields }
+    Ok(LLStruct { name, fields })
   }
 }
 
@@ -90,20 +124,22 @@ pub struct LLArgument {
   pub ty: LLType
 }
 
-impl From<&syn::FnArg> for LLArgument {
-  fn from(arg: &syn::FnArg) -> Self {
+impl TryFrom<&syn::FnArg> for LLArgument {
+  type Error = LLError;
+
+  fn try_from(arg: &syn::FnArg) -> Result<Self, LLError> {
     match arg {
       syn::FnArg::Typed(t) => {
         let name = match &*t.pat {
           syn::Pat::Ident(i) => i.ident.to_string(),
-          _ => panic!("Not implemented")
+          pat => return Err(LLError::UnsupportedPattern { span: pat.span() })
         };
 
-        let ty = LLType::from(t.ty.as_ref());
+        let ty = LLType::try_from(t.ty.as_ref())?;
 
-        LLArgument { name, ty }
+        Ok(LLArgument { name, ty })
       },
-      _ => panic!("Not implemented")
+      syn::FnArg::Receiver(r) => Err(LLError::UnsupportedPattern { span: r.span() })
     }
   }
 }
@@ -116,24 +152,28 @@ pub struct LLType {
   pub name: String
 }
 
-impl From<&syn::ReturnType> for LLType {
-  fn from(ret: &syn::ReturnType) -> Self {
+impl TryFrom<&syn::ReturnType> for LLType {
+  type Error = LLError;
+
+  fn try_from(ret: &syn::ReturnType) -> Result<Self, LLError> {
     match ret {
-      syn::ReturnType::Default => LLType { name: "void".to_string() },
-      syn::ReturnType::Type(_, t) => LLType::from(t.as_ref())
+      syn::ReturnType::Default => Ok(LLType { name: "void".to_string() }),
+      syn::ReturnType::Type(_, t) => LLType::try_from(t.as_ref())
     }
   }
 }
 
-impl From<&syn::Type> for LLType {
-  fn from(ty: &syn::Type) -> Self {
+impl TryFrom<&syn::Type> for LLType {
+  type Error = LLError;
+
+  fn try_from(ty: &syn::Type) -> Result<Self, LLError> {
     match ty {
       syn::Type::Path(p) => {
         let name = p.path.segments.iter().map(|seg| seg.ident.to_string()).collect::<Vec<String>>().join("::");
 
-        LLType { name }
+        Ok(LLType { name })
       },
-      _ => panic!("Not implemented")
+      _ => Err(LLError::UnsupportedType { span: ty.span() })
     }
   }
 }
@@ -147,11 +187,16 @@ pub struct LLStructField {
   pub ty: LLType
 }
 
-impl From<&syn::Field> for LLStructField {
-  fn from(field: &syn::Field) -> Self {
-    let name = field.ident.as_ref().unwrap().to_string();
-    let ty = LLType::from(&field.ty);
+impl TryFrom<&syn::Field> for LLStructField {
+  type Error = LLError;
+
+  fn try_from(field: &syn::Field) -> Result<Self, LLError> {
+    let name = match &field.ident {
+      Some(ident) => ident.to_string(),
+      None => return Err(LLError::UnsupportedPattern { span: field.span() })
+    };
+    let ty = LLType::try_from(&field.ty)?;
 
-    LLStructField { name, ty }
+    Ok(LLStructField { name, ty })
   }
-}
\ No newline at end of file
+}
@@ -21,14 +21,58 @@ Circe. If not, see <https://www.gnu.org/licenses/>.
 
 pub mod ast;
 
-pub fn parse(input: &str) -> syn::Result<Vec<ast::LLTopStatement>> {
+use thiserror::Error;
+
+#[derive(Error, Debug, Clone)]
+pub enum LLError {
+  #[error("{0}")]
+  Syntax(#[from] syn::Error),
+  #[error("Unsupported item: {kind}")]
+  UnsupportedItem {
+    kind: &'static str,
+    span: proc_macro2::Span
+  },
+  #[error("Unsupported pattern, expected a plain name")]
+  UnsupportedPattern {
+    span: proc_macro2::Span
+  },
+  #[error("Unsupported type")]
+  UnsupportedType {
+    span: proc_macro2::Span
+  },
+  #[error("Struct '{name}' must have at least one field")]
+  EmptyStruct {
+    name: String,
+    span: proc_macro2::Span
+  },
+  #[error("Tuple struct '{name}' is not supported")]
+  TupleStruct {
+    name: String,
+    span: proc_macro2::Span
+  }
+}
+
+impl LLError {
+  pub fn span(&self) -> proc_macro2::Span {
+    match self {
+      LLError::Syntax(err) => err.span(),
+      LLError::UnsupportedItem { span, .. } => *span,
+      LLError::UnsupportedPattern { span } => *span,
+      LLError::UnsupportedType { span } => *span,
+      LLError::EmptyStruct { span, .. } => *span,
+      LLError::TupleStruct { span, .. } => *span
+    }
+  }
+}
+
+pub fn parse(input: &str) -> Result<Vec<ast::LLTopStatement>, LLError> {
   let file = syn::parse_file(input)?;
 
   let mut result = Vec::new();
 
   for item in file.items {
-    result.push(ast::LLTopStatement::from(item));
+    result.push(ast::LLTopStatement::try_from(item)?);
   };
 
   Ok(result)
-}
\ No newline at end of file
+}
@@ -18,7 +18,7 @@ Circe. If not, see <https://www.gnu.org/licenses/>.
 
 */
 
-use cce_llast::{parse, ast::*};
+use cce_llast::{parse, ast::*, LLError};
 
 
 #[test]
@@ -74,4 +74,66 @@ fn test_fn_args() {
       }
     })
   ])
-}
\ No newline at end of file
+}
+
+#[test]
+fn test_fn_unsupported_type() {
+  let input = r#"
+    fn foo(bar: &u32) -> u32 {
+      42
+    }
+  "#;
+
+  match parse(input) {
+    Err(err @ LLError::UnsupportedType { .. }) => {
+      assert_eq!(err.span().start().line, 2);
+      assert_eq!(err.span().start().column, 16);
+    },
+    other => panic!("Expected an UnsupportedType error, got {:?}", other)
+  }
+}
+
+#[test]
+fn test_fn_unsupported_pattern() {
+  let input = r#"
+    fn foo((bar, baz): (u32, u32)) -> u32 {
+      42
+    }
+  "#;
+
+  assert!(matches!(parse(input), Err(LLError::UnsupportedPattern { .. })));
+}
+
+#[test]
+fn test_fn_unsupported_receiver() {
+  let input = r#"
+    fn foo(self) -> u32 {
+      42
+    }
+  "#;
+
+  assert!(matches!(parse(input), Err(LLError::UnsupportedPattern { .. })));
+}
+
+#[test]
+fn test_unsupported_item() {
+  let input = r#"
+    enum Foo {
+      Bar
+    }
+  "#;
+
+  match parse(input) {
+    Err(LLError::UnsupportedItem { kind, .. }) => assert_eq!(kind, "enum"),
+    other => panic!("Expected an UnsupportedItem error, got {:?}", other)
+  }
+}
+
+#[test]
+fn test_syntax_error() {
+  let input = r#"
+    fn foo( -> u32 {}
+  "#;
+
+  assert!(matches!(parse(input), Err(LLError::Syntax(_))));
+}
@@ -18,7 +18,7 @@ Circe. If not, see <https://www.gnu.org/licenses/>.
 
 */
 
-use cce_llast::{parse, ast::*};
+use cce_llast::{parse, ast::*, LLError};
 
 
 #[test]
@@ -64,4 +64,40 @@ fn test_struct_no_name() {
   "#;
 
   parse(input).unwrap();
-}
\ No newline at end of file
+}
+
+#[test]
+fn test_struct_empty_error() {
+  let input = r#"
+    struct Foo {}
+  "#;
+
+  match parse(input) {
+    Err(LLError::EmptyStruct { name, .. }) => assert_eq!(name, "Foo"),
+    other => panic!("Expected an EmptyStruct error, got {:?}", other)
+  }
+}
+
+#[test]
+fn test_struct_unit_error() {
+  let input = r#"
+    struct Foo;
+  "#;
+
+  assert!(matches!(parse(input), Err(LLError::EmptyStruct { .. })));
+}
+
+#[test]
+fn test_struct_tuple_error() {
+  let input = r#"
+    struct Foo(u32, u64);
+  "#;
+
+  match parse(input) {
+    Err(err @ LLError::TupleStruct { .. }) => {
+      assert_eq!(err.span().start().line, 2);
+      assert_eq!(err.span().start().column, 4);
+    },
+    other => panic!("Expected a TupleStruct error, got {:?}", other)
+  }
+}